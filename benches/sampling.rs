@@ -0,0 +1,64 @@
+//! Compare the time each distribution takes to build and sample
+//!
+//! Run with `cargo bench --bench sampling`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use some_dice::outcome::Outcome;
+use some_dice::sample::{alias::AliasDistribution, distribution::OutputDistribution, rng};
+
+const BUILDS: u32 = 1_000;
+const SAMPLES: u32 = 1_000_000;
+
+/// A triangle of weights with the given number of faces, like the sum of two dice
+fn outcomes(faces: i32) -> Vec<Outcome> {
+    (0..faces)
+        .map(|value| Outcome {
+            value,
+            weight: (value.min(faces - 1 - value) + 1) as usize,
+        })
+        .collect()
+}
+
+fn time(iterations: u32, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+
+    start.elapsed() / iterations
+}
+
+fn main() {
+    println!(
+        "{:>6}  {:>12}  {:>12}  {:>12}  {:>12}",
+        "faces", "weighted new", "alias new", "weighted", "alias"
+    );
+
+    for faces in [6, 36, 600, 10_000] {
+        let weighted_new = time(BUILDS, || {
+            black_box(OutputDistribution::new(black_box(outcomes(faces))).unwrap());
+        });
+        let alias_new = time(BUILDS, || {
+            black_box(AliasDistribution::new(black_box(outcomes(faces))).unwrap());
+        });
+
+        let weighted = OutputDistribution::new(outcomes(faces)).unwrap();
+        let mut weighted_rng = rng::seeded(0);
+        let weighted_sample = time(SAMPLES, || {
+            black_box(weighted.sample(&mut weighted_rng));
+        });
+
+        let alias = AliasDistribution::new(outcomes(faces)).unwrap();
+        let mut alias_rng = rng::seeded(0);
+        let alias_sample = time(SAMPLES, || {
+            black_box(alias.sample(&mut alias_rng));
+        });
+
+        println!(
+            "{:>6}  {:>12?}  {:>12?}  {:>12?}  {:>12?}",
+            faces, weighted_new, alias_new, weighted_sample, alias_sample
+        );
+    }
+}
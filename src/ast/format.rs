@@ -0,0 +1,520 @@
+//! Writing AST nodes back out as AnyDice source
+//!
+//! Every node implements [`Display`][0] with a canonical form of the source:
+//!
+//! - Parentheses only where the grammar's precedence levels need them, so `(1 + 2) * 3` keeps them
+//!   and `(1 * 2) + 3` is written `1 * 2 + 3`
+//! - Single spaces around binary operators, except for dice which are written `3d6`
+//! - Sequences as `{1, 2:3, 4..6}`, leaving out repetitions of 1
+//! - One statement per line, with the bodies of blocks indented by four spaces
+//!
+//! Anything the parser accepts comes back as an equal tree when its formatted source is parsed.
+//! Nodes created without parsing can be written in ways the parser only reads back as equivalent:
+//! a negative integer is written as a negation, as the language has no negative literals. Anything
+//! the language can't write at all is left out rather than written in a way the parser rejects: the
+//! name of an output, and any `"` in a string, as strings have no escapes.
+//!
+//! [0]: std::fmt::Display
+
+use std::fmt;
+
+use super::{
+    BinaryOperator, Expression, FunctionParameter, FunctionParameterExpectedType, Program,
+    SequenceEntry, Statement, UnaryOperator,
+};
+
+const INDENT: &str = "    ";
+
+/// The loosest precedence level, which any expression can be written at without parentheses
+const LOOSEST: u8 = 8;
+
+/// The level of unary operators, which is also where negative integers are written
+const UNARY: u8 = 1;
+
+impl UnaryOperator {
+    /// The symbol written before the operand
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOperator::Negate => "-",
+            UnaryOperator::Not => "!",
+            UnaryOperator::Length => "#",
+        }
+    }
+}
+
+impl BinaryOperator {
+    /// The symbol written between the operands
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Exponent => "^",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::And => "&",
+            BinaryOperator::Or => "|",
+            BinaryOperator::Equal => "=",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::LessOrEqual => "<=",
+            BinaryOperator::GreaterOrEqual => ">=",
+            BinaryOperator::Dice => "d",
+            BinaryOperator::Access => "@",
+        }
+    }
+
+    /// The grammar's precedence level, where lower levels bind tighter
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Dice => 2,
+            BinaryOperator::Access => 3,
+            BinaryOperator::Exponent => 4,
+            BinaryOperator::Multiply | BinaryOperator::Divide => 5,
+            BinaryOperator::Add | BinaryOperator::Subtract => 6,
+            BinaryOperator::Equal
+            | BinaryOperator::NotEqual
+            | BinaryOperator::Less
+            | BinaryOperator::Greater
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::GreaterOrEqual => 7,
+            BinaryOperator::And | BinaryOperator::Or => LOOSEST,
+        }
+    }
+}
+
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+impl Expression {
+    fn precedence(&self) -> u8 {
+        match self {
+            // The smallest integer has no positive counterpart to negate, so it is a subtraction
+            Expression::Integer {
+                value: i32::MIN, ..
+            } => BinaryOperator::Subtract.precedence(),
+            Expression::Integer { value, .. } if *value < 0 => UNARY,
+            Expression::Integer { .. }
+            | Expression::VariableReference { .. }
+            | Expression::Sequence { .. }
+            | Expression::FunctionCall { .. } => 0,
+            Expression::UnaryOperation { .. } => UNARY,
+            Expression::BinaryOperation { operator, .. } => operator.precedence(),
+        }
+    }
+
+    /// Write the expression, in parentheses if it binds looser than the given level
+    fn write_at(&self, f: &mut fmt::Formatter, level: u8) -> fmt::Result {
+        if self.precedence() > level {
+            write!(f, "(")?;
+            self.write_at(f, LOOSEST)?;
+            return write!(f, ")");
+        }
+
+        match self {
+            Expression::Integer {
+                value: i32::MIN, ..
+            } => write!(f, "-{} - 1", i32::MAX),
+            Expression::Integer { value, .. } => write!(f, "{}", value),
+            Expression::VariableReference { name, .. } => write!(f, "{}", name),
+            Expression::Sequence { entries, .. } => {
+                write!(f, "{{")?;
+                for (index, entry) in entries.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", entry)?;
+                }
+                write!(f, "}}")
+            }
+            Expression::UnaryOperation {
+                operator, operand, ..
+            } => {
+                write!(f, "{}", operator)?;
+                operand.write_at(f, UNARY)
+            }
+            Expression::BinaryOperation {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                // Every level is left associative, so only the right needs to bind tighter
+                let level = operator.precedence();
+                left.write_at(f, level)?;
+                match operator {
+                    BinaryOperator::Dice => write!(f, "{}", operator)?,
+                    _ => write!(f, " {} ", operator)?,
+                }
+                right.write_at(f, level - 1)
+            }
+            Expression::FunctionCall {
+                name, arguments, ..
+            } => {
+                write!(f, "[")?;
+                let mut arguments = arguments.iter();
+                for (index, word) in name.split('?').enumerate() {
+                    if index > 0 {
+                        match arguments.next() {
+                            Some(argument) => write!(f, "{}", argument)?,
+                            None => write!(f, "?")?,
+                        }
+                    }
+                    write!(f, "{}", word)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_at(f, LOOSEST)
+    }
+}
+
+impl fmt::Display for SequenceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let repetitions = match self {
+            SequenceEntry::Single {
+                value, repetitions, ..
+            } => {
+                write!(f, "{}", value)?;
+                repetitions
+            }
+            SequenceEntry::Range {
+                start,
+                end,
+                repetitions,
+                ..
+            } => {
+                write!(f, "{}..{}", start, end)?;
+                repetitions
+            }
+        };
+
+        match repetitions {
+            Expression::Integer { value: 1, .. } => Ok(()),
+            repetitions => write!(f, ":{}", repetitions),
+        }
+    }
+}
+
+impl fmt::Display for FunctionParameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        match self.expected_type {
+            Some(FunctionParameterExpectedType::Dice) => write!(f, ":d"),
+            Some(FunctionParameterExpectedType::Number) => write!(f, ":n"),
+            Some(FunctionParameterExpectedType::Sequence) => write!(f, ":s"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Statement {
+    /// Write the statement with every line indented to the given depth
+    fn write_indented(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        write!(f, "{}", INDENT.repeat(depth))?;
+        match self {
+            Statement::VariableAssignment { name, value, .. } => write!(f, "{}: {}", name, value),
+            // The language has no syntax for naming an output, so a name is left out
+            Statement::Output { value, .. } => write!(f, "output {}", value),
+            Statement::ConfigureString { setting, value, .. } => {
+                write!(f, "set {} to {}", Quoted(setting), Quoted(value))
+            }
+            Statement::ConfigureExpression { setting, value, .. } => {
+                write!(f, "set {} to {}", Quoted(setting), value)
+            }
+            Statement::FunctionDefinition {
+                name, parameters, ..
+            } => {
+                write!(f, "function: ")?;
+                let mut parameters = parameters.iter();
+                for (index, word) in name.split('?').enumerate() {
+                    if index > 0 {
+                        match parameters.next() {
+                            Some(parameter) => write!(f, "{}", parameter)?,
+                            None => write!(f, "?")?,
+                        }
+                    }
+                    write!(f, "{}", word)?;
+                }
+                // Function bodies aren't part of the tree yet, so the block is always empty
+                write!(f, " {{\n{}}}", INDENT.repeat(depth))
+            }
+        }
+    }
+}
+
+/// A string between quotes, leaving out any quotes in it as a string ends at the first one
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"{}\"", self.0.replace('"', ""))
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_indented(f, 0)
+    }
+}
+
+impl fmt::Display for Program {
+    /// Each statement on its own line, ending with a newline
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for statement in &self.statements {
+            statement.write_indented(f, 0)?;
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::functional::*;
+    use super::*;
+
+    #[test]
+    fn test_symbols() {
+        assert_eq!(UnaryOperator::Length.to_string(), "#");
+        assert_eq!(BinaryOperator::GreaterOrEqual.to_string(), ">=");
+        assert_eq!(BinaryOperator::Dice.symbol(), "d");
+    }
+
+    #[test]
+    fn test_parentheses() {
+        let one = || Box::new(integer(1));
+        let two = || Box::new(integer(2));
+        let three = || Box::new(integer(3));
+
+        assert_eq!(
+            multiply(Box::new(add(one(), two())), three()).to_string(),
+            "(1 + 2) * 3"
+        );
+        assert_eq!(
+            add(Box::new(multiply(one(), two())), three()).to_string(),
+            "1 * 2 + 3"
+        );
+        assert_eq!(
+            subtract(Box::new(subtract(one(), two())), three()).to_string(),
+            "1 - 2 - 3"
+        );
+        assert_eq!(
+            subtract(one(), Box::new(subtract(two(), three()))).to_string(),
+            "1 - (2 - 3)"
+        );
+        assert_eq!(
+            access(one(), Box::new(dice(two(), three()))).to_string(),
+            "1 @ 2d3"
+        );
+        assert_eq!(negate(Box::new(dice(one(), two()))).to_string(), "-(1d2)");
+        assert_eq!(dice(Box::new(negate(one())), two()).to_string(), "-1d2");
+        assert_eq!(not(Box::new(negate(one()))).to_string(), "!-1");
+    }
+
+    #[test]
+    fn test_negative_integers() {
+        assert_eq!(integer(-3).to_string(), "-3");
+        assert_eq!(
+            dice(Box::new(integer(2)), Box::new(integer(-3))).to_string(),
+            "2d-3"
+        );
+        assert_eq!(length(Box::new(integer(-3))).to_string(), "#-3");
+        assert_eq!(integer(i32::MIN).to_string(), "-2147483647 - 1");
+        assert_eq!(
+            multiply(Box::new(integer(i32::MIN)), Box::new(integer(2))).to_string(),
+            "(-2147483647 - 1) * 2"
+        );
+    }
+
+    #[test]
+    fn test_sequences() {
+        assert_eq!(sequence(vec![]).to_string(), "{}");
+        assert_eq!(
+            sequence(vec![
+                single_entry(integer(1), integer(1)),
+                single_entry(add(Box::new(integer(1)), Box::new(integer(1))), integer(3)),
+                range_entry(integer(4), integer(6), integer(1)),
+                range_entry(integer(-1), integer(1), variable_reference("X".to_owned())),
+            ])
+            .to_string(),
+            "{1, 1 + 1:3, 4..6, -1..1:X}"
+        );
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(
+            program(vec![
+                variable_assignment(
+                    "X".to_owned(),
+                    dice(Box::new(integer(3)), Box::new(integer(6)))
+                ),
+                output(variable_reference("X".to_owned()), None),
+                output(integer(1), Some("one".to_owned())),
+                configure_string("position order".to_owned(), "lowest first".to_owned()),
+                configure_expression("explode depth".to_owned(), integer(3)),
+            ])
+            .to_string(),
+            "X: 3d6\n\
+             output X\n\
+             output 1\n\
+             set \"position order\" to \"lowest first\"\n\
+             set \"explode depth\" to 3\n"
+        );
+        assert_eq!(program(vec![]).to_string(), "");
+        assert_eq!(
+            configure_string("say \"hi\"".to_owned(), "\\o/".to_owned()).to_string(),
+            "set \"say hi\" to \"\\o/\""
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(
+            function_call(
+                "highest ? of ?".to_owned(),
+                vec![
+                    integer(1),
+                    dice(Box::new(integer(2)), Box::new(integer(20)))
+                ]
+            )
+            .to_string(),
+            "[highest 1 of 2d20]"
+        );
+        assert_eq!(
+            function_definition(
+                "add ? to ?".to_owned(),
+                vec![
+                    function_parameter("X".to_owned(), Some(FunctionParameterExpectedType::Number)),
+                    function_parameter("Y".to_owned(), None),
+                ]
+            )
+            .to_string(),
+            "function: add X:n to Y {\n}"
+        );
+    }
+
+    #[cfg(feature = "parse")]
+    #[test]
+    fn test_round_trip() {
+        use crate::parse::{parse_expression, parse_program};
+
+        let source = "\\ comments are dropped \\\n\
+                      set \"position order\" to \"lowest first\"\n\
+                      set \"explode depth\" to 1+1\n\
+                      X: ((3d6))\n\
+                      Y: {1..6:2, X, -1:1, {}}\n\
+                      output (1@X) + -#Y * (2 - 1) >= 3 & !(X = 2 | Y != 1)\n\
+                      output 2^(3^4) / (X < Y)";
+        let program = parse_program(source).unwrap();
+        let formatted = program.to_string();
+        assert_eq!(
+            formatted,
+            "set \"position order\" to \"lowest first\"\n\
+             set \"explode depth\" to 1 + 1\n\
+             X: 3d6\n\
+             Y: {1..6:2, X, -1, {}}\n\
+             output 1 @ X + -#Y * (2 - 1) >= 3 & !(X = 2 | Y != 1)\n\
+             output 2 ^ (3 ^ 4) / (X < Y)\n"
+        );
+        assert_eq!(parse_program(&formatted).unwrap(), program);
+
+        // Names and quotes are left out so that what is written can still be parsed
+        let named = crate::ast::functional::program(vec![
+            output(integer(1), Some("the \"best\" roll".to_owned())),
+            configure_string("a \"b\"".to_owned(), "c\\".to_owned()),
+        ]);
+        assert_eq!(
+            parse_program(&named.to_string()).unwrap(),
+            crate::ast::functional::program(vec![
+                output(integer(1), None),
+                configure_string("a b".to_owned(), "c\\".to_owned()),
+            ])
+        );
+
+        // Every pair of operators nested on either side
+        let unary = [
+            UnaryOperator::Negate,
+            UnaryOperator::Not,
+            UnaryOperator::Length,
+        ];
+        let binary = [
+            BinaryOperator::Exponent,
+            BinaryOperator::Multiply,
+            BinaryOperator::Divide,
+            BinaryOperator::Add,
+            BinaryOperator::Subtract,
+            BinaryOperator::And,
+            BinaryOperator::Or,
+            BinaryOperator::Equal,
+            BinaryOperator::NotEqual,
+            BinaryOperator::Less,
+            BinaryOperator::Greater,
+            BinaryOperator::LessOrEqual,
+            BinaryOperator::GreaterOrEqual,
+            BinaryOperator::Dice,
+            BinaryOperator::Access,
+        ];
+        let leaf = || Box::new(variable_reference("X".to_owned()));
+        let unary_operation = |operator, operand| Expression::UnaryOperation {
+            operator,
+            operand: Box::new(operand),
+            span: Default::default(),
+        };
+        let binary_operation = |operator, left, right| Expression::BinaryOperation {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+            span: Default::default(),
+        };
+
+        let mut expressions = vec![];
+        for outer in binary {
+            for inner in binary {
+                let nested = binary_operation(inner, *leaf(), *leaf());
+                expressions.push(binary_operation(outer, nested.clone(), *leaf()));
+                expressions.push(binary_operation(outer, *leaf(), nested));
+            }
+            for inner in unary {
+                let nested = unary_operation(inner, *leaf());
+                expressions.push(binary_operation(outer, nested.clone(), *leaf()));
+                expressions.push(binary_operation(outer, *leaf(), nested.clone()));
+                expressions.push(unary_operation(
+                    inner,
+                    binary_operation(outer, *leaf(), *leaf()),
+                ));
+            }
+        }
+        for outer in unary {
+            for inner in unary {
+                expressions.push(unary_operation(outer, unary_operation(inner, *leaf())));
+            }
+        }
+
+        for expression in expressions {
+            let formatted = expression.to_string();
+            assert_eq!(
+                parse_expression(&formatted).as_ref(),
+                Ok(&expression),
+                "{}",
+                formatted
+            );
+        }
+    }
+}
@@ -1,11 +1,13 @@
 //! Helper functions to create AST nodes.
 //!
-//! Each function creates a single AST node.
+//! Each function creates a single AST node, with a default [span][0] as it was not parsed.
+//! Use `with_span` on the node to set one.
 //!
 //! Due to the recursive nature of expressions,
 //! the output of expression functions must be boxed before being passed to another expression function.
 //!
 //! ```
+//! # use some_dice::ast::functional::*;
 //! // output 1d3 - 2 named "fudge die"
 //! // A program with one output: a die with options -1, 0, and 1 named fudge die
 //! program(vec![output(
@@ -14,8 +16,10 @@
 //!         Box::new(integer(2)),
 //!     ),
 //!     None,
-//!     )])
+//!     )]);
 //! ```
+//!
+//! [0]: ast::Span
 
 use crate::ast;
 
@@ -29,6 +33,7 @@ macro_rules! unary_operation {
             ast::Expression::UnaryOperation {
                 operator: $op,
                 operand,
+                span: ast::Span::default(),
             }
         }
     };
@@ -52,6 +57,7 @@ macro_rules! binary_operation {
                 operator: $op,
                 left,
                 right,
+                span: ast::Span::default(),
             }
         }
     };
@@ -77,21 +83,31 @@ binary_operation!(access, ast::BinaryOperator::Access);
 ///
 /// [integer expression]: ast::Expression::Integer
 pub fn integer(value: i32) -> ast::Expression {
-    ast::Expression::Integer { value }
+    ast::Expression::Integer {
+        value,
+        span: ast::Span::default(),
+    }
 }
 
 /// Create [variable reference]
 ///
 /// [variable reference]: ast::Expression::VariableReference
 pub fn variable_reference(name: String) -> ast::Expression {
-    ast::Expression::VariableReference { name }
+    ast::Expression::VariableReference {
+        name,
+        span: ast::Span::default(),
+    }
 }
 
 /// Create [single sequence entry]
 ///
 /// [single sequence entry]: ast::SequenceEntry::Single
 pub fn single_entry(value: ast::Expression, repetitions: ast::Expression) -> ast::SequenceEntry {
-    ast::SequenceEntry::Single { value, repetitions }
+    ast::SequenceEntry::Single {
+        value,
+        repetitions,
+        span: ast::Span::default(),
+    }
 }
 
 /// Create [range sequence entry]
@@ -106,6 +122,7 @@ pub fn range_entry(
         start,
         end,
         repetitions,
+        span: ast::Span::default(),
     }
 }
 
@@ -113,35 +130,65 @@ pub fn range_entry(
 ///
 /// [sequence expression]: ast::Expression::Sequence
 pub fn sequence(entries: Vec<ast::SequenceEntry>) -> ast::Expression {
-    ast::Expression::Sequence { entries }
+    ast::Expression::Sequence {
+        entries,
+        span: ast::Span::default(),
+    }
 }
 
 /// Create [function call expression]
 ///
 /// [function call expression]: ast::Expression::FunctionCall
 pub fn function_call(name: String, arguments: Vec<ast::Expression>) -> ast::Expression {
-    ast::Expression::FunctionCall { name, arguments }
+    ast::Expression::FunctionCall {
+        name,
+        arguments,
+        span: ast::Span::default(),
+    }
+}
+
+/// Create [variable assignment statement]
+///
+/// [variable assignment statement]: ast::Statement::VariableAssignment
+pub fn variable_assignment(name: String, value: ast::Expression) -> ast::Statement {
+    ast::Statement::VariableAssignment {
+        name,
+        value,
+        span: ast::Span::default(),
+    }
 }
 
 /// Create [output statement]
 ///
 /// [output statement]: ast::Statement::Output
 pub fn output(value: ast::Expression, name: Option<String>) -> ast::Statement {
-    ast::Statement::Output { value, name }
+    ast::Statement::Output {
+        value,
+        name,
+        span: ast::Span::default(),
+    }
 }
 
 /// Create [configure statement] with string value
 ///
 /// [configure statement]: ast::Statement::ConfigureString
 pub fn configure_string(setting: String, value: String) -> ast::Statement {
-    ast::Statement::ConfigureString { setting, value }
+    ast::Statement::ConfigureString {
+        setting,
+        value,
+        span: ast::Span::default(),
+    }
 }
 
 /// Create [configure statement] with expression value
 ///
 /// [configure statement]: ast::Statement::ConfigureExpression
 pub fn configure_expression(setting: String, value: ast::Expression) -> ast::Statement {
-    ast::Statement::ConfigureExpression { setting, value }
+    ast::Statement::ConfigureExpression {
+        setting,
+        value,
+        span: ast::Span::default(),
+    }
 }
 
 /// Create [function parameter]
@@ -154,6 +201,7 @@ pub fn function_parameter(
     ast::FunctionParameter {
         name,
         expected_type,
+        span: ast::Span::default(),
     }
 }
 
@@ -164,12 +212,19 @@ pub fn function_definition(
     name: String,
     parameters: Vec<ast::FunctionParameter>,
 ) -> ast::Statement {
-    ast::Statement::FunctionDefinition { name, parameters }
+    ast::Statement::FunctionDefinition {
+        name,
+        parameters,
+        span: ast::Span::default(),
+    }
 }
 
 /// Create [program]
 ///
 /// [program]: ast::Program
 pub fn program(statements: Vec<ast::Statement>) -> ast::Program {
-    ast::Program { statements }
+    ast::Program {
+        statements,
+        span: ast::Span::default(),
+    }
 }
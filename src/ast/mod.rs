@@ -2,11 +2,82 @@
 //!
 //! In general, they are the output of the parser and never handled directly.
 //! If creating without  parsing, the functions in [`functional`] offer a simpler interface.
+//!
+//! With the `serde` feature enabled, all nodes implement `Serialize` and `Deserialize`.
+//! The representation is considered stable:
+//!
+//! - Operators and parameter types are `snake_case` strings, e.g. `"greater_or_equal"`
+//! - Enums with fields are objects internally tagged by a `snake_case` `"type"`, e.g. `{"type": "integer", "value": 1}`
+//! - Structs are objects with their field names, e.g. `{"statements": [...]}`
+//! - Spans are objects with a `"start"` and `"end"`, and are left out when they are the default
+//!
+//! Every node has a [`Span`] of where it came from in the source.
+//! Spans are ignored when comparing nodes, so the same tree is equal wherever it was parsed from.
+//!
+//! Every node implements `Display` as canonical AnyDice source, which parses back to an equal tree.
+//!
+//! Passes over a whole tree can use the traits in [`visit`] instead of matching on every node.
 
+use std::ops;
+
+mod format;
 pub mod functional;
+pub mod visit;
+
+/// A range of bytes in the source that a node was parsed from, from `start` up to but not
+/// including `end`
+///
+/// Nodes that were not parsed, like those created with [`functional`], default to an empty span
+/// at the start of the source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The smallest span covering both spans
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// The span as a range, for slicing the source it came from
+    pub fn range(&self) -> ops::Range<usize> {
+        self.start..self.end
+    }
+}
+
+impl From<ops::Range<usize>> for Span {
+    fn from(range: ops::Range<usize>) -> Self {
+        Span::new(range.start, range.end)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn is_default_span(span: &Span) -> bool {
+    *span == Span::default()
+}
 
 /// Operators which have only one operand
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum UnaryOperator {
     /// Negation: `(-1) = -1`, `(--2) = 2`, `(-0) = 0`
     Negate,
@@ -18,6 +89,8 @@ pub enum UnaryOperator {
 
 /// Operators with a left and right operand
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum BinaryOperator {
     /// Exponentiation: `(2 ^ 3) = 8`
     Exponent,
@@ -78,12 +151,19 @@ pub enum BinaryOperator {
 /// it must evaluate to a single number or there will be a runtime error.
 ///
 /// </div>
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
 pub enum SequenceEntry {
     /// A single expression to include in a sequence and the number of times to repeat it
     Single {
         value: Expression,
         repetitions: Expression,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
     },
     /// A range between (inclusive) expressions to include in a sequence and the number of times to repeat it
     ///
@@ -97,32 +177,70 @@ pub enum SequenceEntry {
         start: Expression,
         end: Expression,
         repetitions: Expression,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
     },
 }
 
 /// An expression evaluates always to a single value
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
 pub enum Expression {
     /// An Integer literal
     ///
     /// Capped to i32 as the AnyDice reference server appears to use double-precisions floats.
     /// This means that a loss of precision for integers is possible starting at (positive or negative) `2^53`.
     /// Unfortunately i32 is much below that limit and i64 is much above, but this keeps things simple and will work for almost all common usage.
-    Integer { value: i32 },
+    Integer {
+        value: i32,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
+    },
     /// Name of variable to look up and substiture value of at runtime
-    VariableReference { name: String },
+    VariableReference {
+        name: String,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
+    },
     /// An ordered sequence of possible values
-    Sequence { entries: Vec<SequenceEntry> },
+    Sequence {
+        entries: Vec<SequenceEntry>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
+    },
     /// Operations with only one operand
     UnaryOperation {
         operator: UnaryOperator,
         operand: Box<Expression>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
     },
     /// Operations with a left and right operand
     BinaryOperation {
         operator: BinaryOperator,
         left: Box<Expression>,
         right: Box<Expression>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
     },
     /// Calls to a user-defined or built-in function
     ///
@@ -130,11 +248,18 @@ pub enum Expression {
     FunctionCall {
         name: String,
         arguments: Vec<Expression>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
     },
 }
 
 /// An expected type for a function parameter
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum FunctionParameterExpectedType {
     Dice,
     Number,
@@ -142,36 +267,536 @@ pub enum FunctionParameterExpectedType {
 }
 
 /// A named parameter for a function definition with an optional expected type
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionParameter {
     name: String,
     expected_type: Option<FunctionParameterExpectedType>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "is_default_span")
+    )]
+    span: Span,
 }
 
 /// A statement alters the state of the interpreter, but does not produce a value
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
 pub enum Statement {
+    /// Assign the value of an expression to a variable
+    VariableAssignment {
+        name: String,
+        value: Expression,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
+    },
     /// Output an expression with an optional name
     Output {
         value: Expression,
         name: Option<String>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
     },
     /// Configure the interpreter behavior with a string value
-    ConfigureString { setting: String, value: String },
+    ConfigureString {
+        setting: String,
+        value: String,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
+    },
     /// Configure the interpreter behavior with an expression value
-    ConfigureExpression { setting: String, value: Expression },
+    ConfigureExpression {
+        setting: String,
+        value: Expression,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
+    },
     /// Define a new or redefine an existing function
     ///
     /// Name should be normalized by replacing positional parameters with `?`.
     FunctionDefinition {
         name: String,
         parameters: Vec<FunctionParameter>,
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "is_default_span")
+        )]
+        span: Span,
     },
 }
 
 /// The root node of the AST, representing a full program to run
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Program {
     // A list of all statements to be run in order
     pub statements: Vec<Statement>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "is_default_span")
+    )]
+    pub span: Span,
+}
+
+impl SequenceEntry {
+    pub fn span(&self) -> Span {
+        match self {
+            SequenceEntry::Single { span, .. } | SequenceEntry::Range { span, .. } => *span,
+        }
+    }
+
+    /// Set where this entry was parsed from
+    pub fn with_span(mut self, span: Span) -> Self {
+        match &mut self {
+            SequenceEntry::Single { span: old, .. } | SequenceEntry::Range { span: old, .. } => {
+                *old = span
+            }
+        }
+        self
+    }
+}
+
+impl PartialEq for SequenceEntry {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                SequenceEntry::Single {
+                    value, repetitions, ..
+                },
+                SequenceEntry::Single {
+                    value: other_value,
+                    repetitions: other_repetitions,
+                    ..
+                },
+            ) => value == other_value && repetitions == other_repetitions,
+            (
+                SequenceEntry::Range {
+                    start,
+                    end,
+                    repetitions,
+                    ..
+                },
+                SequenceEntry::Range {
+                    start: other_start,
+                    end: other_end,
+                    repetitions: other_repetitions,
+                    ..
+                },
+            ) => start == other_start && end == other_end && repetitions == other_repetitions,
+            _ => false,
+        }
+    }
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Integer { span, .. }
+            | Expression::VariableReference { span, .. }
+            | Expression::Sequence { span, .. }
+            | Expression::UnaryOperation { span, .. }
+            | Expression::BinaryOperation { span, .. }
+            | Expression::FunctionCall { span, .. } => *span,
+        }
+    }
+
+    /// Set where this expression was parsed from
+    pub fn with_span(mut self, span: Span) -> Self {
+        match &mut self {
+            Expression::Integer { span: old, .. }
+            | Expression::VariableReference { span: old, .. }
+            | Expression::Sequence { span: old, .. }
+            | Expression::UnaryOperation { span: old, .. }
+            | Expression::BinaryOperation { span: old, .. }
+            | Expression::FunctionCall { span: old, .. } => *old = span,
+        }
+        self
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expression::Integer { value, .. }, Expression::Integer { value: other, .. }) => {
+                value == other
+            }
+            (
+                Expression::VariableReference { name, .. },
+                Expression::VariableReference { name: other, .. },
+            ) => name == other,
+            (Expression::Sequence { entries, .. }, Expression::Sequence { entries: other, .. }) => {
+                entries == other
+            }
+            (
+                Expression::UnaryOperation {
+                    operator, operand, ..
+                },
+                Expression::UnaryOperation {
+                    operator: other_operator,
+                    operand: other_operand,
+                    ..
+                },
+            ) => operator == other_operator && operand == other_operand,
+            (
+                Expression::BinaryOperation {
+                    operator,
+                    left,
+                    right,
+                    ..
+                },
+                Expression::BinaryOperation {
+                    operator: other_operator,
+                    left: other_left,
+                    right: other_right,
+                    ..
+                },
+            ) => operator == other_operator && left == other_left && right == other_right,
+            (
+                Expression::FunctionCall {
+                    name, arguments, ..
+                },
+                Expression::FunctionCall {
+                    name: other_name,
+                    arguments: other_arguments,
+                    ..
+                },
+            ) => name == other_name && arguments == other_arguments,
+            _ => false,
+        }
+    }
+}
+
+impl FunctionParameter {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Set where this parameter was parsed from
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for FunctionParameter {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.expected_type == other.expected_type
+    }
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::VariableAssignment { span, .. }
+            | Statement::Output { span, .. }
+            | Statement::ConfigureString { span, .. }
+            | Statement::ConfigureExpression { span, .. }
+            | Statement::FunctionDefinition { span, .. } => *span,
+        }
+    }
+
+    /// Set where this statement was parsed from
+    pub fn with_span(mut self, span: Span) -> Self {
+        match &mut self {
+            Statement::VariableAssignment { span: old, .. }
+            | Statement::Output { span: old, .. }
+            | Statement::ConfigureString { span: old, .. }
+            | Statement::ConfigureExpression { span: old, .. }
+            | Statement::FunctionDefinition { span: old, .. } => *old = span,
+        }
+        self
+    }
+}
+
+impl PartialEq for Statement {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Statement::VariableAssignment { name, value, .. },
+                Statement::VariableAssignment {
+                    name: other_name,
+                    value: other_value,
+                    ..
+                },
+            ) => name == other_name && value == other_value,
+            (
+                Statement::Output { value, name, .. },
+                Statement::Output {
+                    value: other_value,
+                    name: other_name,
+                    ..
+                },
+            ) => value == other_value && name == other_name,
+            (
+                Statement::ConfigureString { setting, value, .. },
+                Statement::ConfigureString {
+                    setting: other_setting,
+                    value: other_value,
+                    ..
+                },
+            ) => setting == other_setting && value == other_value,
+            (
+                Statement::ConfigureExpression { setting, value, .. },
+                Statement::ConfigureExpression {
+                    setting: other_setting,
+                    value: other_value,
+                    ..
+                },
+            ) => setting == other_setting && value == other_value,
+            (
+                Statement::FunctionDefinition {
+                    name, parameters, ..
+                },
+                Statement::FunctionDefinition {
+                    name: other_name,
+                    parameters: other_parameters,
+                    ..
+                },
+            ) => name == other_name && parameters == other_parameters,
+            _ => false,
+        }
+    }
+}
+
+impl Program {
+    /// Set where this program was parsed from
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+}
+
+impl PartialEq for Program {
+    fn eq(&self, other: &Self) -> bool {
+        self.statements == other.statements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::functional::*;
+    use super::*;
+
+    #[test]
+    fn test_span() {
+        let span = Span::new(2, 5);
+
+        assert_eq!(span.len(), 3);
+        assert!(!span.is_empty());
+        assert!(Span::default().is_empty());
+        assert_eq!(span.range(), 2..5);
+        assert_eq!(Span::from(2..5), span);
+        assert_eq!(span.to(Span::new(7, 9)), Span::new(2, 9));
+        assert_eq!(Span::new(7, 9).to(span), Span::new(2, 9));
+    }
+
+    #[test]
+    fn test_equality_ignores_spans() {
+        let spanned = add(
+            Box::new(integer(1).with_span(Span::new(0, 1))),
+            Box::new(integer(2).with_span(Span::new(4, 5))),
+        )
+        .with_span(Span::new(0, 5));
+
+        assert_eq!(spanned, add(Box::new(integer(1)), Box::new(integer(2))));
+        assert_eq!(spanned.span(), Span::new(0, 5));
+        assert_ne!(spanned, add(Box::new(integer(1)), Box::new(integer(3))));
+        assert_ne!(
+            spanned,
+            subtract(Box::new(integer(1)), Box::new(integer(2)))
+        );
+
+        assert_eq!(
+            output(integer(1), None).with_span(Span::new(0, 8)),
+            output(integer(1), None)
+        );
+        assert_ne!(
+            output(integer(1), None),
+            variable_assignment("X".to_owned(), integer(1))
+        );
+        assert_eq!(
+            function_parameter("X".to_owned(), None).with_span(Span::new(0, 1)),
+            function_parameter("X".to_owned(), None)
+        );
+        assert_eq!(
+            single_entry(integer(1), integer(1)).with_span(Span::new(1, 2)),
+            single_entry(integer(1), integer(1))
+        );
+        assert_eq!(program(vec![]).with_span(Span::new(0, 3)), program(vec![]));
+    }
+
+    #[cfg(feature = "serde")]
+    fn round_trip<T>(node: T)
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        let json = serde_json::to_string(&node).unwrap();
+        assert_eq!(serde_json::from_str::<T>(&json).unwrap(), node);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_representation() {
+        assert_eq!(
+            serde_json::to_value(program(vec![output(
+                less_or_equal(Box::new(integer(1)), Box::new(sequence(vec![]))),
+                Some("name".to_owned()),
+            )]))
+            .unwrap(),
+            serde_json::json!({
+                "statements": [{
+                    "type": "output",
+                    "value": {
+                        "type": "binary_operation",
+                        "operator": "less_or_equal",
+                        "left": {"type": "integer", "value": 1},
+                        "right": {"type": "sequence", "entries": []},
+                    },
+                    "name": "name",
+                }],
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip_unary_operators() {
+        for operator in [
+            UnaryOperator::Negate,
+            UnaryOperator::Not,
+            UnaryOperator::Length,
+        ] {
+            round_trip(operator);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip_binary_operators() {
+        for operator in [
+            BinaryOperator::Exponent,
+            BinaryOperator::Multiply,
+            BinaryOperator::Divide,
+            BinaryOperator::Add,
+            BinaryOperator::Subtract,
+            BinaryOperator::And,
+            BinaryOperator::Or,
+            BinaryOperator::Equal,
+            BinaryOperator::NotEqual,
+            BinaryOperator::Less,
+            BinaryOperator::Greater,
+            BinaryOperator::LessOrEqual,
+            BinaryOperator::GreaterOrEqual,
+            BinaryOperator::Dice,
+            BinaryOperator::Access,
+        ] {
+            round_trip(operator);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip_sequence_entries() {
+        round_trip(single_entry(integer(1), integer(2)));
+        round_trip(range_entry(integer(1), integer(2), integer(3)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip_expressions() {
+        round_trip(integer(-1));
+        round_trip(variable_reference("X".to_owned()));
+        round_trip(sequence(vec![
+            single_entry(integer(1), integer(1)),
+            range_entry(integer(2), integer(3), integer(4)),
+        ]));
+        round_trip(negate(Box::new(integer(1))));
+        round_trip(dice(Box::new(integer(3)), Box::new(integer(6))));
+        round_trip(function_call(
+            "highest ? of ?".to_owned(),
+            vec![integer(1), variable_reference("X".to_owned())],
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip_parameter_types() {
+        for expected_type in [
+            FunctionParameterExpectedType::Dice,
+            FunctionParameterExpectedType::Number,
+            FunctionParameterExpectedType::Sequence,
+        ] {
+            round_trip(expected_type);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip_statements() {
+        round_trip(variable_assignment("X".to_owned(), integer(1)));
+        round_trip(output(integer(1), None));
+        round_trip(output(integer(1), Some("named".to_owned())));
+        round_trip(configure_string(
+            "position order".to_owned(),
+            "lowest first".to_owned(),
+        ));
+        round_trip(configure_expression("explode depth".to_owned(), integer(3)));
+        round_trip(function_definition(
+            "double ?".to_owned(),
+            vec![
+                function_parameter("X".to_owned(), None),
+                function_parameter("Y".to_owned(), Some(FunctionParameterExpectedType::Dice)),
+            ],
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_spans() {
+        let node =
+            negate(Box::new(integer(1).with_span(Span::new(1, 2)))).with_span(Span::new(0, 2));
+
+        assert_eq!(
+            serde_json::to_value(&node).unwrap(),
+            serde_json::json!({
+                "type": "unary_operation",
+                "operator": "negate",
+                "operand": {"type": "integer", "value": 1, "span": {"start": 1, "end": 2}},
+                "span": {"start": 0, "end": 2},
+            })
+        );
+
+        let parsed =
+            serde_json::from_value::<Expression>(serde_json::to_value(&node).unwrap()).unwrap();
+        assert_eq!(parsed.span(), Span::new(0, 2));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip_program() {
+        round_trip(program(vec![]));
+        round_trip(program(vec![
+            configure_string("position order".to_owned(), "lowest first".to_owned()),
+            output(
+                access(
+                    Box::new(integer(1)),
+                    Box::new(dice(Box::new(integer(2)), Box::new(integer(20)))),
+                ),
+                Some("advantage".to_owned()),
+            ),
+        ]));
+    }
 }
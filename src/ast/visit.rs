@@ -0,0 +1,534 @@
+//! Walking every node of an AST without matching on each kind of node
+//!
+//! There are three traits, each with a method for every kind of node:
+//!
+//! - [`Visitor`] looks at each node by reference
+//! - [`VisitorMut`] changes nodes in place
+//! - [`Fold`] takes each node by value and rebuilds the tree from what it returns
+//!
+//! The default methods walk into every child, so a pass only overrides the nodes it cares about.
+//! An overriding method can call the matching `walk_*` or `fold_*` function to carry on into the
+//! children of the node, or leave it out to skip them.
+//!
+//! ```
+//! # use some_dice::ast::{visit::*, Expression};
+//! // Rename every reference to a variable
+//! struct Rename;
+//!
+//! impl VisitorMut for Rename {
+//!     fn visit_expression_mut(&mut self, expression: &mut Expression) {
+//!         if let Expression::VariableReference { name, .. } = expression {
+//!             *name = name.replace("OLD", "NEW");
+//!         }
+//!         walk_expression_mut(self, expression);
+//!     }
+//! }
+//! ```
+//!
+//! Function definitions don't have bodies in the tree yet, so only their parameters are walked.
+
+use super::{Expression, FunctionParameter, Program, SequenceEntry, Statement};
+
+/// Look at each node of a tree
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+
+    fn visit_sequence_entry(&mut self, entry: &SequenceEntry) {
+        walk_sequence_entry(self, entry);
+    }
+
+    /// Parameters have no children, so there is nothing to walk
+    fn visit_function_parameter(&mut self, _parameter: &FunctionParameter) {}
+}
+
+/// Visit each statement of a program in order
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for statement in &program.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+/// Visit each expression or parameter of a statement
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::VariableAssignment { value, .. }
+        | Statement::Output { value, .. }
+        | Statement::ConfigureExpression { value, .. } => visitor.visit_expression(value),
+        Statement::ConfigureString { .. } => {}
+        Statement::FunctionDefinition { parameters, .. } => {
+            for parameter in parameters {
+                visitor.visit_function_parameter(parameter);
+            }
+        }
+    }
+}
+
+/// Visit each operand, entry, or argument of an expression, from left to right
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Integer { .. } | Expression::VariableReference { .. } => {}
+        Expression::Sequence { entries, .. } => {
+            for entry in entries {
+                visitor.visit_sequence_entry(entry);
+            }
+        }
+        Expression::UnaryOperation { operand, .. } => visitor.visit_expression(operand),
+        Expression::BinaryOperation { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        Expression::FunctionCall { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression(argument);
+            }
+        }
+    }
+}
+
+/// Visit the values of an entry and then its repetitions
+pub fn walk_sequence_entry<V: Visitor + ?Sized>(visitor: &mut V, entry: &SequenceEntry) {
+    match entry {
+        SequenceEntry::Single {
+            value, repetitions, ..
+        } => {
+            visitor.visit_expression(value);
+            visitor.visit_expression(repetitions);
+        }
+        SequenceEntry::Range {
+            start,
+            end,
+            repetitions,
+            ..
+        } => {
+            visitor.visit_expression(start);
+            visitor.visit_expression(end);
+            visitor.visit_expression(repetitions);
+        }
+    }
+}
+
+/// Change each node of a tree in place
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+    }
+
+    fn visit_sequence_entry_mut(&mut self, entry: &mut SequenceEntry) {
+        walk_sequence_entry_mut(self, entry);
+    }
+
+    /// Parameters have no children, so there is nothing to walk
+    fn visit_function_parameter_mut(&mut self, _parameter: &mut FunctionParameter) {}
+}
+
+/// Visit each statement of a program in order
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for statement in &mut program.statements {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+/// Visit each expression or parameter of a statement
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::VariableAssignment { value, .. }
+        | Statement::Output { value, .. }
+        | Statement::ConfigureExpression { value, .. } => visitor.visit_expression_mut(value),
+        Statement::ConfigureString { .. } => {}
+        Statement::FunctionDefinition { parameters, .. } => {
+            for parameter in parameters {
+                visitor.visit_function_parameter_mut(parameter);
+            }
+        }
+    }
+}
+
+/// Visit each operand, entry, or argument of an expression, from left to right
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match expression {
+        Expression::Integer { .. } | Expression::VariableReference { .. } => {}
+        Expression::Sequence { entries, .. } => {
+            for entry in entries {
+                visitor.visit_sequence_entry_mut(entry);
+            }
+        }
+        Expression::UnaryOperation { operand, .. } => visitor.visit_expression_mut(operand),
+        Expression::BinaryOperation { left, right, .. } => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(right);
+        }
+        Expression::FunctionCall { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expression_mut(argument);
+            }
+        }
+    }
+}
+
+/// Visit the values of an entry and then its repetitions
+pub fn walk_sequence_entry_mut<V: VisitorMut + ?Sized>(visitor: &mut V, entry: &mut SequenceEntry) {
+    match entry {
+        SequenceEntry::Single {
+            value, repetitions, ..
+        } => {
+            visitor.visit_expression_mut(value);
+            visitor.visit_expression_mut(repetitions);
+        }
+        SequenceEntry::Range {
+            start,
+            end,
+            repetitions,
+            ..
+        } => {
+            visitor.visit_expression_mut(start);
+            visitor.visit_expression_mut(end);
+            visitor.visit_expression_mut(repetitions);
+        }
+    }
+}
+
+/// Rebuild a tree from what each node is replaced with
+///
+/// Unlike [`VisitorMut`], a node can be replaced with a different kind of node, like an operation
+/// with the integer it works out to.
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        fold_statement(self, statement)
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression(self, expression)
+    }
+
+    fn fold_sequence_entry(&mut self, entry: SequenceEntry) -> SequenceEntry {
+        fold_sequence_entry(self, entry)
+    }
+
+    /// Parameters have no children, so they are kept as they are
+    fn fold_function_parameter(&mut self, parameter: FunctionParameter) -> FunctionParameter {
+        parameter
+    }
+}
+
+/// Rebuild a program from each of its statements folded in order
+pub fn fold_program<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        statements: program
+            .statements
+            .into_iter()
+            .map(|statement| folder.fold_statement(statement))
+            .collect(),
+        span: program.span,
+    }
+}
+
+/// Rebuild a statement from each of its expressions or parameters folded
+pub fn fold_statement<F: Fold + ?Sized>(folder: &mut F, statement: Statement) -> Statement {
+    match statement {
+        Statement::VariableAssignment { name, value, span } => Statement::VariableAssignment {
+            name,
+            value: folder.fold_expression(value),
+            span,
+        },
+        Statement::Output { value, name, span } => Statement::Output {
+            value: folder.fold_expression(value),
+            name,
+            span,
+        },
+        Statement::ConfigureExpression {
+            setting,
+            value,
+            span,
+        } => Statement::ConfigureExpression {
+            setting,
+            value: folder.fold_expression(value),
+            span,
+        },
+        statement @ Statement::ConfigureString { .. } => statement,
+        Statement::FunctionDefinition {
+            name,
+            parameters,
+            span,
+        } => Statement::FunctionDefinition {
+            name,
+            parameters: parameters
+                .into_iter()
+                .map(|parameter| folder.fold_function_parameter(parameter))
+                .collect(),
+            span,
+        },
+    }
+}
+
+/// Rebuild an expression from each of its operands, entries, or arguments folded, from left to
+/// right
+pub fn fold_expression<F: Fold + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    match expression {
+        expression @ (Expression::Integer { .. } | Expression::VariableReference { .. }) => {
+            expression
+        }
+        Expression::Sequence { entries, span } => Expression::Sequence {
+            entries: entries
+                .into_iter()
+                .map(|entry| folder.fold_sequence_entry(entry))
+                .collect(),
+            span,
+        },
+        Expression::UnaryOperation {
+            operator,
+            operand,
+            span,
+        } => Expression::UnaryOperation {
+            operator,
+            operand: Box::new(folder.fold_expression(*operand)),
+            span,
+        },
+        Expression::BinaryOperation {
+            operator,
+            left,
+            right,
+            span,
+        } => {
+            let left = Box::new(folder.fold_expression(*left));
+            Expression::BinaryOperation {
+                operator,
+                left,
+                right: Box::new(folder.fold_expression(*right)),
+                span,
+            }
+        }
+        Expression::FunctionCall {
+            name,
+            arguments,
+            span,
+        } => Expression::FunctionCall {
+            name,
+            arguments: arguments
+                .into_iter()
+                .map(|argument| folder.fold_expression(argument))
+                .collect(),
+            span,
+        },
+    }
+}
+
+/// Rebuild an entry from its values and then its repetitions folded
+pub fn fold_sequence_entry<F: Fold + ?Sized>(
+    folder: &mut F,
+    entry: SequenceEntry,
+) -> SequenceEntry {
+    match entry {
+        SequenceEntry::Single {
+            value,
+            repetitions,
+            span,
+        } => {
+            let value = folder.fold_expression(value);
+            SequenceEntry::Single {
+                value,
+                repetitions: folder.fold_expression(repetitions),
+                span,
+            }
+        }
+        SequenceEntry::Range {
+            start,
+            end,
+            repetitions,
+            span,
+        } => {
+            let start = folder.fold_expression(start);
+            let end = folder.fold_expression(end);
+            SequenceEntry::Range {
+                start,
+                end,
+                repetitions: folder.fold_expression(repetitions),
+                span,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::functional::*;
+    use super::super::{BinaryOperator, FunctionParameterExpectedType, Span};
+    use super::*;
+
+    fn example() -> Program {
+        program(vec![
+            variable_assignment(
+                "X".to_owned(),
+                sequence(vec![
+                    single_entry(integer(1), variable_reference("N".to_owned())),
+                    range_entry(integer(2), variable_reference("M".to_owned()), integer(3)),
+                ]),
+            ),
+            output(
+                add(
+                    Box::new(negate(Box::new(variable_reference("X".to_owned())))),
+                    Box::new(function_call(
+                        "highest ? of ?".to_owned(),
+                        vec![integer(1), variable_reference("Y".to_owned())],
+                    )),
+                ),
+                None,
+            ),
+            configure_string("position order".to_owned(), "lowest first".to_owned()),
+            configure_expression(
+                "explode depth".to_owned(),
+                variable_reference("Z".to_owned()),
+            ),
+            function_definition(
+                "double ?".to_owned(),
+                vec![function_parameter(
+                    "P".to_owned(),
+                    Some(FunctionParameterExpectedType::Number),
+                )],
+            ),
+        ])
+    }
+
+    /// Every variable referenced, in the order they are walked
+    #[derive(Default)]
+    struct References(Vec<String>);
+
+    impl Visitor for References {
+        fn visit_expression(&mut self, expression: &Expression) {
+            if let Expression::VariableReference { name, .. } = expression {
+                self.0.push(name.clone());
+            }
+            walk_expression(self, expression);
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let mut references = References::default();
+        references.visit_program(&example());
+        assert_eq!(references.0, vec!["N", "M", "X", "Y", "Z"]);
+
+        #[derive(Default)]
+        struct Counts {
+            statements: usize,
+            entries: usize,
+            parameters: usize,
+        }
+
+        impl Visitor for Counts {
+            fn visit_statement(&mut self, statement: &Statement) {
+                self.statements += 1;
+                walk_statement(self, statement);
+            }
+
+            fn visit_sequence_entry(&mut self, entry: &SequenceEntry) {
+                self.entries += 1;
+                walk_sequence_entry(self, entry);
+            }
+
+            fn visit_function_parameter(&mut self, _parameter: &FunctionParameter) {
+                self.parameters += 1;
+            }
+        }
+
+        let mut counts = Counts::default();
+        counts.visit_program(&example());
+        assert_eq!(
+            (counts.statements, counts.entries, counts.parameters),
+            (5, 2, 1)
+        );
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        struct Rename;
+
+        impl VisitorMut for Rename {
+            fn visit_expression_mut(&mut self, expression: &mut Expression) {
+                if let Expression::VariableReference { name, .. } = expression {
+                    name.insert_str(0, "NEW_");
+                }
+                walk_expression_mut(self, expression);
+            }
+        }
+
+        let mut program = example();
+        Rename.visit_program_mut(&mut program);
+
+        let mut references = References::default();
+        references.visit_program(&program);
+        assert_eq!(
+            references.0,
+            vec!["NEW_N", "NEW_M", "NEW_X", "NEW_Y", "NEW_Z"]
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        /// Replace every variable with 0, and every negation with its operand
+        struct Zero;
+
+        impl Fold for Zero {
+            fn fold_expression(&mut self, expression: Expression) -> Expression {
+                match fold_expression(self, expression) {
+                    Expression::VariableReference { span, .. } => integer(0).with_span(span),
+                    Expression::UnaryOperation { operand, .. } => *operand,
+                    expression => expression,
+                }
+            }
+        }
+
+        let folded = Zero.fold_expression(
+            negate(Box::new(
+                multiply(
+                    Box::new(variable_reference("X".to_owned()).with_span(Span::new(1, 2))),
+                    Box::new(integer(2)),
+                )
+                .with_span(Span::new(1, 6)),
+            ))
+            .with_span(Span::new(0, 6)),
+        );
+        assert_eq!(folded, multiply(Box::new(integer(0)), Box::new(integer(2))));
+        // Nodes that aren't replaced keep their spans
+        assert_eq!(folded.span(), Span::new(1, 6));
+        let Expression::BinaryOperation {
+            operator: BinaryOperator::Multiply,
+            left,
+            ..
+        } = &folded
+        else {
+            panic!("expected a multiplication");
+        };
+        assert_eq!(left.span(), Span::new(1, 2));
+
+        // Without any overrides, folding rebuilds the same tree
+        struct Identity;
+        impl Fold for Identity {}
+        assert_eq!(Identity.fold_program(example()), example());
+
+        let mut references = References::default();
+        references.visit_program(&Zero.fold_program(example()));
+        assert!(references.0.is_empty());
+    }
+}
@@ -0,0 +1,295 @@
+//! Command-line interface for running AnyDice programs
+
+use std::fs;
+use std::io::{self, Read};
+use std::num;
+use std::path;
+use std::process;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use some_dice::ast;
+use some_dice::interpret::output::Outputs;
+use some_dice::interpret::{interpreter::Interpreter, render, simulate};
+use some_dice::parse;
+use some_dice::sample::rng;
+
+mod print;
+mod repl;
+mod roll;
+
+/// Run AnyDice programs and print their outputs
+#[derive(Debug, Parser)]
+#[command(
+    name = "some-dice",
+    version,
+    about,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Start an interactive session
+    Repl,
+    /// Roll an expression by sampling its exact outcomes
+    Roll(RollArgs),
+    /// Reformat a program, keeping its comments
+    Fmt(FmtArgs),
+}
+
+#[derive(Debug, Args)]
+struct FmtArgs {
+    /// Program to format, read from stdin if missing or `-`
+    file: Option<path::PathBuf>,
+
+    /// Write the formatted program back to the file instead of printing it
+    #[arg(long, requires = "file")]
+    write: bool,
+}
+
+#[derive(Debug, Args)]
+struct RollArgs {
+    /// Expression to roll, such as `3d6+2`
+    expression: String,
+
+    /// Number of times to roll
+    #[arg(short = 'n', long, default_value_t = 1)]
+    count: usize,
+
+    /// Seed for reproducible rolls, random if missing
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Compare how often each value was rolled against its exact chance
+    #[arg(long, conflicts_with = "transcript")]
+    stats: bool,
+
+    /// Test the rolls against the exact chances with chi-square and Kolmogorov-Smirnov tests
+    #[arg(long, conflicts_with = "transcript")]
+    verify: bool,
+
+    /// Show every die rolled and every value along the way
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    transcript: Option<TranscriptFormat>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum TranscriptFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Args)]
+struct RunArgs {
+    /// Program to run, read from stdin if missing or `-`
+    file: Option<path::PathBuf>,
+
+    /// Format to print outputs in
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// How to show each output when printing text
+    #[arg(long, value_enum, default_value_t = View::Table)]
+    view: View,
+
+    /// Which chances to show when printing text
+    #[arg(long, value_enum, default_value_t = Mode::Normal)]
+    mode: Mode,
+
+    /// Show exact fractions instead of percentages when printing text
+    #[arg(long)]
+    fractions: bool,
+
+    /// Width in characters of the longest bar in charts
+    #[arg(long, default_value_t = 40)]
+    width: usize,
+
+    /// Estimate outputs from this many random trials instead of computing them exactly
+    #[arg(long, value_name = "TRIALS")]
+    simulate: Option<num::NonZeroUsize>,
+
+    /// Seed for reproducible trials when simulating, random if missing
+    #[arg(long, requires = "simulate")]
+    seed: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum View {
+    Table,
+    Chart,
+    Summary,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    Normal,
+    AtLeast,
+    AtMost,
+}
+
+impl From<Mode> for render::Mode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Normal => render::Mode::Normal,
+            Mode::AtLeast => render::Mode::AtLeast,
+            Mode::AtMost => render::Mode::AtMost,
+        }
+    }
+}
+
+fn read_program(file: Option<&path::Path>) -> io::Result<String> {
+    match file {
+        Some(file) if file != path::Path::new("-") => fs::read_to_string(file),
+        _ => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+/// Parse a whole program, showing every syntax error rather than only the first
+fn parse_program(input: &str) -> Result<ast::Program, String> {
+    let (program, errors) = parse::parse_program_recovering(input);
+    if errors.is_empty() {
+        return Ok(program);
+    }
+
+    // Each error after the first needs its own prefix, as only one is added when printed
+    Err(errors
+        .iter()
+        .map(|err| err.diagnostic(input).to_string())
+        .collect::<Vec<_>>()
+        .join("\n\nerror: "))
+}
+
+fn run(cli: &RunArgs) -> Result<String, String> {
+    let input = read_program(cli.file.as_deref())
+        .map_err(|err| format!("Failed to read program: {}", err))?;
+    let program = parse_program(&input)?;
+
+    let Some(trials) = cli.simulate else {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .run_program(program)
+            .map_err(|err| err.to_string())?;
+
+        return Ok(show(cli, interpreter.outputs()));
+    };
+
+    let simulator = simulate::SimulatedInterpreter::new(trials);
+    let simulation = match cli.seed {
+        Some(seed) => simulator.run_program_seeded(program, seed),
+        None => simulator.run_program(program, &mut rand::thread_rng()),
+    }
+    .map_err(|err| err.to_string())?;
+
+    Ok(match (cli.format, cli.view) {
+        (Format::Text, View::Table) => {
+            print::simulation_table(&simulation, cli.mode.into(), cli.fractions)
+        }
+        _ => show(cli, simulation.outputs()),
+    })
+}
+
+/// Outputs in the format and view asked for
+fn show(cli: &RunArgs, outputs: &Outputs) -> String {
+    match cli.format {
+        Format::Csv => outputs.to_csv(),
+        Format::Json => format!("{}\n", outputs.to_json()),
+        Format::Text => match cli.view {
+            View::Table => print::table(outputs, cli.mode.into(), cli.fractions),
+            View::Chart => render::BarChart::new()
+                .mode(cli.mode.into())
+                .width(cli.width)
+                .render_outputs(outputs),
+            View::Summary => print::summaries(outputs, cli.fractions),
+        },
+    }
+}
+
+fn run_roll(args: &RollArgs) -> Result<String, String> {
+    if let Some(format) = args.transcript {
+        let json = format == TranscriptFormat::Json;
+
+        return match args.seed {
+            Some(seed) => {
+                roll::transcripts(&args.expression, args.count, json, &mut rng::seeded(seed))
+            }
+            None => roll::transcripts(&args.expression, args.count, json, &mut rand::thread_rng()),
+        };
+    }
+
+    let (output, rolls) = match args.seed {
+        Some(seed) => roll::roll(&args.expression, args.count, &mut rng::seeded(seed))?,
+        None => roll::roll(&args.expression, args.count, &mut rand::thread_rng())?,
+    };
+
+    let mut text = if args.stats {
+        roll::stats(&output, &rolls)
+    } else if args.verify {
+        String::new()
+    } else {
+        roll::rolls(&rolls)
+    };
+    if args.verify {
+        text.push_str(&roll::verify(&output, &rolls)?);
+    }
+
+    Ok(text)
+}
+
+fn run_fmt(args: &FmtArgs) -> Result<String, String> {
+    let input = read_program(args.file.as_deref())
+        .map_err(|err| format!("Failed to read program: {}", err))?;
+    // The syntax tree only has the first error, so every error is found first
+    parse_program(&input)?;
+    let formatted = parse::parse_syntax_tree(&input)
+        .map_err(|err| err.diagnostic(&input).to_string())?
+        .format();
+
+    match &args.file {
+        Some(file) if args.write && file != path::Path::new("-") => {
+            fs::write(file, formatted)
+                .map_err(|err| format!("Failed to write program: {}", err))?;
+            Ok(String::new())
+        }
+        _ => Ok(formatted),
+    }
+}
+
+fn main() -> process::ExitCode {
+    let cli = Cli::parse();
+
+    let result = match &cli.command {
+        Some(Command::Repl) => return repl::run(),
+        Some(Command::Roll(args)) => run_roll(args),
+        Some(Command::Fmt(args)) => run_fmt(args),
+        None => run(&cli.run),
+    };
+
+    match result {
+        Ok(printed) => {
+            print!("{}", printed);
+            process::ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("error: {}", message);
+            process::ExitCode::FAILURE
+        }
+    }
+}
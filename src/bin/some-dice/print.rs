@@ -0,0 +1,246 @@
+//! Plain text tables and summaries of outputs
+
+use std::fmt::Write;
+
+use some_dice::interpret::output::{Output, Outputs};
+use some_dice::interpret::rational::Rational;
+use some_dice::interpret::render;
+use some_dice::interpret::simulate::Simulation;
+
+fn chance(chance: Rational, fractions: bool) -> String {
+    if fractions {
+        chance.to_string()
+    } else {
+        format!("{:.2}%", chance.to_f64() * 100.0)
+    }
+}
+
+fn number(number: Rational, fractions: bool) -> String {
+    if fractions {
+        number.to_string()
+    } else {
+        format!("{:.2}", number.to_f64())
+    }
+}
+
+/// A single line of summary statistics
+pub(crate) fn summary(output: &Output, fractions: bool) -> String {
+    let deviation = |deviation: Option<f64>| {
+        deviation.map_or("unknown".to_owned(), |deviation| {
+            format!("{:.2}", deviation)
+        })
+    };
+
+    match output.summary() {
+        Some(summary) => format!(
+            "mean {}, deviation {}, min {}, max {}, median {}, mode {}",
+            number(summary.mean, fractions),
+            deviation(summary.standard_deviation),
+            summary.minimum,
+            summary.maximum,
+            summary.median,
+            summary
+                .modes
+                .iter()
+                .map(|mode| mode.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        None => "no outcomes".to_owned(),
+    }
+}
+
+/// A single output with its summary and a row with the chance of each value
+pub(crate) fn output_table(
+    name: &str,
+    output: &Output,
+    mode: render::Mode,
+    fractions: bool,
+) -> String {
+    let chances = mode.chances(output);
+    let value_width = chances
+        .iter()
+        .map(|(value, _)| value.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut text = format!("{}\n{}\n", name, summary(output, fractions));
+    for (value, value_chance) in chances {
+        writeln!(
+            text,
+            "{:>value_width$}  {}",
+            value,
+            chance(value_chance, fractions)
+        )
+        .expect("writing to a string to succeed");
+    }
+
+    text
+}
+
+/// Each output with its summary and a row with the chance of each value
+pub(crate) fn table(outputs: &Outputs, mode: render::Mode, fractions: bool) -> String {
+    outputs
+        .names()
+        .zip(outputs.outcomes())
+        .map(|(name, output)| output_table(&name, output, mode, fractions))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Each output with only its summary
+pub(crate) fn summaries(outputs: &Outputs, fractions: bool) -> String {
+    outputs
+        .names()
+        .zip(outputs.outcomes())
+        .map(|(name, output)| format!("{}\n{}\n", name, summary(output, fractions)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Each estimated output like a table, with how far off each estimate could be
+///
+/// Only the normal mode shows an interval for the chance of each value.
+pub(crate) fn simulation_table(
+    simulation: &Simulation,
+    mode: render::Mode,
+    fractions: bool,
+) -> String {
+    let outputs = simulation.outputs();
+    let confidence = simulation.confidence() * 100.0;
+
+    outputs
+        .names()
+        .zip(outputs.outcomes())
+        .zip(simulation.intervals())
+        .map(|((name, output), intervals)| {
+            let chances = mode.chances(output);
+            let value_width = chances
+                .iter()
+                .map(|(value, _)| value.to_string().len())
+                .max()
+                .unwrap_or(0);
+
+            let mut text = format!(
+                "{}\n{}\nmean between {:.2} and {:.2} with {}% confidence over {} trials\n",
+                name,
+                summary(output, fractions),
+                intervals.mean.low,
+                intervals.mean.high,
+                confidence,
+                simulation.trials()
+            );
+            for (value, value_chance) in chances {
+                write!(
+                    text,
+                    "{:>value_width$}  {}",
+                    value,
+                    chance(value_chance, fractions)
+                )
+                .expect("writing to a string to succeed");
+
+                let interval = intervals
+                    .chances
+                    .iter()
+                    .find(|(interval_value, _)| *interval_value == value)
+                    .map(|(_, interval)| interval);
+                if let (render::Mode::Normal, Some(interval)) = (mode, interval) {
+                    write!(
+                        text,
+                        "  ({:.2}% to {:.2}%)",
+                        interval.low * 100.0,
+                        interval.high * 100.0
+                    )
+                    .expect("writing to a string to succeed");
+                }
+                text.push('\n');
+            }
+
+            text
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num;
+
+    use some_dice::interpret::{interpreter::Interpreter, simulate};
+    use some_dice::parse;
+
+    use super::*;
+
+    fn outputs(program: &str) -> Outputs {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .run_program(parse::parse_program(program).unwrap())
+            .unwrap();
+
+        interpreter.outputs().clone()
+    }
+
+    #[test]
+    fn test_table() {
+        assert_eq!(
+            table(&outputs("output 1d4 output 2"), render::Mode::Normal, false),
+            "output 1\n\
+             mean 2.50, deviation 1.12, min 1, max 4, median 2, mode 1 2 3 4\n\
+             1  25.00%\n\
+             2  25.00%\n\
+             3  25.00%\n\
+             4  25.00%\n\
+             \n\
+             output 2\n\
+             mean 2.00, deviation 0.00, min 2, max 2, median 2, mode 2\n\
+             2  100.00%\n"
+        );
+    }
+
+    #[test]
+    fn test_table_fractions_at_least() {
+        assert_eq!(
+            table(&outputs("output 1d4"), render::Mode::AtLeast, true),
+            "output 1\n\
+             mean 5/2, deviation 1.12, min 1, max 4, median 2, mode 1 2 3 4\n\
+             1  1\n\
+             2  3/4\n\
+             3  1/2\n\
+             4  1/4\n"
+        );
+    }
+
+    #[test]
+    fn test_simulation_table() {
+        let simulation = simulate::SimulatedInterpreter::new(num::NonZeroUsize::new(100).unwrap())
+            .run_program_seeded(parse::parse_program("output 2").unwrap(), 0)
+            .unwrap();
+
+        assert_eq!(
+            simulation_table(&simulation, render::Mode::Normal, false),
+            "output 1\n\
+             mean 2.00, deviation 0.00, min 2, max 2, median 2, mode 2\n\
+             mean between 2.00 and 2.00 with 95% confidence over 100 trials\n\
+             2  100.00%  (96.30% to 100.00%)\n"
+        );
+        assert_eq!(
+            simulation_table(&simulation, render::Mode::AtLeast, false),
+            "output 1\n\
+             mean 2.00, deviation 0.00, min 2, max 2, median 2, mode 2\n\
+             mean between 2.00 and 2.00 with 95% confidence over 100 trials\n\
+             2  100.00%\n"
+        );
+    }
+
+    #[test]
+    fn test_summaries() {
+        assert_eq!(
+            summaries(&outputs("output 2d6 output -1"), false),
+            "output 1\n\
+             mean 7.00, deviation 2.42, min 2, max 12, median 7, mode 7\n\
+             \n\
+             output 2\n\
+             mean -1.00, deviation 0.00, min -1, max -1, median -1, mode -1\n"
+        );
+    }
+}
@@ -0,0 +1,370 @@
+//! Interactive session that keeps one interpreter across entries
+
+use std::fmt::Write;
+use std::fs;
+use std::process;
+
+use rustyline::error::ReadlineError;
+
+use some_dice::interpret::interpreter::Interpreter;
+use some_dice::interpret::render;
+use some_dice::parse;
+
+use crate::print;
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = ". ";
+
+const HELP: &str = "\
+Enter statements to run them, or an expression to see its outcomes.
+Unfinished blocks continue on the next line.
+
+:vars         Show the value of every variable
+:funcs        Show every defined function, once definitions are supported
+:config       Show the current configuration
+:load FILE    Run a program from a file in this session
+:reset        Forget all variables, functions, configuration, and outputs
+:help         Show this help
+:quit         Leave the session (or press Ctrl-D)
+";
+
+/// Whether the input has unclosed brackets, strings, or comments and needs more lines
+pub(crate) fn is_incomplete(input: &str) -> bool {
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut in_comment = false;
+
+    for c in input.chars() {
+        match c {
+            '"' if !in_comment => in_string = !in_string,
+            '\\' if !in_string => in_comment = !in_comment,
+            _ if in_string || in_comment => {}
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth > 0 || in_string || in_comment
+}
+
+/// What to do after an entry
+#[derive(Debug, PartialEq)]
+pub(crate) enum Action {
+    Print(String),
+    Quit,
+}
+
+/// State that lives for the whole interactive session
+#[derive(Debug, Default)]
+pub(crate) struct Session {
+    interpreter: Interpreter,
+}
+
+impl Session {
+    pub(crate) fn new() -> Self {
+        Self {
+            interpreter: Interpreter::new(),
+        }
+    }
+
+    /// Handle a complete entry, which is either a meta-command or code
+    pub(crate) fn enter(&mut self, entry: &str) -> Result<Action, String> {
+        let entry = entry.trim();
+
+        let Some(command) = entry.strip_prefix(':') else {
+            return self.run(entry).map(Action::Print);
+        };
+
+        let (command, argument) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(command, argument)| {
+                (command, argument.trim())
+            });
+
+        match (command, argument) {
+            ("vars", "") => self.variables().map(Action::Print),
+            ("funcs", "") => Ok(Action::Print(
+                "Function definitions are not supported yet\n".to_owned(),
+            )),
+            ("config", "") => Ok(Action::Print(self.settings())),
+            ("load", "") => Err("Usage: :load FILE".to_owned()),
+            ("load", file) => {
+                let program = fs::read_to_string(file)
+                    .map_err(|err| format!("Failed to read {}: {}", file, err))?;
+                self.run(&program).map(Action::Print)
+            }
+            ("reset", "") => {
+                self.interpreter = Interpreter::new();
+                Ok(Action::Print(String::new()))
+            }
+            ("help", "") => Ok(Action::Print(HELP.to_owned())),
+            ("quit" | "q", "") => Ok(Action::Quit),
+            _ => Err(format!("Unknown command :{}, try :help", command)),
+        }
+    }
+
+    /// Run statements, or a lone expression, and show any new outputs
+    fn run(&mut self, code: &str) -> Result<String, String> {
+        if code.is_empty() {
+            return Ok(String::new());
+        }
+
+        let program = match parse::parse_program(code) {
+            Ok(program) => program,
+            Err(program_error) => {
+                // Fall back to showing a bare expression without it becoming an output
+                let expression = parse::parse_expression(code)
+                    .map_err(|_| program_error.diagnostic(code).to_string())?;
+                let output = self
+                    .interpreter
+                    .run_expression(expression)
+                    .map_err(|err| err.to_string())?;
+
+                return Ok(print::output_table(
+                    code,
+                    &output,
+                    render::Mode::Normal,
+                    false,
+                ));
+            }
+        };
+
+        let previous = self.interpreter.outputs().outcomes().len();
+        let result = self.interpreter.run_program(program);
+
+        // Outputs before any error were still produced, so show them either way
+        let outputs = self.interpreter.outputs();
+        let mut printed = String::new();
+        for (name, output) in outputs.names().zip(outputs.outcomes()).skip(previous) {
+            if !printed.is_empty() {
+                printed.push('\n');
+            }
+            printed.push_str(&print::output_table(
+                &name,
+                output,
+                render::Mode::Normal,
+                false,
+            ));
+        }
+
+        match result {
+            Ok(()) => Ok(printed),
+            Err(err) => Err(format!("{}{}", printed, err)),
+        }
+    }
+
+    fn variables(&self) -> Result<String, String> {
+        let variables = self
+            .interpreter
+            .variables()
+            .map_err(|err| err.to_string())?;
+        if variables.is_empty() {
+            return Ok("No variables are defined\n".to_owned());
+        }
+
+        Ok(variables
+            .iter()
+            .map(|variable| {
+                format!(
+                    "{}: {}\n",
+                    variable.name().unwrap_or_default(),
+                    print::summary(variable, false)
+                )
+            })
+            .collect())
+    }
+
+    fn settings(&self) -> String {
+        let mut printed = String::new();
+        for (setting, value) in self.interpreter.settings() {
+            writeln!(printed, "{}: {}", setting, value).expect("writing to a string to succeed");
+        }
+
+        printed
+    }
+}
+
+/// Read entries from the terminal until the session is closed
+pub(crate) fn run() -> process::ExitCode {
+    let mut editor = match rustyline::DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("error: Failed to start the session: {}", err);
+            return process::ExitCode::FAILURE;
+        }
+    };
+    let mut session = Session::new();
+    let mut entry = String::new();
+
+    println!(
+        "some-dice {}, enter :help for help",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    loop {
+        let prompt = if entry.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+
+        match editor.readline(prompt) {
+            Ok(line) => {
+                entry.push_str(&line);
+                entry.push('\n');
+                if is_incomplete(&entry) {
+                    continue;
+                }
+
+                // Failing to remember history is not worth interrupting the session
+                let _ = editor.add_history_entry(entry.trim_end());
+
+                match session.enter(&entry) {
+                    Ok(Action::Print(printed)) => print!("{}", printed),
+                    Ok(Action::Quit) => return process::ExitCode::SUCCESS,
+                    Err(message) => eprintln!("error: {}", message),
+                }
+                entry.clear();
+            }
+            // Ctrl-C abandons the current entry
+            Err(ReadlineError::Interrupted) => entry.clear(),
+            Err(ReadlineError::Eof) => return process::ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                return process::ExitCode::FAILURE;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printed(session: &mut Session, entry: &str) -> String {
+        match session.enter(entry) {
+            Ok(Action::Print(printed)) => printed,
+            other => panic!("expected printed text, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(!is_incomplete("output 1"));
+        assert!(is_incomplete("output {1,\n"));
+        assert!(!is_incomplete("output {1,\n2}"));
+        assert!(is_incomplete("function: double X {\n"));
+        assert!(is_incomplete("output 1 \\ unfinished"));
+        assert!(!is_incomplete("output 1 \\ { \\"));
+        assert!(is_incomplete("set \"position"));
+        assert!(!is_incomplete("set \"{\" to 1"));
+    }
+
+    #[test]
+    fn test_session_keeps_state() {
+        let mut session = Session::new();
+
+        assert_eq!(printed(&mut session, "X: 1d2"), "");
+        assert_eq!(
+            printed(&mut session, "output X + 1"),
+            "output 1\n\
+             mean 2.50, deviation 0.50, min 2, max 3, median 2, mode 2 3\n\
+             2  50.00%\n\
+             3  50.00%\n"
+        );
+        assert_eq!(
+            printed(&mut session, "output X"),
+            "output 2\n\
+             mean 1.50, deviation 0.50, min 1, max 2, median 1, mode 1 2\n\
+             1  50.00%\n\
+             2  50.00%\n"
+        );
+        assert_eq!(
+            printed(&mut session, ":vars"),
+            "X: mean 1.50, deviation 0.50, min 1, max 2, median 1, mode 1 2\n"
+        );
+    }
+
+    #[test]
+    fn test_session_expression() {
+        let mut session = Session::new();
+
+        assert_eq!(
+            printed(&mut session, "2 * 3"),
+            "2 * 3\n\
+             mean 6.00, deviation 0.00, min 6, max 6, median 6, mode 6\n\
+             6  100.00%\n"
+        );
+        assert_eq!(session.interpreter.outputs().outcomes().len(), 0);
+    }
+
+    #[test]
+    fn test_session_meta_commands() {
+        let mut session = Session::new();
+
+        assert_eq!(printed(&mut session, ":vars"), "No variables are defined\n");
+        assert_eq!(printed(&mut session, "set \"explode depth\" to 4"), "");
+        assert_eq!(
+            printed(&mut session, ":config"),
+            "position order: highest first\n\
+             maximum function depth: 10\n\
+             explode depth: 4\n"
+        );
+
+        printed(&mut session, "X: 1");
+        assert_eq!(printed(&mut session, ":reset"), "");
+        assert_eq!(printed(&mut session, ":vars"), "No variables are defined\n");
+        assert_eq!(
+            printed(&mut session, ":config"),
+            "position order: highest first\n\
+             maximum function depth: 10\n\
+             explode depth: 2\n"
+        );
+
+        // Function definitions aren't interpreted yet, so there is never anything to list
+        assert_eq!(
+            printed(&mut session, ":funcs"),
+            "Function definitions are not supported yet\n"
+        );
+
+        assert_eq!(session.enter(":quit"), Ok(Action::Quit));
+        assert_eq!(
+            session.enter(":nope"),
+            Err("Unknown command :nope, try :help".to_owned())
+        );
+        assert_eq!(session.enter(":load"), Err("Usage: :load FILE".to_owned()));
+    }
+
+    #[test]
+    fn test_session_load() {
+        let path = std::env::temp_dir().join(format!("some-dice-repl-{}.dice", process::id()));
+        fs::write(&path, "X: 2\noutput X").unwrap();
+
+        let mut session = Session::new();
+        let loaded = printed(&mut session, &format!(":load {}", path.display()));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            loaded,
+            "output 1\n\
+             mean 2.00, deviation 0.00, min 2, max 2, median 2, mode 2\n\
+             2  100.00%\n"
+        );
+        assert_eq!(session.interpreter.variables().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_session_errors_keep_earlier_outputs() {
+        let mut session = Session::new();
+
+        assert_eq!(
+            session.enter("output 1 output 1 / 0"),
+            Err("output 1\n\
+                 mean 1.00, deviation 0.00, min 1, max 1, median 1, mode 1\n\
+                 1  100.00%\n\
+                 Division by zero"
+                .to_owned())
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Rolling expressions by sampling their exact outcomes
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use some_dice::ast::functional;
+use some_dice::interpret::interpreter::Interpreter;
+use some_dice::interpret::joint::JointSampler;
+use some_dice::interpret::output::Output;
+use some_dice::parse;
+use some_dice::sample::distribution::OutputDistribution;
+use some_dice::sample::verify;
+
+/// Evaluate an expression exactly and roll it `count` times
+pub(crate) fn roll<R: rand::Rng>(
+    expression: &str,
+    count: usize,
+    rng: &mut R,
+) -> Result<(Output, Vec<i32>), String> {
+    let expression = parse::parse_expression(expression)
+        .map_err(|err| err.diagnostic(expression).to_string())?;
+    let output = Interpreter::new()
+        .run_expression(expression)
+        .map_err(|err| err.to_string())?;
+    let distribution =
+        OutputDistribution::new(output.outcomes().clone()).map_err(|err| err.to_string())?;
+    let rolls = distribution.sample_many(count, rng);
+
+    Ok((output, rolls))
+}
+
+/// Roll an expression `count` times, recording each die rolled and each value along the way
+///
+/// Text transcripts are separated by blank lines, and JSON transcripts are collected in an array.
+pub(crate) fn transcripts<R: rand::Rng>(
+    expression: &str,
+    count: usize,
+    json: bool,
+    rng: &mut R,
+) -> Result<String, String> {
+    let expression = parse::parse_expression(expression)
+        .map_err(|err| err.diagnostic(expression).to_string())?;
+    let sampler = JointSampler::new(functional::program(vec![functional::output(
+        expression, None,
+    )]));
+
+    let transcripts = (0..count)
+        .map(|_| sampler.transcribe(rng).map_err(|err| err.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(if json {
+        format!(
+            "[{}]\n",
+            transcripts
+                .iter()
+                .map(|transcript| transcript.to_json())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    } else {
+        transcripts
+            .iter()
+            .map(|transcript| transcript.to_text())
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
+
+/// Each roll on its own line
+pub(crate) fn rolls(rolls: &[i32]) -> String {
+    rolls.iter().map(|roll| format!("{}\n", roll)).collect()
+}
+
+/// How often each value was rolled compared to how often it should have been
+pub(crate) fn stats(output: &Output, rolls: &[i32]) -> String {
+    let mut counts = BTreeMap::new();
+    for &roll in rolls {
+        *counts.entry(roll).or_insert(0usize) += 1;
+    }
+
+    let probabilities = output.probabilities();
+    let value_width = probabilities
+        .iter()
+        .map(|(value, _)| value.to_string().len())
+        .chain(["value".len()])
+        .max()
+        .unwrap_or(0);
+    let count_width = rolls.len().to_string().len().max("count".len());
+
+    let mut text = format!(
+        "{:>value_width$}  {:>count_width$}  {:>8}  {:>8}\n",
+        "value", "count", "observed", "expected"
+    );
+    for (value, probability) in probabilities {
+        let count = counts.get(&value).copied().unwrap_or(0);
+        let observed = if rolls.is_empty() {
+            0.0
+        } else {
+            count as f64 / rolls.len() as f64
+        };
+
+        writeln!(
+            text,
+            "{:>value_width$}  {:>count_width$}  {:>7.2}%  {:>7.2}%",
+            value,
+            count,
+            observed * 100.0,
+            probability.to_f64() * 100.0
+        )
+        .expect("writing to a string to succeed");
+    }
+
+    let observed_mean = if rolls.is_empty() {
+        0.0
+    } else {
+        rolls.iter().map(|&roll| roll as f64).sum::<f64>() / rolls.len() as f64
+    };
+    writeln!(
+        text,
+        "mean {:.2}, expected {:.2}",
+        observed_mean,
+        output.mean().map_or(0.0, |mean| mean.to_f64())
+    )
+    .expect("writing to a string to succeed");
+
+    text
+}
+
+/// Goodness-of-fit tests of the rolls against their exact chances
+pub(crate) fn verify(output: &Output, rolls: &[i32]) -> Result<String, String> {
+    let verification =
+        verify::verify_samples(output.outcomes(), rolls).map_err(|err| err.to_string())?;
+
+    Ok(format!("{}\n", verification))
+}
+
+#[cfg(test)]
+mod tests {
+    use some_dice::sample::rng;
+
+    use super::*;
+
+    #[test]
+    fn test_roll() {
+        let mut rng = rng::seeded(42);
+        let (output, rolled) = roll("3d6 + 2", 100, &mut rng).unwrap();
+
+        assert_eq!(rolled.len(), 100);
+        assert!(rolled.iter().all(|roll| (5..=20).contains(roll)));
+        assert_eq!(output.minimum(), Some(5));
+
+        let mut rng = rng::seeded(42);
+        assert_eq!(roll("3d6 + 2", 100, &mut rng).unwrap().1, rolled);
+
+        let mut rng = rng::seeded(42);
+        assert_eq!(
+            roll("3d6+2", 10, &mut rng).unwrap().1,
+            vec![14, 17, 12, 14, 11, 9, 11, 15, 15, 10]
+        );
+    }
+
+    #[test]
+    fn test_roll_errors() {
+        let mut rng = rng::seeded(0);
+
+        assert_eq!(
+            roll("X", 1, &mut rng).unwrap_err(),
+            "The variable X is not defined"
+        );
+        assert_eq!(
+            roll("3d", 1, &mut rng).unwrap_err(),
+            "unexpected end of input, expected an expression\n\
+             \x20--> line 1, column 3\n\
+             \x20 |\n\
+             1 | 3d\n\
+             \x20 |   ^"
+        );
+    }
+
+    #[test]
+    fn test_transcripts() {
+        assert_eq!(
+            transcripts("{1, 2}@3d6", 2, false, &mut rng::seeded(0)).unwrap(),
+            "roll 3d6: [5, 5, 4]\n\
+             {1, 2} @ [5, 5, 4] = [10], dropping [4]\n\
+             output 1 = 10\n\
+             \n\
+             roll 3d6: [5, 4, 1]\n\
+             {1, 2} @ [5, 4, 1] = [9], dropping [1]\n\
+             output 1 = 9\n"
+        );
+        assert_eq!(
+            transcripts("1d2", 1, true, &mut rng::seeded(0)).unwrap(),
+            "[{\"steps\":[\
+             {\"type\":\"roll\",\"count\":1,\"sides\":2,\"rolled\":{\"count\":1,\"dice\":[2]}},\
+             {\"type\":\"output\",\"name\":\"output 1\",\"value\":2}\
+             ],\"outputs\":[2]}]\n"
+        );
+    }
+
+    #[test]
+    fn test_rolls_and_stats() {
+        let mut rng = rng::seeded(0);
+        let (output, _) = roll("1d{1, 1, 2}", 0, &mut rng).unwrap();
+        let rolled = vec![1; 4];
+
+        assert_eq!(rolls(&rolled), "1\n1\n1\n1\n");
+        assert_eq!(
+            stats(&output, &rolled),
+            "value  count  observed  expected\n\
+             \x20   1      4   100.00%    66.67%\n\
+             \x20   2      0     0.00%    33.33%\n\
+             mean 1.00, expected 1.33\n"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut rng = rng::seeded(0);
+        let (output, rolled) = roll("1d{1, 1, 2}", 0, &mut rng).unwrap();
+        let rolled = [rolled, vec![1; 40]].concat();
+
+        assert_eq!(
+            verify(&output, &rolled).unwrap(),
+            "chi-square 20.0000 with 1 degrees of freedom, p-value 0.0000\n\
+             Kolmogorov-Smirnov 0.3333, p-value 0.0002\n"
+        );
+
+        let (output, rolled) = roll("3d6", 10_000, &mut rng).unwrap();
+        assert!(verify(&output, &rolled).unwrap().starts_with("chi-square "));
+    }
+}
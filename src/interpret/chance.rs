@@ -1,5 +1,3 @@
-use std::ops;
-
 use super::factor;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -8,46 +6,6 @@ pub(super) struct Chance {
     pub(super) denominator: usize,
 }
 
-impl ops::Mul<Self> for Chance {
-    type Output = Self;
-
-    fn mul(self, rhs: Self) -> Self::Output {
-        Self::new(
-            self.numerator * rhs.numerator,
-            self.denominator * rhs.denominator,
-        )
-    }
-}
-
-impl ops::Add<Self> for Chance {
-    type Output = Self;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        Self::new(
-            self.numerator * rhs.denominator + self.denominator * rhs.numerator,
-            self.denominator * rhs.denominator,
-        )
-    }
-}
-
-impl ops::Mul<usize> for Chance {
-    type Output = Self;
-
-    fn mul(self, rhs: usize) -> Self::Output {
-        Self::new(self.numerator * rhs, self.denominator)
-    }
-}
-
-impl ops::Div<usize> for Chance {
-    type Output = Self;
-
-    // Rational devision is implemented by multiplying the denominator
-    #[allow(clippy::suspicious_arithmetic_impl)]
-    fn div(self, rhs: usize) -> Self::Output {
-        Self::new(self.numerator, self.denominator * rhs)
-    }
-}
-
 impl Chance {
     pub(super) fn new(numerator: usize, denominator: usize) -> Chance {
         let mut result = Chance {
@@ -59,6 +17,39 @@ impl Chance {
         result
     }
 
+    /// The chance of both happening, or `None` if it is too small to be represented
+    pub(super) fn checked_mul(self, rhs: Self) -> Option<Self> {
+        // Cross simplify first to keep intermediate values small
+        let left_factor = factor::gcd(self.numerator, rhs.denominator).max(1);
+        let right_factor = factor::gcd(rhs.numerator, self.denominator).max(1);
+
+        Some(Self::new(
+            (self.numerator / left_factor).checked_mul(rhs.numerator / right_factor)?,
+            (self.denominator / right_factor).checked_mul(rhs.denominator / left_factor)?,
+        ))
+    }
+
+    /// The chance of either happening, or `None` if it can't be represented
+    pub(super) fn checked_add(self, rhs: Self) -> Option<Self> {
+        // Scale to the smallest common denominator to keep intermediate values small
+        let denominator = factor::checked_lcm(self.denominator, rhs.denominator)?;
+
+        Some(Self::new(
+            self.numerator
+                .checked_mul(denominator / self.denominator)?
+                .checked_add(rhs.numerator.checked_mul(denominator / rhs.denominator)?)?,
+            denominator,
+        ))
+    }
+
+    /// The chance of happening in any of `rhs` ways, or `None` if it can't be represented
+    pub(super) fn checked_scale(self, rhs: usize) -> Option<Self> {
+        Some(Self::new(
+            self.numerator.checked_mul(rhs)?,
+            self.denominator,
+        ))
+    }
+
     fn simplify(&mut self) {
         if self.denominator <= 1 {
             return;
@@ -106,29 +97,47 @@ mod tests {
 
     #[test]
     fn test_multiply_by_chance() {
-        assert_eq!(Chance::new(1, 2) * Chance::new(1, 1), Chance::new(1, 2));
-        assert_eq!(Chance::new(1, 2) * Chance::new(1, 3), Chance::new(1, 6));
-        assert_eq!(Chance::new(1, 3) * Chance::new(3, 4), Chance::new(1, 4));
+        let multiply = |left: Chance, right| left.checked_mul(right).unwrap();
+        assert_eq!(
+            multiply(Chance::new(1, 2), Chance::new(1, 1)),
+            Chance::new(1, 2)
+        );
+        assert_eq!(
+            multiply(Chance::new(1, 2), Chance::new(1, 3)),
+            Chance::new(1, 6)
+        );
+        assert_eq!(
+            multiply(Chance::new(1, 3), Chance::new(3, 4)),
+            Chance::new(1, 4)
+        );
     }
 
     #[test]
     fn test_add_to_chance() {
-        assert_eq!(Chance::new(1, 2) + Chance::new(1, 2), Chance::new(1, 1));
-        assert_eq!(Chance::new(1, 3) + Chance::new(1, 6), Chance::new(1, 2));
-        assert_eq!(Chance::new(1, 3) + Chance::new(1, 5), Chance::new(8, 15));
+        let add = |left: Chance, right| left.checked_add(right).unwrap();
+        assert_eq!(add(Chance::new(1, 2), Chance::new(1, 2)), Chance::new(1, 1));
+        assert_eq!(add(Chance::new(1, 3), Chance::new(1, 6)), Chance::new(1, 2));
+        assert_eq!(
+            add(Chance::new(1, 3), Chance::new(1, 5)),
+            Chance::new(8, 15)
+        );
     }
 
     #[test]
     fn test_multiply_by_scalar() {
-        assert_eq!(Chance::new(1, 2) * 2, Chance::new(1, 1));
-        assert_eq!(Chance::new(1, 3) * 2, Chance::new(2, 3));
-        assert_eq!(Chance::new(1, 3) * 1, Chance::new(1, 3));
+        assert_eq!(Chance::new(1, 2).checked_scale(2), Some(Chance::new(1, 1)));
+        assert_eq!(Chance::new(1, 3).checked_scale(2), Some(Chance::new(2, 3)));
+        assert_eq!(Chance::new(1, 3).checked_scale(1), Some(Chance::new(1, 3)));
     }
 
     #[test]
-    fn test_divide_by_scalar() {
-        assert_eq!(Chance::new(1, 1) / 2, Chance::new(1, 2));
-        assert_eq!(Chance::new(2, 3) / 2, Chance::new(1, 3));
-        assert_eq!(Chance::new(1, 3) / 1, Chance::new(1, 3));
+    fn test_overflow() {
+        let tiny = Chance::new(1, usize::MAX / 2);
+        assert_eq!(tiny.checked_mul(Chance::new(1, 3)), None);
+        assert_eq!(tiny.checked_add(Chance::new(1, 3)), None);
+        assert_eq!(
+            Chance::new(usize::MAX / 2, usize::MAX).checked_scale(3),
+            None
+        );
     }
 }
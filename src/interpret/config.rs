@@ -1,3 +1,5 @@
+use super::error::InterpretError;
+
 #[derive(Clone, Debug, PartialEq)]
 pub(super) struct Configuration {
     pub(super) position_order: PositionOrder,
@@ -19,6 +21,45 @@ impl Configuration {
             explode_depth: 2,
         }
     }
+
+    /// Change a setting that takes a string, like `set "position order" to "lowest first"`
+    pub(super) fn set_string(
+        &mut self,
+        setting: String,
+        value: &str,
+    ) -> Result<(), InterpretError> {
+        match (setting.as_str(), value) {
+            ("position order", "highest first") => {
+                self.position_order = PositionOrder::HighestFirst
+            }
+            ("position order", "lowest first") => self.position_order = PositionOrder::LowestFirst,
+            ("position order" | "maximum function depth" | "explode depth", _) => {
+                return Err(InterpretError::InvalidSetting(setting))
+            }
+            _ => return Err(InterpretError::UnknownSetting(setting)),
+        }
+
+        Ok(())
+    }
+
+    /// Change a setting that takes a number, like `set "explode depth" to 3`
+    pub(super) fn set_number(&mut self, setting: String, value: i32) -> Result<(), InterpretError> {
+        let depth = match setting.as_str() {
+            "maximum function depth" | "explode depth" => {
+                u8::try_from(value).map_err(|_| InterpretError::InvalidSetting(setting.clone()))?
+            }
+            "position order" => return Err(InterpretError::InvalidSetting(setting)),
+            _ => return Err(InterpretError::UnknownSetting(setting)),
+        };
+
+        if setting == "explode depth" {
+            self.explode_depth = depth;
+        } else {
+            self.maximum_function_depth = depth;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
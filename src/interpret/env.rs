@@ -15,4 +15,15 @@ impl Environment {
     pub(super) fn get_value(&self, key: &str) -> Option<&value::Value> {
         self.values.get(key)
     }
+
+    pub(super) fn set_value(&mut self, key: String, value: value::Value) {
+        self.values.insert(key, value);
+    }
+
+    /// Names of all variables in alphabetical order
+    pub(super) fn names(&self) -> Vec<&str> {
+        let mut names = self.values.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
 }
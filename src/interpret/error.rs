@@ -0,0 +1,63 @@
+//! Errors that may be encountered while interpreting
+
+use std::error;
+use std::fmt;
+
+/// An error encountered while interpreting.
+///
+/// This is non-exhaustive and expected to grow as the interpreter matures.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterpretError {
+    /// A variable was referenced before being assigned
+    UndefinedVariable(String),
+    /// A function was called that is not defined
+    UndefinedFunction(String),
+    /// A statement is recognized, but cannot be run yet
+    UnsupportedStatement,
+    /// A configuration setting does not exist
+    UnknownSetting(String),
+    /// A configuration setting was given a value it does not accept
+    InvalidSetting(String),
+    /// A single number was required, but another type of value was given
+    ExpectedNumber,
+    /// Dice were given where they are not allowed
+    UnexpectedDice,
+    /// A division (or negative exponent) by zero was attempted
+    DivisionByZero,
+    /// A value grew too large to be represented
+    Overflow,
+    /// A simulation was given a confidence level that isn't between 0 and 1
+    InvalidConfidence,
+}
+
+impl error::Error for InterpretError {}
+
+impl fmt::Display for InterpretError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterpretError::UndefinedVariable(name) => {
+                write!(f, "The variable {} is not defined", name)
+            }
+            InterpretError::UndefinedFunction(name) => {
+                write!(f, "The function [{}] is not defined", name)
+            }
+            InterpretError::UnsupportedStatement => {
+                f.write_str("The statement is not supported yet")
+            }
+            InterpretError::UnknownSetting(setting) => {
+                write!(f, "The setting \"{}\" does not exist", setting)
+            }
+            InterpretError::InvalidSetting(setting) => {
+                write!(f, "The setting \"{}\" was given an invalid value", setting)
+            }
+            InterpretError::ExpectedNumber => f.write_str("A single number was expected"),
+            InterpretError::UnexpectedDice => f.write_str("Dice are not allowed here"),
+            InterpretError::DivisionByZero => f.write_str("Division by zero"),
+            InterpretError::Overflow => f.write_str("A value is too large to be represented"),
+            InterpretError::InvalidConfidence => {
+                f.write_str("The confidence must be between 0 and 1 exclusive")
+            }
+        }
+    }
+}
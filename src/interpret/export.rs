@@ -0,0 +1,259 @@
+//! Serialization of [outputs][0] into formats for other tools
+//!
+//! [0]: super::output::Outputs
+
+use std::fmt::Write;
+
+use super::output::{Output, Outputs};
+
+impl Outputs {
+    /// Export as CSV with a header and one row per value per output
+    ///
+    /// Columns are `output`, `value`, `weight`, and `probability` (between 0 and 1).
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("output,value,weight,probability\n");
+
+        for (name, output) in self.names().zip(self.outcomes()) {
+            let total = total_weight(output);
+            for outcome in output.outcomes() {
+                writeln!(
+                    csv,
+                    "{},{},{},{}",
+                    csv_field(&name),
+                    outcome.value,
+                    outcome.weight,
+                    probability(outcome.weight, total),
+                )
+                .expect("writing to a string to succeed");
+            }
+        }
+
+        csv
+    }
+
+    /// Export as a JSON document
+    ///
+    /// The document is an object with an `outputs` array.
+    /// Each entry has a `name` (`null` if unnamed), parallel `values`, `weights`, and `probabilities` arrays,
+    /// and a `summary` object (`null` if the output has no weight).
+    pub fn to_json(&self) -> String {
+        let outputs = self
+            .outcomes()
+            .iter()
+            .map(output_to_json)
+            .collect::<Vec<_>>();
+
+        format!("{{\"outputs\":[{}]}}", outputs.join(","))
+    }
+
+    /// Export as the tab-separated text shown by AnyDice's export view
+    ///
+    /// Each output starts with a line of its quoted name, mean, standard deviation, minimum, and maximum,
+    /// then a `#`/`%` header, and then one line per value with its percentage.
+    /// Outputs are separated by a blank line.
+    pub fn to_anydice_text(&self) -> String {
+        self.names()
+            .zip(self.outcomes())
+            .map(|(name, output)| {
+                let mut text = String::new();
+
+                match output.summary() {
+                    Some(summary) => writeln!(
+                        text,
+                        "\"{}\"\t{}\t{}\t{}\t{}",
+                        name,
+                        summary.mean.to_f64(),
+                        summary.standard_deviation.unwrap_or(f64::NAN),
+                        summary.minimum,
+                        summary.maximum,
+                    ),
+                    None => writeln!(text, "\"{}\"", name),
+                }
+                .expect("writing to a string to succeed");
+
+                text.push_str("#\t%\n");
+                for (value, chance) in output.probabilities() {
+                    writeln!(text, "{}\t{}", value, chance.to_f64() * 100.0)
+                        .expect("writing to a string to succeed");
+                }
+
+                text
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn total_weight(output: &Output) -> u128 {
+    output
+        .outcomes()
+        .iter()
+        .map(|outcome| outcome.weight as u128)
+        .sum()
+}
+
+fn probability(weight: usize, total: u128) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        weight as f64 / total as f64
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+pub(super) fn json_string(string: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => {
+                write!(escaped, "\\u{:04x}", c as u32).expect("writing to a string to succeed")
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+
+    escaped
+}
+
+fn json_number(number: f64) -> String {
+    // JSON has no representation for NaN or infinities
+    if number.is_finite() {
+        format!("{}", number)
+    } else {
+        "null".to_owned()
+    }
+}
+
+fn json_array<T>(items: impl Iterator<Item = T>, to_json: impl Fn(T) -> String) -> String {
+    format!("[{}]", items.map(to_json).collect::<Vec<_>>().join(","))
+}
+
+fn output_to_json(output: &Output) -> String {
+    let total = total_weight(output);
+
+    let name = output.name().map_or("null".to_owned(), json_string);
+    let values = json_array(output.outcomes().iter(), |outcome| {
+        outcome.value.to_string()
+    });
+    let weights = json_array(output.outcomes().iter(), |outcome| {
+        outcome.weight.to_string()
+    });
+    let probabilities = json_array(output.outcomes().iter(), |outcome| {
+        json_number(probability(outcome.weight, total))
+    });
+    let summary = match output.summary() {
+        Some(summary) => format!(
+            "{{\"mean\":{},\"variance\":{},\"standard_deviation\":{},\"minimum\":{},\"maximum\":{},\"modes\":{},\"median\":{}}}",
+            json_number(summary.mean.to_f64()),
+            json_number(summary.variance.map_or(f64::NAN, |variance| variance.to_f64())),
+            json_number(summary.standard_deviation.unwrap_or(f64::NAN)),
+            summary.minimum,
+            summary.maximum,
+            json_array(summary.modes.iter(), |mode| mode.to_string()),
+            summary.median,
+        ),
+        None => "null".to_owned(),
+    };
+
+    format!(
+        "{{\"name\":{},\"values\":{},\"weights\":{},\"probabilities\":{},\"summary\":{}}}",
+        name, values, weights, probabilities, summary
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outcome::Outcome;
+
+    fn outputs() -> Outputs {
+        let mut outputs = Outputs::new();
+        outputs.add_output(Output::new(
+            vec![
+                Outcome {
+                    value: 1,
+                    weight: 1,
+                },
+                Outcome {
+                    value: 2,
+                    weight: 3,
+                },
+            ],
+            None,
+        ));
+        outputs.add_output(Output::new(
+            vec![Outcome {
+                value: -1,
+                weight: 2,
+            }],
+            Some("say \"hi\", bye".to_owned()),
+        ));
+
+        outputs
+    }
+
+    #[test]
+    fn test_to_csv() {
+        assert_eq!(
+            outputs().to_csv(),
+            "output,value,weight,probability\n\
+             output 1,1,1,0.25\n\
+             output 1,2,3,0.75\n\
+             \"say \"\"hi\"\", bye\",-1,2,1\n"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(
+            outputs().to_json(),
+            concat!(
+                r#"{"outputs":["#,
+                r#"{"name":null,"values":[1,2],"weights":[1,3],"probabilities":[0.25,0.75],"#,
+                r#""summary":{"mean":1.75,"variance":0.1875,"standard_deviation":0.4330127018922193,"minimum":1,"maximum":2,"modes":[2],"median":2}},"#,
+                r#"{"name":"say \"hi\", bye","values":[-1],"weights":[2],"probabilities":[1],"#,
+                r#""summary":{"mean":-1,"variance":0,"standard_deviation":0,"minimum":-1,"maximum":-1,"modes":[-1],"median":-1}}"#,
+                r#"]}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_json_without_weight() {
+        let mut outputs = Outputs::new();
+        outputs.add_output(Output::new(vec![], Some("\\\n".to_owned())));
+
+        assert_eq!(
+            outputs.to_json(),
+            r#"{"outputs":[{"name":"\\\n","values":[],"weights":[],"probabilities":[],"summary":null}]}"#
+        );
+    }
+
+    #[test]
+    fn test_to_anydice_text() {
+        assert_eq!(
+            outputs().to_anydice_text(),
+            "\"output 1\"\t1.75\t0.4330127018922193\t1\t2\n\
+             #\t%\n\
+             1\t25\n\
+             2\t75\n\
+             \n\
+             \"say \"hi\", bye\"\t-1\t0\t-1\t-1\n\
+             #\t%\n\
+             -1\t100\n"
+        );
+    }
+}
@@ -5,42 +5,50 @@
 // Apache-2.0: https://github.com/rust-num/num-integer/blob/03640c2a9472fad6f40845ab29c7c9502935d1d3/LICENSE-APACHE
 
 // https://github.com/rust-num/num-integer/blob/03640c2a9472fad6f40845ab29c7c9502935d1d3/src/lib.rs#L868-L895
-pub(super) fn gcd(mut first: usize, mut second: usize) -> usize {
-    let ones = first | second;
-    if first == 0 || second == 0 {
-        return ones;
-    }
-
-    let shift = ones.trailing_zeros();
-    first >>= first.trailing_zeros();
-    second >>= second.trailing_zeros();
+macro_rules! gcd_impl {
+    ($name: ident, $type: ty) => {
+        pub(super) fn $name(mut first: $type, mut second: $type) -> $type {
+            let ones = first | second;
+            if first == 0 || second == 0 {
+                return ones;
+            }
 
-    while first != second {
-        if first > second {
-            first -= second;
+            let shift = ones.trailing_zeros();
             first >>= first.trailing_zeros();
-        } else {
-            second -= first;
             second >>= second.trailing_zeros();
-        }
-    }
 
-    first << shift
+            while first != second {
+                if first > second {
+                    first -= second;
+                    first >>= first.trailing_zeros();
+                } else {
+                    second -= first;
+                    second >>= second.trailing_zeros();
+                }
+            }
+
+            first << shift
+        }
+    };
 }
 
+gcd_impl!(gcd, usize);
+gcd_impl!(gcd_u128, u128);
+
 // https://github.com/rust-num/num-integer/blob/03640c2a9472fad6f40845ab29c7c9502935d1d3/src/lib.rs#L909-L913
-pub(super) fn lcm(first: usize, second: usize) -> usize {
-    gcd_lcm(first, second).1
+/// The least common multiple, or `None` if it doesn't fit
+pub(super) fn checked_lcm(first: usize, second: usize) -> Option<usize> {
+    gcd_lcm(first, second).map(|(_, lcm)| lcm)
 }
 
 // https://github.com/rust-num/num-integer/blob/03640c2a9472fad6f40845ab29c7c9502935d1d3/src/lib.rs#L915-L925
-fn gcd_lcm(first: usize, second: usize) -> (usize, usize) {
+fn gcd_lcm(first: usize, second: usize) -> Option<(usize, usize)> {
     if first == 0 && second == 0 {
-        return (0, 0);
+        return Some((0, 0));
     }
     let gcd = gcd(first, second);
-    let lcm = first * (second / gcd);
-    (gcd, lcm)
+    let lcm = first.checked_mul(second / gcd)?;
+    Some((gcd, lcm))
 }
 
 #[cfg(test)]
@@ -58,15 +66,24 @@ mod tests {
         assert_eq!(gcd(56, 42), 14);
     }
 
+    #[test]
+    fn test_gcd_u128() {
+        assert_eq!(gcd_u128(0, 0), 0);
+        assert_eq!(gcd_u128(0, 3), 3);
+        assert_eq!(gcd_u128(56, 42), 14);
+        assert_eq!(gcd_u128(u128::MAX, 3), 3);
+    }
+
     #[test]
     fn test_lcm() {
-        assert_eq!(lcm(0, 0), 0);
-        assert_eq!(lcm(0, 3), 0);
-        assert_eq!(lcm(3, 0), 0);
-        assert_eq!(lcm(3, 3), 3);
-        assert_eq!(lcm(11, 22), 22);
-        assert_eq!(lcm(8, 9), 72);
-        assert_eq!(lcm(11, 5), 55);
-        assert_eq!(lcm(15, 17), 255);
+        assert_eq!(checked_lcm(0, 0), Some(0));
+        assert_eq!(checked_lcm(0, 3), Some(0));
+        assert_eq!(checked_lcm(3, 0), Some(0));
+        assert_eq!(checked_lcm(3, 3), Some(3));
+        assert_eq!(checked_lcm(11, 22), Some(22));
+        assert_eq!(checked_lcm(8, 9), Some(72));
+        assert_eq!(checked_lcm(11, 5), Some(55));
+        assert_eq!(checked_lcm(15, 17), Some(255));
+        assert_eq!(checked_lcm(usize::MAX, 2), None);
     }
 }
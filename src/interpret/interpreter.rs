@@ -1,4 +1,5 @@
-use super::{config, env, output};
+use super::error::InterpretError;
+use super::{config, env, output, value};
 use crate::ast;
 
 /// An interpreter for AnyDice with state for configuration, variable, functions, and outputs
@@ -6,25 +7,410 @@ use crate::ast;
 pub struct Interpreter {
     environment: env::Environment,
     configuration: config::Configuration,
+    outputs: output::Outputs,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Default::default()
+        Self {
+            environment: env::Environment::new(),
+            configuration: config::Configuration::new(),
+            outputs: output::Outputs::new(),
+        }
+    }
+
+    /// All outputs produced so far, in the order they were produced
+    pub fn outputs(&self) -> &output::Outputs {
+        &self.outputs
+    }
+
+    /// The current value of each variable as an output named after it, in alphabetical order
+    pub fn variables(&self) -> Result<Vec<output::Output>, InterpretError> {
+        self.environment
+            .names()
+            .into_iter()
+            .filter_map(|name| {
+                self.environment.get_value(name).map(|value| {
+                    Ok(output::Output::new(
+                        value.clone().try_into()?,
+                        Some(name.to_owned()),
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// The current value of each configuration setting, named as they are in `set` statements
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "position order",
+                match self.configuration.position_order {
+                    config::PositionOrder::HighestFirst => "highest first".to_owned(),
+                    config::PositionOrder::LowestFirst => "lowest first".to_owned(),
+                },
+            ),
+            (
+                "maximum function depth",
+                self.configuration.maximum_function_depth.to_string(),
+            ),
+            (
+                "explode depth",
+                self.configuration.explode_depth.to_string(),
+            ),
+        ]
     }
 
     /// Evaluate many statements
-    pub fn run_program(&mut self, program: ast::Program) {
-        todo!("Run a full program")
+    ///
+    /// Stops at the first statement that fails, keeping the effects of the statements before it.
+    pub fn run_program(&mut self, program: ast::Program) -> Result<(), InterpretError> {
+        program
+            .statements
+            .into_iter()
+            .try_for_each(|statement| self.run_statement(statement))
     }
 
     /// Evaluate a single statement
-    pub fn run_statement(&mut self, statement: ast::Statement) {
-        todo!("Run a single statement")
+    pub fn run_statement(&mut self, statement: ast::Statement) -> Result<(), InterpretError> {
+        match statement {
+            ast::Statement::VariableAssignment { name, value, .. } => {
+                let value = self.evaluate(&value)?;
+                self.environment.set_value(name, value);
+            }
+            ast::Statement::Output { value, name, .. } => {
+                let output = output::Output::new(self.evaluate(&value)?.try_into()?, name);
+                self.outputs.add_output(output);
+            }
+            ast::Statement::ConfigureString { setting, value, .. } => {
+                self.configuration.set_string(setting, &value)?
+            }
+            ast::Statement::ConfigureExpression { setting, value, .. } => {
+                let value = self.evaluate(&value)?.as_number()?;
+                self.configuration.set_number(setting, value)?
+            }
+            ast::Statement::FunctionDefinition { .. } => {
+                return Err(InterpretError::UnsupportedStatement)
+            }
+        }
+
+        Ok(())
     }
 
     /// Evaluate a single expression
-    pub fn run_expression(&self, expression: ast::Expression) -> output::Output {
-        todo!("Run a single Expression")
+    pub fn run_expression(
+        &self,
+        expression: ast::Expression,
+    ) -> Result<output::Output, InterpretError> {
+        Ok(output::Output::new(
+            self.evaluate(&expression)?.try_into()?,
+            None,
+        ))
+    }
+
+    pub(super) fn evaluate(
+        &self,
+        expression: &ast::Expression,
+    ) -> Result<value::Value, InterpretError> {
+        match expression {
+            ast::Expression::Integer { value, .. } => Ok(value::Value::number(*value)),
+            ast::Expression::VariableReference { name, .. } => self
+                .environment
+                .get_value(name)
+                .cloned()
+                .ok_or_else(|| InterpretError::UndefinedVariable(name.clone())),
+            ast::Expression::Sequence { entries, .. } => {
+                let mut values = vec![];
+                for entry in entries {
+                    let (entry_values, repetitions) = match entry {
+                        ast::SequenceEntry::Single {
+                            value, repetitions, ..
+                        } => (self.evaluate(value)?.into_sequence_entries()?, repetitions),
+                        ast::SequenceEntry::Range {
+                            start,
+                            end,
+                            repetitions,
+                            ..
+                        } => {
+                            let start = self.evaluate(start)?.as_number()?;
+                            let end = self.evaluate(end)?.as_number()?;
+                            let range = if start <= end {
+                                (start..=end).collect()
+                            } else {
+                                (end..=start).rev().collect()
+                            };
+
+                            (range, repetitions)
+                        }
+                    };
+
+                    // Negative repetitions are treated as none
+                    let repetitions = self.evaluate(repetitions)?.as_number()?.max(0);
+                    for _ in 0..repetitions {
+                        values.extend_from_slice(&entry_values);
+                    }
+                }
+
+                Ok(value::Value::sequence(values))
+            }
+            ast::Expression::UnaryOperation {
+                operator, operand, ..
+            } => self.evaluate(operand)?.unary(*operator),
+            ast::Expression::BinaryOperation {
+                operator,
+                left,
+                right,
+                ..
+            } => value::Value::binary(
+                *operator,
+                self.evaluate(left)?,
+                self.evaluate(right)?,
+                &self.configuration.position_order,
+            ),
+            ast::Expression::FunctionCall { name, .. } => {
+                Err(InterpretError::UndefinedFunction(name.clone()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::functional::*;
+    use crate::outcome::Outcome;
+
+    fn outcomes(pairs: &[(i32, usize)]) -> Vec<Outcome> {
+        pairs
+            .iter()
+            .map(|&(value, weight)| Outcome { value, weight })
+            .collect()
+    }
+
+    #[test]
+    fn test_run_expression() {
+        assert_eq!(
+            Interpreter::new()
+                .run_expression(add(
+                    Box::new(dice(Box::new(integer(2)), Box::new(integer(3)))),
+                    Box::new(integer(1)),
+                ))
+                .unwrap()
+                .outcomes(),
+            &outcomes(&[(3, 1), (4, 2), (5, 3), (6, 2), (7, 1)])
+        );
+    }
+
+    #[test]
+    fn test_run_expression_sequence() {
+        assert_eq!(
+            Interpreter::new()
+                .run_expression(sequence(vec![
+                    single_entry(integer(1), integer(2)),
+                    range_entry(integer(4), integer(3), integer(1)),
+                    single_entry(
+                        sequence(vec![range_entry(integer(5), integer(6), integer(1))]),
+                        integer(1)
+                    ),
+                    single_entry(integer(9), integer(-1)),
+                ]))
+                .unwrap()
+                .outcomes(),
+            &outcomes(&[(1, 1), (1, 1), (4, 1), (3, 1), (5, 1), (6, 1)])
+        );
+    }
+
+    #[test]
+    fn test_run_expression_errors() {
+        let interpreter = Interpreter::new();
+
+        assert_eq!(
+            interpreter.run_expression(variable_reference("X".to_owned())),
+            Err(InterpretError::UndefinedVariable("X".to_owned()))
+        );
+        assert_eq!(
+            interpreter.run_expression(function_call("highest ? of ?".to_owned(), vec![])),
+            Err(InterpretError::UndefinedFunction(
+                "highest ? of ?".to_owned()
+            ))
+        );
+        assert_eq!(
+            interpreter.run_expression(sequence(vec![single_entry(
+                dice(Box::new(integer(1)), Box::new(integer(6))),
+                integer(1)
+            )])),
+            Err(InterpretError::UnexpectedDice)
+        );
+        assert_eq!(
+            interpreter.run_expression(sequence(vec![single_entry(integer(1), sequence(vec![]))])),
+            Err(InterpretError::ExpectedNumber)
+        );
+    }
+
+    #[test]
+    fn test_run_expression_large_pools() {
+        let interpreter = Interpreter::new();
+        let pool = |count| dice(Box::new(integer(count)), Box::new(integer(6)));
+
+        assert!(interpreter.run_expression(pool(20)).is_ok());
+        assert_eq!(
+            interpreter.run_expression(pool(30)),
+            Err(InterpretError::Overflow)
+        );
+        assert_eq!(
+            interpreter.run_expression(add(Box::new(pool(30)), Box::new(integer(1)))),
+            Err(InterpretError::Overflow)
+        );
+        assert_eq!(
+            interpreter.run_expression(access(Box::new(integer(1)), Box::new(pool(30)))),
+            Err(InterpretError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_run_expression_large_sides() {
+        let interpreter = Interpreter::new();
+        let smallest = || subtract(Box::new(integer(-i32::MAX)), Box::new(integer(1)));
+
+        assert_eq!(
+            interpreter.run_expression(dice(
+                Box::new(integer(2)),
+                Box::new(sequence(vec![single_entry(
+                    integer(2_000_000_000),
+                    integer(1)
+                )]))
+            )),
+            Err(InterpretError::Overflow)
+        );
+        assert_eq!(
+            interpreter.run_expression(dice(Box::new(smallest()), Box::new(integer(6)))),
+            Err(InterpretError::Overflow)
+        );
+        assert_eq!(
+            interpreter.run_expression(negate(Box::new(dice(
+                Box::new(integer(1)),
+                Box::new(sequence(vec![single_entry(smallest(), integer(1))]))
+            )))),
+            Err(InterpretError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_run_program() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .run_program(program(vec![
+                output(dice(Box::new(integer(1)), Box::new(integer(2))), None),
+                output(integer(3), Some("three".to_owned())),
+            ]))
+            .unwrap();
+
+        let outputs = interpreter.outputs().outcomes();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].outcomes(), &outcomes(&[(1, 1), (2, 1)]));
+        assert_eq!(outputs[0].name(), None);
+        assert_eq!(outputs[1].outcomes(), &outcomes(&[(3, 1)]));
+        assert_eq!(outputs[1].name(), Some("three"));
+    }
+
+    #[test]
+    fn test_run_program_variables() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .run_program(program(vec![
+                variable_assignment("Y".to_owned(), integer(2)),
+                variable_assignment(
+                    "X".to_owned(),
+                    dice(
+                        Box::new(variable_reference("Y".to_owned())),
+                        Box::new(integer(2)),
+                    ),
+                ),
+                output(variable_reference("X".to_owned()), None),
+            ]))
+            .unwrap();
+
+        assert_eq!(
+            interpreter.outputs().outcomes()[0].outcomes(),
+            &outcomes(&[(2, 1), (3, 2), (4, 1)])
+        );
+        assert_eq!(
+            interpreter.variables().unwrap(),
+            vec![
+                output::Output::new(outcomes(&[(2, 1), (3, 2), (4, 1)]), Some("X".to_owned())),
+                output::Output::new(outcomes(&[(2, 1)]), Some("Y".to_owned())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_program_stops_at_error() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            interpreter.run_program(program(vec![
+                output(integer(1), None),
+                output(divide(Box::new(integer(1)), Box::new(integer(0))), None),
+                output(integer(2), None),
+            ])),
+            Err(InterpretError::DivisionByZero)
+        );
+        assert_eq!(interpreter.outputs().outcomes().len(), 1);
+    }
+
+    #[test]
+    fn test_run_statement_configure() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .run_statement(configure_string(
+                "position order".to_owned(),
+                "lowest first".to_owned(),
+            ))
+            .unwrap();
+        interpreter
+            .run_statement(configure_expression("explode depth".to_owned(), integer(3)))
+            .unwrap();
+        interpreter
+            .run_statement(configure_expression(
+                "maximum function depth".to_owned(),
+                integer(20),
+            ))
+            .unwrap();
+
+        assert_eq!(
+            interpreter.settings(),
+            vec![
+                ("position order", "lowest first".to_owned()),
+                ("maximum function depth", "20".to_owned()),
+                ("explode depth", "3".to_owned()),
+            ]
+        );
+        assert_eq!(
+            interpreter.configuration,
+            config::Configuration {
+                position_order: config::PositionOrder::LowestFirst,
+                maximum_function_depth: 20,
+                explode_depth: 3,
+            }
+        );
+
+        assert_eq!(
+            interpreter.run_statement(configure_string(
+                "position order".to_owned(),
+                "sideways".to_owned(),
+            )),
+            Err(InterpretError::InvalidSetting("position order".to_owned()))
+        );
+        assert_eq!(
+            interpreter.run_statement(configure_expression(
+                "explode depth".to_owned(),
+                integer(-1),
+            )),
+            Err(InterpretError::InvalidSetting("explode depth".to_owned()))
+        );
+        assert_eq!(
+            interpreter.run_statement(configure_string("color".to_owned(), "red".to_owned(),)),
+            Err(InterpretError::UnknownSetting("color".to_owned()))
+        );
     }
 }
@@ -0,0 +1,573 @@
+//! Sampling all outputs of a program together
+//!
+//! Sampling each output on its own loses how outputs relate to each other. In a program like
+//! `X: 3d6 output X output X >= 10`, both outputs describe the same roll, so a trial where the
+//! first output is 12 must have 1 for the second. A [`JointSampler`] runs the whole program for
+//! each trial, rolling every die once, so that all outputs from a trial come from the same rolls.
+
+use std::collections;
+use std::iter;
+use std::mem;
+use std::rc::Rc;
+
+use super::config;
+use super::error::InterpretError;
+use super::transcript::{self, Step, Transcript};
+use super::value::rolled::Rolled;
+use crate::ast::{self, visit};
+use crate::sample::rng;
+
+/// Runs a program with random rolls instead of exact chances
+///
+/// Each variable is rolled once when it is assigned, and every later use of it in that trial sees
+/// the same roll. This includes using it as the sides of more dice, which then always show the
+/// value that was rolled. [`reroll_variables`][Self::reroll_variables] rolls each use instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointSampler {
+    program: ast::Program,
+    reroll_variables: bool,
+}
+
+impl JointSampler {
+    pub fn new(program: ast::Program) -> Self {
+        Self {
+            program,
+            reroll_variables: false,
+        }
+    }
+
+    /// Roll the dice of a variable again each time it is used, instead of once when it is assigned
+    ///
+    /// Every use is then independent, as it is in the [exact interpreter][0], so each output
+    /// follows its exact chances. Outputs from a trial no longer share the rolls of variables.
+    ///
+    /// [0]: super::interpreter::Interpreter
+    pub fn reroll_variables(mut self) -> Self {
+        self.reroll_variables = true;
+        self
+    }
+
+    /// The name of each output, in the order of the values in every trial
+    ///
+    /// Unnamed outputs are named by their 1-based position, like "output 1".
+    pub fn names(&self) -> Vec<String> {
+        self.output_names()
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| name.unwrap_or_else(|| format!("output {}", i + 1)))
+            .collect()
+    }
+
+    /// The name given to each output, if any
+    pub(super) fn output_names(&self) -> Vec<Option<String>> {
+        self.program
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                ast::Statement::Output { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Run the program once and give the value of every output, in the order they were output
+    pub fn sample<R: rand::Rng>(&self, rng: &mut R) -> Result<Vec<i32>, InterpretError> {
+        Ok(self.run(rng, None)?.outputs)
+    }
+
+    /// Run the program once like [`sample`][0], recording every die rolled and every value
+    /// along the way
+    ///
+    /// [0]: Self::sample
+    pub fn transcribe<R: rand::Rng>(&self, rng: &mut R) -> Result<Transcript, InterpretError> {
+        let trial = self.run(rng, Some(vec![]))?;
+
+        Ok(Transcript::new(
+            trial.steps.unwrap_or_default(),
+            trial.outputs,
+        ))
+    }
+
+    fn run<'a, R: rand::Rng>(
+        &self,
+        rng: &'a mut R,
+        steps: Option<Vec<Step>>,
+    ) -> Result<Trial<'a, R>, InterpretError> {
+        let mut trial = Trial {
+            variables: collections::HashMap::new(),
+            configuration: config::Configuration::new(),
+            outputs: vec![],
+            steps,
+            reroll_variables: self.reroll_variables,
+            rng,
+        };
+
+        for statement in &self.program.statements {
+            trial.run_statement(statement)?;
+        }
+
+        Ok(trial)
+    }
+
+    pub fn sample_many<R: rand::Rng>(
+        &self,
+        n: usize,
+        rng: &mut R,
+    ) -> Result<Vec<Vec<i32>>, InterpretError> {
+        iter::repeat_with(|| self.sample(rng)).take(n).collect()
+    }
+
+    /// Run the program `n` times using a [seeded generator][0], giving the same values for the
+    /// same seed
+    ///
+    /// [0]: crate::sample::rng::seeded
+    pub fn sample_many_seeded(&self, n: usize, seed: u64) -> Result<Vec<Vec<i32>>, InterpretError> {
+        self.sample_many(n, &mut rng::seeded(seed))
+    }
+}
+
+/// State for a single run of a program
+struct Trial<'a, R> {
+    variables: collections::HashMap<String, Variable>,
+    configuration: config::Configuration,
+    outputs: Vec<i32>,
+    /// Only kept when transcribing, as recording every step takes time
+    steps: Option<Vec<Step>>,
+    reroll_variables: bool,
+    rng: &'a mut R,
+}
+
+/// The value of a variable in a single run of a program
+#[derive(Clone)]
+enum Variable {
+    /// Rolled once when it was assigned
+    Rolled(Rolled),
+    /// Rolled again each time it is used
+    Rerolled(Rc<Assignment>),
+}
+
+/// An assignment with everything needed to evaluate it again as it was
+struct Assignment {
+    value: ast::Expression,
+    variables: collections::HashMap<String, Variable>,
+    configuration: config::Configuration,
+}
+
+/// Whether an expression rolls any dice, either itself or through a variable that is rerolled
+fn rolls_dice(
+    expression: &ast::Expression,
+    variables: &collections::HashMap<String, Variable>,
+) -> bool {
+    struct FindDice<'a> {
+        variables: &'a collections::HashMap<String, Variable>,
+        found: bool,
+    }
+
+    impl visit::Visitor for FindDice<'_> {
+        fn visit_expression(&mut self, expression: &ast::Expression) {
+            match expression {
+                ast::Expression::BinaryOperation {
+                    operator: ast::BinaryOperator::Dice,
+                    ..
+                } => self.found = true,
+                ast::Expression::VariableReference { name, .. } => {
+                    self.found |= matches!(self.variables.get(name), Some(Variable::Rerolled(_)))
+                }
+                _ => {}
+            }
+            visit::walk_expression(self, expression);
+        }
+    }
+
+    let mut finder = FindDice {
+        variables,
+        found: false,
+    };
+    visit::Visitor::visit_expression(&mut finder, expression);
+    finder.found
+}
+
+impl<R: rand::Rng> Trial<'_, R> {
+    fn record(&mut self, step: impl FnOnce() -> Step) {
+        if let Some(steps) = &mut self.steps {
+            steps.push(step());
+        }
+    }
+
+    fn run_statement(&mut self, statement: &ast::Statement) -> Result<(), InterpretError> {
+        match statement {
+            ast::Statement::VariableAssignment { name, value, .. } => {
+                let expression = value;
+                // Evaluated even when it will be rerolled, so that errors happen where it's assigned
+                let value = self.evaluate(expression)?;
+                self.record(|| Step::Assignment {
+                    name: name.clone(),
+                    value: (&value).into(),
+                });
+
+                let variable = if self.reroll_variables && rolls_dice(expression, &self.variables) {
+                    Variable::Rerolled(Rc::new(Assignment {
+                        value: expression.clone(),
+                        variables: self.variables.clone(),
+                        configuration: self.configuration.clone(),
+                    }))
+                } else {
+                    Variable::Rolled(value)
+                };
+                self.variables.insert(name.clone(), variable);
+            }
+            ast::Statement::Output { value, name, .. } => {
+                let value = self.evaluate(value)?.output(self.rng)?;
+                self.outputs.push(value);
+
+                let position = self.outputs.len();
+                self.record(|| Step::Output {
+                    name: name
+                        .clone()
+                        .unwrap_or_else(|| format!("output {}", position)),
+                    value,
+                });
+            }
+            ast::Statement::ConfigureString { setting, value, .. } => {
+                self.configuration.set_string(setting.clone(), value)?
+            }
+            ast::Statement::ConfigureExpression { setting, value, .. } => {
+                let value = self.evaluate(value)?.as_number()?;
+                self.configuration.set_number(setting.clone(), value)?
+            }
+            ast::Statement::FunctionDefinition { .. } => {
+                return Err(InterpretError::UnsupportedStatement)
+            }
+        }
+
+        Ok(())
+    }
+
+    fn evaluate(&mut self, expression: &ast::Expression) -> Result<Rolled, InterpretError> {
+        match expression {
+            ast::Expression::Integer { value, .. } => Ok(Rolled::Number(*value)),
+            ast::Expression::VariableReference { name, .. } => match self.variables.get(name) {
+                Some(Variable::Rolled(value)) => Ok(value.clone()),
+                Some(Variable::Rerolled(assignment)) => {
+                    let assignment = Rc::clone(assignment);
+                    let variables = mem::replace(&mut self.variables, assignment.variables.clone());
+                    let configuration =
+                        mem::replace(&mut self.configuration, assignment.configuration.clone());
+                    let value = self.evaluate(&assignment.value);
+                    self.variables = variables;
+                    self.configuration = configuration;
+
+                    value
+                }
+                None => Err(InterpretError::UndefinedVariable(name.clone())),
+            },
+            ast::Expression::Sequence { entries, .. } => {
+                let mut values = vec![];
+                for entry in entries {
+                    let (entry_values, repetitions) = match entry {
+                        ast::SequenceEntry::Single {
+                            value, repetitions, ..
+                        } => (self.evaluate(value)?.into_sequence_entries()?, repetitions),
+                        ast::SequenceEntry::Range {
+                            start,
+                            end,
+                            repetitions,
+                            ..
+                        } => {
+                            let start = self.evaluate(start)?.as_number()?;
+                            let end = self.evaluate(end)?.as_number()?;
+                            let range = if start <= end {
+                                (start..=end).collect()
+                            } else {
+                                (end..=start).rev().collect()
+                            };
+
+                            (range, repetitions)
+                        }
+                    };
+
+                    // Negative repetitions are treated as none
+                    let repetitions = self.evaluate(repetitions)?.as_number()?.max(0);
+                    for _ in 0..repetitions {
+                        values.extend_from_slice(&entry_values);
+                    }
+                }
+
+                Ok(Rolled::Sequence(values))
+            }
+            ast::Expression::UnaryOperation {
+                operator, operand, ..
+            } => {
+                let operand = self.evaluate(operand)?;
+                let operand_step = self
+                    .steps
+                    .as_ref()
+                    .map(|_| transcript::Value::from(&operand));
+                let result = operand.unary(*operator)?;
+
+                if let Some(operand) = operand_step {
+                    self.record(|| Step::Unary {
+                        operator: *operator,
+                        operand,
+                        result: (&result).into(),
+                    });
+                }
+
+                Ok(result)
+            }
+            ast::Expression::BinaryOperation {
+                operator,
+                left,
+                right,
+                ..
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                let operand_steps = self.steps.as_ref().map(|_| {
+                    (
+                        transcript::Value::from(&left),
+                        transcript::Value::from(&right),
+                    )
+                });
+                // Accessing dice drops the dice at any other position, which the step shows
+                let kept = match (operator, &left, &right) {
+                    (
+                        ast::BinaryOperator::Access,
+                        Rolled::Number(_) | Rolled::Sequence(_),
+                        Rolled::Dice { sides, .. },
+                    ) if self.steps.is_some() => Some(Rolled::kept(
+                        &left.clone().into_sequence_entries()?,
+                        sides,
+                        &self.configuration.position_order,
+                    )),
+                    _ => None,
+                };
+
+                let result = Rolled::binary(
+                    *operator,
+                    left,
+                    right,
+                    &self.configuration.position_order,
+                    self.rng,
+                )?;
+
+                if let Some((left, right)) = operand_steps {
+                    let result = transcript::Value::from(&result);
+                    self.record(|| match (operator, kept) {
+                        (ast::BinaryOperator::Dice, _) => Step::Roll {
+                            count: left,
+                            sides: right,
+                            rolled: result,
+                        },
+                        (_, Some(kept)) => Step::Access {
+                            positions: left,
+                            dice: right,
+                            kept,
+                            result,
+                        },
+                        (_, None) => Step::Binary {
+                            operator: *operator,
+                            left,
+                            right,
+                            result,
+                        },
+                    });
+                }
+
+                Ok(result)
+            }
+            ast::Expression::FunctionCall {
+                name, arguments, ..
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.evaluate(argument))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.record(|| Step::FunctionCall {
+                    name: name.clone(),
+                    arguments: arguments.iter().map(Into::into).collect(),
+                });
+
+                // Functions can't be defined yet, so there is nothing to call
+                Err(InterpretError::UndefinedFunction(name.clone()))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn sampler(program: &str) -> JointSampler {
+        JointSampler::new(parse::parse_program(program).unwrap())
+    }
+
+    #[test]
+    fn test_names() {
+        use crate::ast::functional::*;
+
+        assert_eq!(
+            JointSampler::new(program(vec![
+                variable_assignment("X".to_owned(), integer(1)),
+                output(variable_reference("X".to_owned()), None),
+                output(variable_reference("X".to_owned()), Some("again".to_owned())),
+                output(integer(2), None),
+            ]))
+            .names(),
+            vec!["output 1", "again", "output 3"]
+        );
+    }
+
+    #[test]
+    fn test_sample_correlated() {
+        let trials = sampler("X: 3d6 output X output X >= 10 output 1@X")
+            .sample_many_seeded(1000, 0)
+            .unwrap();
+
+        for trial in trials {
+            let [sum, high, highest] = trial[..] else {
+                panic!("expected three outputs, got {:?}", trial);
+            };
+
+            assert!((3..=18).contains(&sum));
+            assert_eq!(high, (sum >= 10) as i32);
+            assert!((1..=6).contains(&highest));
+            assert!(highest * 3 >= sum && highest + 2 <= sum);
+        }
+    }
+
+    #[test]
+    fn test_sample_independent_dice() {
+        // Dice written out separately are separate rolls, even when written the same way
+        let trials = sampler("output 1d1000 - 1d1000")
+            .sample_many_seeded(100, 0)
+            .unwrap();
+
+        assert!(trials.iter().any(|trial| trial[0] != 0));
+    }
+
+    #[test]
+    fn test_sample_seeded() {
+        let sampler = sampler("X: 2d6 output X output X * 2 output {1, 2, 3}");
+
+        assert_eq!(
+            sampler.sample_many_seeded(3, 42),
+            Ok(vec![vec![7, 14, 1], vec![11, 22, 2], vec![8, 16, 1]])
+        );
+        assert_eq!(
+            sampler.sample_many_seeded(3, 42),
+            sampler.sample_many(3, &mut rng::seeded(42))
+        );
+    }
+
+    #[test]
+    fn test_sample_matches_exact_means() {
+        use crate::interpret::interpreter::Interpreter;
+
+        for expression in [
+            "1@3d6",
+            "{1, 2}@4d6",
+            "3d{1, 1, 6}",
+            "(1d3)d6",
+            "2d6 >= 8",
+            "-2d4",
+        ] {
+            let exact = Interpreter::new()
+                .run_expression(parse::parse_expression(expression).unwrap())
+                .unwrap()
+                .mean()
+                .unwrap()
+                .to_f64();
+
+            let trials = sampler(&format!("output {}", expression))
+                .sample_many_seeded(20_000, 0)
+                .unwrap();
+            let mean = trials.iter().map(|trial| trial[0] as f64).sum::<f64>() / 20_000.0;
+
+            assert!(
+                (mean - exact).abs() < 0.1,
+                "{}: sampled mean {} is too far from {}",
+                expression,
+                mean,
+                exact
+            );
+        }
+    }
+
+    #[test]
+    fn test_reroll_variables() {
+        let trials = sampler("X: 1d6 Y: 2 Z: X + Y output X - X output Z - Z output Y - Y")
+            .reroll_variables()
+            .sample_many_seeded(1000, 0)
+            .unwrap();
+
+        assert!(trials.iter().any(|trial| trial[0] != 0));
+        assert!(trials.iter().any(|trial| trial[1] != 0));
+        assert!(trials.iter().all(|trial| trial[2] == 0));
+
+        // Rerolls use the settings from when the variable was assigned
+        let trials = sampler(
+            "set \"position order\" to \"lowest first\" X: 1@3d6 \
+             set \"position order\" to \"highest first\" output X",
+        )
+        .reroll_variables()
+        .sample_many_seeded(100, 0)
+        .unwrap();
+        assert!(trials.iter().filter(|trial| trial[0] == 6).count() < 10);
+    }
+
+    #[test]
+    fn test_sample_configuration() {
+        let trials = sampler("set \"position order\" to \"lowest first\" output 1@3d6")
+            .sample_many_seeded(100, 0)
+            .unwrap();
+
+        // The lowest of three dice is rarely a 6
+        assert!(trials.iter().filter(|trial| trial[0] == 6).count() < 10);
+    }
+
+    #[test]
+    fn test_transcribe() {
+        let transcript = sampler("X: {1, 2, 3}@4d6 output X + 1")
+            .transcribe(&mut rng::seeded(0))
+            .unwrap();
+
+        assert_eq!(
+            transcript.to_text(),
+            "roll 4d6: [5, 5, 4, 5]\n\
+             {1, 2, 3} @ [5, 5, 4, 5] = [15], dropping [4]\n\
+             X: [15]\n\
+             [15] + 1 = [16]\n\
+             output 1 = 16\n"
+        );
+        assert_eq!(transcript.outputs(), &vec![16]);
+    }
+
+    #[test]
+    fn test_transcribe_matches_sample() {
+        let sampler = sampler("X: 3d6 output X output 1@X output -X");
+
+        assert_eq!(
+            sampler.transcribe(&mut rng::seeded(7)).unwrap().outputs(),
+            &sampler.sample(&mut rng::seeded(7)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_sample_errors() {
+        assert_eq!(
+            sampler("output 1 output X").sample(&mut rng::seeded(0)),
+            Err(InterpretError::UndefinedVariable("X".to_owned()))
+        );
+        assert_eq!(
+            sampler("output {1d6}").sample(&mut rng::seeded(0)),
+            Err(InterpretError::UnexpectedDice)
+        );
+        assert_eq!(
+            sampler("output 1d6 / 0").sample(&mut rng::seeded(0)),
+            Err(InterpretError::DivisionByZero)
+        );
+    }
+}
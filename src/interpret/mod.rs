@@ -5,7 +5,20 @@
 mod chance;
 mod config;
 mod env;
+pub mod error;
+#[cfg(feature = "export")]
+pub mod export;
 mod factor;
 pub mod interpreter;
+#[cfg(feature = "sample")]
+pub mod joint;
+pub mod optimize;
 pub mod output;
+pub mod rational;
+pub mod render;
+#[cfg(feature = "sample")]
+pub mod simulate;
+pub mod svg;
+#[cfg(feature = "sample")]
+pub mod transcript;
 mod value;
@@ -0,0 +1,293 @@
+//! Simplifying expressions before they are interpreted
+//!
+//! Parts of an expression made only of literals work out the same every time, so they can be
+//! worked out once ahead of time:
+//!
+//! - Operations on numbers and sequences are folded, so `2 * 3 + 1` becomes `7`
+//! - Sequences are expanded, so `{1..3, 4:2}` becomes `{1, 2, 3, 4, 4}`
+//! - Dice with the sides `{1..N}` are written with a number of sides, so `1d{1..6}` becomes `1d6`
+//!
+//! Folding uses the interpreter itself, so the results follow AnyDice exactly, like division
+//! truncating toward zero. Anything that would fail, like a division by zero, is left as written
+//! so it fails when run. Dice are never rolled ahead of time, variables are never substituted, and
+//! accessing the digits of a number is left alone as it depends on the `position order` setting.
+
+use super::interpreter::Interpreter;
+use super::value::Value;
+use crate::ast::functional;
+use crate::ast::visit::{self, Fold};
+use crate::ast::{self, BinaryOperator, Expression, SequenceEntry};
+
+/// Sequences that would expand to more values than this are left as written, so that a short
+/// range like `{1..100000}` doesn't grow the tree
+const MAXIMUM_EXPANDED_LENGTH: usize = 1000;
+
+/// Fold every expression of a program
+pub fn optimize_program(program: ast::Program) -> ast::Program {
+    Optimizer::default().fold_program(program)
+}
+
+/// Fold every part of an expression
+pub fn optimize_expression(expression: Expression) -> Expression {
+    Optimizer::default().fold_expression(expression)
+}
+
+#[derive(Default)]
+struct Optimizer {
+    /// Only ever evaluates literals, so it never has any variables or settings
+    interpreter: Interpreter,
+}
+
+impl Fold for Optimizer {
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        let expression = visit::fold_expression(self, expression);
+
+        match &expression {
+            Expression::BinaryOperation {
+                operator: BinaryOperator::Dice,
+                ..
+            } => normalize_dice(expression),
+            Expression::BinaryOperation {
+                operator: BinaryOperator::Access,
+                right,
+                ..
+            } if as_literal_sequence(right).is_none() => expression,
+            Expression::Sequence { .. } if as_literal_sequence(&expression).is_some() => expression,
+            Expression::UnaryOperation { .. }
+            | Expression::BinaryOperation { .. }
+            | Expression::Sequence { .. }
+                if has_only_literal_children(&expression) =>
+            {
+                self.evaluate(expression)
+            }
+            _ => expression,
+        }
+    }
+}
+
+impl Optimizer {
+    /// The literal an expression works out to, or the expression as it was if it can't be one
+    fn evaluate(&self, expression: Expression) -> Expression {
+        let span = expression.span();
+        let literal = match self.interpreter.evaluate(&expression) {
+            Ok(value @ Value::Number(_)) => value.as_number().ok().map(functional::integer),
+            Ok(value @ Value::Sequence(_)) => value
+                .into_sequence_entries()
+                .ok()
+                .filter(|values| values.len() <= MAXIMUM_EXPANDED_LENGTH)
+                .map(|values| expanded_sequence(&values)),
+            Ok(Value::Dice(_)) | Err(_) => None,
+        };
+
+        match literal {
+            Some(literal) => literal.with_span(span),
+            None => expression,
+        }
+    }
+}
+
+/// Whether every operand, entry, and repetition of an expression is a literal
+fn has_only_literal_children(expression: &Expression) -> bool {
+    let is_literal = |expression: &Expression| {
+        as_integer(expression).is_some() || as_literal_sequence(expression).is_some()
+    };
+
+    match expression {
+        Expression::UnaryOperation { operand, .. } => is_literal(operand),
+        Expression::BinaryOperation { left, right, .. } => is_literal(left) && is_literal(right),
+        Expression::Sequence { entries, .. } => entries.iter().all(|entry| match entry {
+            SequenceEntry::Single {
+                value, repetitions, ..
+            } => is_literal(value) && is_literal(repetitions),
+            SequenceEntry::Range {
+                start,
+                end,
+                repetitions,
+                ..
+            } => is_literal(start) && is_literal(end) && is_literal(repetitions),
+        }),
+        _ => false,
+    }
+}
+
+fn as_integer(expression: &Expression) -> Option<i32> {
+    match expression {
+        Expression::Integer { value, .. } => Some(*value),
+        _ => None,
+    }
+}
+
+/// The values of a sequence that is already expanded, where each entry is a single integer
+fn as_literal_sequence(expression: &Expression) -> Option<Vec<i32>> {
+    let Expression::Sequence { entries, .. } = expression else {
+        return None;
+    };
+
+    entries
+        .iter()
+        .map(|entry| match entry {
+            SequenceEntry::Single {
+                value, repetitions, ..
+            } if as_integer(repetitions) == Some(1) => as_integer(value),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A sequence with a single entry for each value
+fn expanded_sequence(values: &[i32]) -> Expression {
+    functional::sequence(
+        values
+            .iter()
+            .map(|&value| {
+                functional::single_entry(functional::integer(value), functional::integer(1))
+            })
+            .collect(),
+    )
+}
+
+/// Write dice with the sides `{1..N}` as `N` sides, which is the same die
+fn normalize_dice(expression: Expression) -> Expression {
+    let Expression::BinaryOperation {
+        operator,
+        left,
+        right,
+        span,
+    } = expression
+    else {
+        return expression;
+    };
+
+    let right = match as_literal_sequence(&right) {
+        Some(sides)
+            if !sides.is_empty() && sides.iter().zip(1..).all(|(&side, index)| side == index) =>
+        {
+            let largest = i32::try_from(sides.len()).expect("sides to fit as they are integers");
+            Box::new(functional::integer(largest).with_span(right.span()))
+        }
+        _ => right,
+    };
+
+    Expression::BinaryOperation {
+        operator,
+        left,
+        right,
+        span,
+    }
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    use super::*;
+    use crate::ast::functional::*;
+    use crate::parse::{parse_expression, parse_program};
+
+    fn optimized(source: &str) -> String {
+        optimize_expression(parse_expression(source).unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_fold_numbers() {
+        assert_eq!(optimized("2 * 3 + 1"), "7");
+        assert_eq!(optimized("(1 + 2) * -(3 - 5)"), "6");
+        assert_eq!(optimized("2 ^ 10 >= 1000 & !0"), "1");
+        assert_eq!(optimized("#12345"), "5");
+        assert_eq!(optimized("X + 2 * 3"), "X + 6");
+        assert_eq!(optimized("3d6 + 4 / 2"), "3d6 + 2");
+    }
+
+    #[test]
+    fn test_division_truncates() {
+        assert_eq!(optimized("7 / 2"), "3");
+        assert_eq!(
+            optimize_expression(parse_expression("-7 / 2").unwrap()),
+            integer(-3)
+        );
+        assert_eq!(
+            optimize_expression(parse_expression("7 / -2").unwrap()),
+            integer(-3)
+        );
+        assert_eq!(optimized("-7 / -2"), "3");
+        assert_eq!(optimized("2 ^ -1"), "0");
+    }
+
+    #[test]
+    fn test_fold_sequences() {
+        assert_eq!(optimized("{1..3, 4:2, 2..1}"), "{1, 2, 3, 4, 4, 2, 1}");
+        assert_eq!(optimized("{1..2, {3, 4}}"), "{1, 2, 3, 4}");
+        assert_eq!(optimized("{1:1 + 1, 5:-1}"), "{1, 1}");
+        assert_eq!(optimized("#{1..6}"), "6");
+        assert_eq!(optimized("{1, 2} + 3"), "6");
+        assert_eq!(optimized("3 = {1, 3, 3}"), "2");
+        assert_eq!(optimized("2 @ {4..6}"), "5");
+        assert_eq!(optimized("{1..X}"), "{1..X}");
+        assert_eq!(optimized("{1..2000}"), "{1..2000}");
+    }
+
+    #[test]
+    fn test_normalize_dice() {
+        assert_eq!(optimized("1d{1..6}"), "1d6");
+        assert_eq!(optimized("2d{1, 2, 3, 4}"), "2d4");
+        assert_eq!(optimized("(1 + 2)d(3 * 2)"), "3d6");
+        assert_eq!(optimized("1d{0..5}"), "1d{0, 1, 2, 3, 4, 5}");
+        assert_eq!(optimized("1d{1, 1, 2}"), "1d{1, 1, 2}");
+        assert_eq!(optimized("1d{}"), "1d{}");
+    }
+
+    #[test]
+    fn test_left_as_written() {
+        // Failures happen when the program is run, not when it is optimized
+        assert_eq!(optimized("1 / 0"), "1 / 0");
+        assert_eq!(optimized("2147483647 + 1"), "2147483647 + 1");
+        assert_eq!(optimized("{1:{2}}"), "{1:{2}}");
+        // Digits depend on the position order setting
+        assert_eq!(optimized("1 @ 123"), "1 @ 123");
+        assert_eq!(optimized("1 @ 2d6"), "1 @ 2d6");
+        // Calls are left alone, though their arguments are folded
+        assert_eq!(
+            optimize_expression(function_call(
+                "highest ? of ?".to_owned(),
+                vec![
+                    parse_expression("1 + 1").unwrap(),
+                    variable_reference("X".to_owned())
+                ]
+            ))
+            .to_string(),
+            "[highest 2 of X]"
+        );
+    }
+
+    #[test]
+    fn test_keeps_spans() {
+        let expression = optimize_expression(parse_expression("X + (2 * 3)").unwrap());
+        let Expression::BinaryOperation { left, right, .. } = &expression else {
+            panic!("expected an addition");
+        };
+
+        assert_eq!(expression.span(), ast::Span::new(0, 11));
+        assert_eq!(left.span(), ast::Span::new(0, 1));
+        assert_eq!(right.span(), ast::Span::new(5, 10));
+    }
+
+    #[test]
+    fn test_same_outputs() {
+        let program = parse_program(
+            "set \"position order\" to \"lowest first\"\n\
+             set \"explode depth\" to 1 + 1\n\
+             X: 1d{1..6} + 10 / -3\n\
+             output X\n\
+             output 1 @ 234 + {2, 4} * 2\n\
+             output {1..3}d{1..4} - #{1:3}\n\
+             output 2 @ 3d{1..6, 8 / 2:2}",
+        )
+        .unwrap();
+        let optimized = optimize_program(program.clone());
+        assert_ne!(optimized, program);
+
+        let mut interpreter = Interpreter::new();
+        interpreter.run_program(program).unwrap();
+        let mut optimized_interpreter = Interpreter::new();
+        optimized_interpreter.run_program(optimized).unwrap();
+        assert_eq!(optimized_interpreter.outputs(), interpreter.outputs());
+    }
+}
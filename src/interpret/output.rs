@@ -1,6 +1,9 @@
+use super::factor;
+use super::rational::Rational;
 use crate::outcome::Outcome;
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Outputs {
     outputs: Vec<Output>,
 }
@@ -25,9 +28,21 @@ impl Outputs {
     pub fn outcomes(&self) -> &Vec<Output> {
         &self.outputs
     }
+
+    /// Name of each output, falling back to AnyDice's `output N` (1-based) for unnamed outputs
+    pub fn names(&self) -> impl Iterator<Item = String> + '_ {
+        self.outputs
+            .iter()
+            .enumerate()
+            .map(|(index, output)| match output.name() {
+                Some(name) => name.to_owned(),
+                None => format!("output {}", index + 1),
+            })
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Output {
     outcomes: Vec<Outcome>,
     name: Option<String>,
@@ -41,4 +56,551 @@ impl Output {
     pub fn outcomes(&self) -> &Vec<Outcome> {
         &self.outcomes
     }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// Statistics describing a single [`Output`], as shown alongside each output by AnyDice
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+    pub mean: Rational,
+    /// Empty if the weights are too large for it to be worked out exactly
+    pub variance: Option<Rational>,
+    /// Empty exactly when the variance is
+    pub standard_deviation: Option<f64>,
+    pub minimum: i32,
+    pub maximum: i32,
+    pub modes: Vec<i32>,
+    pub median: i32,
+}
+
+impl Output {
+    /// Sum of all outcome weights, widened to avoid overflow while accumulating
+    fn total_weight(&self) -> u128 {
+        self.outcomes
+            .iter()
+            .map(|outcome| outcome.weight as u128)
+            .sum()
+    }
+
+    /// Outcomes that can actually happen, ignoring those with a weight of zero
+    fn possible_outcomes(&self) -> impl Iterator<Item = &Outcome> {
+        self.outcomes.iter().filter(|outcome| outcome.weight > 0)
+    }
+
+    /// Expected value of the output
+    ///
+    /// Returns `None` if there are no outcomes with any weight.
+    pub fn mean(&self) -> Option<Rational> {
+        let total = self.total_weight();
+        if total == 0 {
+            return None;
+        }
+
+        let sum = self
+            .possible_outcomes()
+            .map(|outcome| outcome.value as i128 * outcome.weight as i128)
+            .sum::<i128>();
+
+        Some(Rational::new(sum, total as i128))
+    }
+
+    /// Population variance of the output
+    ///
+    /// Returns `None` if there are no outcomes with any weight, or if the weights are too large
+    /// for it to be worked out exactly.
+    pub fn variance(&self) -> Option<Rational> {
+        let total = i128::try_from(self.total_weight()).ok()?;
+        if total == 0 {
+            return None;
+        }
+
+        let (sum, sum_of_squares) = self.possible_outcomes().try_fold(
+            (0i128, 0i128),
+            |(sum, sum_of_squares), outcome| {
+                let value = outcome.value as i128;
+                let weight = outcome.weight as i128;
+
+                Some((
+                    sum.checked_add(value * weight)?,
+                    sum_of_squares.checked_add((value * value).checked_mul(weight)?)?,
+                ))
+            },
+        )?;
+
+        // Var(X) = E[X^2] - E[X]^2, with each in lowest terms first so that large weights still fit
+        let mean = Rational::new(sum, total);
+        let mean_of_squares = Rational::new(sum_of_squares, total);
+        let squared_denominator = mean.denominator().checked_mul(mean.denominator())?;
+        let denominator = mean_of_squares.denominator().checked_mul(
+            squared_denominator
+                / factor::gcd_u128(
+                    mean_of_squares.denominator().unsigned_abs(),
+                    squared_denominator.unsigned_abs(),
+                ) as i128,
+        )?;
+
+        let numerator = mean_of_squares
+            .numerator()
+            .checked_mul(denominator / mean_of_squares.denominator())?
+            .checked_sub(
+                mean.numerator()
+                    .checked_mul(mean.numerator())?
+                    .checked_mul(denominator / squared_denominator)?,
+            )?;
+
+        Some(Rational::new(numerator, denominator))
+    }
+
+    /// Population standard deviation of the output
+    ///
+    /// Returns `None` whenever the [variance][0] is `None`.
+    ///
+    /// [0]: Output::variance
+    pub fn standard_deviation(&self) -> Option<f64> {
+        self.variance().map(|variance| variance.to_f64().sqrt())
+    }
+
+    /// Smallest value with a non-zero weight
+    pub fn minimum(&self) -> Option<i32> {
+        self.possible_outcomes().map(|outcome| outcome.value).min()
+    }
+
+    /// Largest value with a non-zero weight
+    pub fn maximum(&self) -> Option<i32> {
+        self.possible_outcomes().map(|outcome| outcome.value).max()
+    }
+
+    /// All values sharing the largest weight, in ascending order
+    pub fn modes(&self) -> Vec<i32> {
+        let Some(largest) = self.possible_outcomes().map(|outcome| outcome.weight).max() else {
+            return vec![];
+        };
+
+        let mut modes = self
+            .possible_outcomes()
+            .filter(|outcome| outcome.weight == largest)
+            .map(|outcome| outcome.value)
+            .collect::<Vec<_>>();
+        modes.sort_unstable();
+        modes.dedup();
+
+        modes
+    }
+
+    /// Smallest value for which at least half of the weight is at or below it
+    pub fn median(&self) -> Option<i32> {
+        self.percentile(50.0)
+    }
+
+    /// Smallest value for which at least `percent` (between 0 and 100) of the weight is at or below it
+    ///
+    /// Returns `None` if there are no outcomes with any weight or `percent` is out of range.
+    pub fn percentile(&self, percent: f64) -> Option<i32> {
+        if !(0.0..=100.0).contains(&percent) {
+            return None;
+        }
+
+        let total = self.total_weight();
+        if total == 0 {
+            return None;
+        }
+
+        let mut sorted = self.possible_outcomes().collect::<Vec<_>>();
+        sorted.sort_by_key(|outcome| outcome.value);
+
+        let threshold = percent / 100.0 * total as f64;
+        let mut cumulative = 0u128;
+        sorted
+            .into_iter()
+            .find(|outcome| {
+                cumulative += outcome.weight as u128;
+                cumulative as f64 >= threshold
+            })
+            .map(|outcome| outcome.value)
+    }
+
+    /// Distinct values in ascending order with their combined weight
+    fn sorted_weights(&self) -> Vec<(i32, u128)> {
+        let mut sorted = self.possible_outcomes().collect::<Vec<_>>();
+        sorted.sort_by_key(|outcome| outcome.value);
+
+        sorted.into_iter().fold(vec![], |mut acc, outcome| {
+            match acc.last_mut() {
+                Some((value, weight)) if *value == outcome.value => {
+                    *weight += outcome.weight as u128
+                }
+                _ => acc.push((outcome.value, outcome.weight as u128)),
+            }
+            acc
+        })
+    }
+
+    /// Exact chance of each distinct value, in ascending order of value
+    ///
+    /// This is the "normal" view of an output.
+    pub fn probabilities(&self) -> Vec<(i32, Rational)> {
+        let total = self.total_weight() as i128;
+
+        self.sorted_weights()
+            .into_iter()
+            .map(|(value, weight)| (value, Rational::new(weight as i128, total)))
+            .collect()
+    }
+
+    /// Exact chance of rolling each distinct value or higher, in ascending order of value
+    pub fn at_least(&self) -> Vec<(i32, Rational)> {
+        let total = self.total_weight() as i128;
+        let mut remaining = total;
+
+        self.sorted_weights()
+            .into_iter()
+            .map(|(value, weight)| {
+                let chance = Rational::new(remaining, total);
+                remaining -= weight as i128;
+                (value, chance)
+            })
+            .collect()
+    }
+
+    /// Exact chance of rolling each distinct value or lower, in ascending order of value
+    pub fn at_most(&self) -> Vec<(i32, Rational)> {
+        let total = self.total_weight() as i128;
+        let mut cumulative = 0;
+
+        self.sorted_weights()
+            .into_iter()
+            .map(|(value, weight)| {
+                cumulative += weight as i128;
+                (value, Rational::new(cumulative, total))
+            })
+            .collect()
+    }
+
+    /// Exact chance of rolling `value` or higher
+    ///
+    /// Returns `None` if there are no outcomes with any weight.
+    pub fn chance_at_least(&self, value: i32) -> Option<Rational> {
+        let total = self.total_weight() as i128;
+        if total == 0 {
+            return None;
+        }
+
+        let matching = self
+            .possible_outcomes()
+            .filter(|outcome| outcome.value >= value)
+            .map(|outcome| outcome.weight as i128)
+            .sum::<i128>();
+
+        Some(Rational::new(matching, total))
+    }
+
+    /// Compute all summary statistics at once
+    ///
+    /// Returns `None` if there are no outcomes with any weight.
+    pub fn summary(&self) -> Option<Summary> {
+        let variance = self.variance();
+
+        Some(Summary {
+            mean: self.mean()?,
+            variance,
+            standard_deviation: variance.map(|variance| variance.to_f64().sqrt()),
+            minimum: self.minimum()?,
+            maximum: self.maximum()?,
+            modes: self.modes(),
+            median: self.median()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_d4() -> Output {
+        Output::new(
+            vec![
+                Outcome {
+                    value: 2,
+                    weight: 1,
+                },
+                Outcome {
+                    value: 3,
+                    weight: 2,
+                },
+                Outcome {
+                    value: 4,
+                    weight: 3,
+                },
+                Outcome {
+                    value: 5,
+                    weight: 4,
+                },
+                Outcome {
+                    value: 6,
+                    weight: 3,
+                },
+                Outcome {
+                    value: 7,
+                    weight: 2,
+                },
+                Outcome {
+                    value: 8,
+                    weight: 1,
+                },
+            ],
+            None,
+        )
+    }
+
+    fn empty() -> Output {
+        Output::new(
+            vec![Outcome {
+                value: 1,
+                weight: 0,
+            }],
+            None,
+        )
+    }
+
+    #[test]
+    fn test_mean() {
+        assert_eq!(two_d4().mean(), Some(Rational::new(5, 1)));
+        assert_eq!(
+            Output::new(
+                vec![
+                    Outcome {
+                        value: -1,
+                        weight: 1,
+                    },
+                    Outcome {
+                        value: 2,
+                        weight: 2,
+                    },
+                ],
+                None,
+            )
+            .mean(),
+            Some(Rational::new(1, 1))
+        );
+        assert_eq!(empty().mean(), None);
+    }
+
+    #[test]
+    fn test_variance_and_standard_deviation() {
+        assert_eq!(two_d4().variance(), Some(Rational::new(5, 2)));
+        assert_eq!(two_d4().standard_deviation(), Some(2.5f64.sqrt()));
+        assert_eq!(empty().variance(), None);
+        assert_eq!(empty().standard_deviation(), None);
+    }
+
+    #[test]
+    fn test_variance_large_weights() {
+        let output = Output::new(
+            vec![
+                Outcome {
+                    value: 1,
+                    weight: 1 << 62,
+                },
+                Outcome {
+                    value: 2,
+                    weight: 1 << 62,
+                },
+                Outcome {
+                    value: 3,
+                    weight: 1 << 63,
+                },
+            ],
+            None,
+        );
+
+        assert_eq!(output.variance(), Some(Rational::new(11, 16)));
+    }
+
+    #[test]
+    fn test_summary_without_variance() {
+        let output = Output::new(
+            [i32::MIN, i32::MIN + 1, i32::MAX - 1, i32::MAX]
+                .into_iter()
+                .map(|value| Outcome {
+                    value,
+                    weight: usize::MAX,
+                })
+                .collect(),
+            None,
+        );
+
+        assert_eq!(output.variance(), None);
+        assert_eq!(
+            output.summary(),
+            Some(Summary {
+                mean: Rational::new(-1, 2),
+                variance: None,
+                standard_deviation: None,
+                minimum: i32::MIN,
+                maximum: i32::MAX,
+                modes: vec![i32::MIN, i32::MIN + 1, i32::MAX - 1, i32::MAX],
+                median: i32::MIN + 1,
+            })
+        );
+        assert_eq!(output.percentile(100.0), Some(i32::MAX));
+    }
+
+    #[test]
+    fn test_minimum_and_maximum() {
+        assert_eq!(two_d4().minimum(), Some(2));
+        assert_eq!(two_d4().maximum(), Some(8));
+        assert_eq!(empty().minimum(), None);
+        assert_eq!(empty().maximum(), None);
+    }
+
+    #[test]
+    fn test_modes() {
+        assert_eq!(two_d4().modes(), vec![5]);
+        assert_eq!(
+            Output::new(
+                vec![
+                    Outcome {
+                        value: 3,
+                        weight: 2,
+                    },
+                    Outcome {
+                        value: 1,
+                        weight: 2,
+                    },
+                    Outcome {
+                        value: 2,
+                        weight: 1,
+                    },
+                ],
+                None,
+            )
+            .modes(),
+            vec![1, 3]
+        );
+        assert_eq!(empty().modes(), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_median_and_percentile() {
+        assert_eq!(two_d4().median(), Some(5));
+        assert_eq!(two_d4().percentile(0.0), Some(2));
+        assert_eq!(two_d4().percentile(25.0), Some(4));
+        assert_eq!(two_d4().percentile(100.0), Some(8));
+        assert_eq!(two_d4().percentile(100.5), None);
+        assert_eq!(two_d4().percentile(f64::NAN), None);
+        assert_eq!(empty().median(), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(
+            two_d4().summary(),
+            Some(Summary {
+                mean: Rational::new(5, 1),
+                variance: Some(Rational::new(5, 2)),
+                standard_deviation: Some(2.5f64.sqrt()),
+                minimum: 2,
+                maximum: 8,
+                modes: vec![5],
+                median: 5,
+            })
+        );
+        assert_eq!(empty().summary(), None);
+    }
+
+    #[test]
+    fn test_probabilities() {
+        assert_eq!(
+            Output::new(
+                vec![
+                    Outcome {
+                        value: 2,
+                        weight: 1,
+                    },
+                    Outcome {
+                        value: 1,
+                        weight: 2,
+                    },
+                    Outcome {
+                        value: 2,
+                        weight: 1,
+                    },
+                    Outcome {
+                        value: 3,
+                        weight: 0,
+                    },
+                ],
+                None,
+            )
+            .probabilities(),
+            vec![(1, Rational::new(1, 2)), (2, Rational::new(1, 2))]
+        );
+        assert_eq!(empty().probabilities(), vec![]);
+    }
+
+    #[test]
+    fn test_at_least() {
+        assert_eq!(
+            two_d4().at_least(),
+            vec![
+                (2, Rational::new(16, 16)),
+                (3, Rational::new(15, 16)),
+                (4, Rational::new(13, 16)),
+                (5, Rational::new(10, 16)),
+                (6, Rational::new(6, 16)),
+                (7, Rational::new(3, 16)),
+                (8, Rational::new(1, 16)),
+            ]
+        );
+        assert_eq!(empty().at_least(), vec![]);
+    }
+
+    #[test]
+    fn test_at_most() {
+        assert_eq!(
+            two_d4().at_most(),
+            vec![
+                (2, Rational::new(1, 16)),
+                (3, Rational::new(3, 16)),
+                (4, Rational::new(6, 16)),
+                (5, Rational::new(10, 16)),
+                (6, Rational::new(13, 16)),
+                (7, Rational::new(15, 16)),
+                (8, Rational::new(16, 16)),
+            ]
+        );
+        assert_eq!(empty().at_most(), vec![]);
+    }
+
+    #[test]
+    fn test_chance_at_least() {
+        assert_eq!(two_d4().chance_at_least(6), Some(Rational::new(3, 8)));
+        assert_eq!(two_d4().chance_at_least(-10), Some(Rational::new(1, 1)));
+        assert_eq!(two_d4().chance_at_least(9), Some(Rational::new(0, 1)));
+        assert_eq!(empty().chance_at_least(1), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut outputs = Outputs::new();
+        outputs.add_output(two_d4());
+        outputs.add_output(Output::new(
+            vec![Outcome {
+                value: 1,
+                weight: 1,
+            }],
+            Some("named".to_owned()),
+        ));
+
+        let json = serde_json::to_string(&outputs).unwrap();
+        assert_eq!(serde_json::from_str::<Outputs>(&json).unwrap(), outputs);
+        assert_eq!(
+            serde_json::to_value(&outputs.outcomes()[1]).unwrap(),
+            serde_json::json!({"outcomes": [{"value": 1, "weight": 1}], "name": "named"})
+        );
+    }
 }
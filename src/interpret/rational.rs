@@ -0,0 +1,216 @@
+//! Exact signed fractions used to report statistics of [outputs][0]
+//!
+//! [0]: super::output::Output
+
+use std::cmp;
+use std::fmt;
+
+use super::factor;
+
+/// A signed fraction that is always kept in lowest terms with a positive denominator
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    /// Create a new rational, simplified to lowest terms
+    ///
+    /// # Panics
+    ///
+    /// Panics if the denominator is zero.
+    pub fn new(numerator: i128, denominator: i128) -> Self {
+        assert!(denominator != 0, "denominator must not be zero");
+
+        let sign = if (numerator < 0) != (denominator < 0) {
+            -1
+        } else {
+            1
+        };
+        let numerator = numerator.unsigned_abs();
+        let denominator = denominator.unsigned_abs();
+
+        let factor = factor::gcd_u128(numerator, denominator).max(1);
+
+        Self {
+            numerator: sign * i128::try_from(numerator / factor).expect("to fit after simplifying"),
+            denominator: i128::try_from(denominator / factor).expect("to fit after simplifying"),
+        }
+    }
+
+    /// Create a rational representing a whole number
+    pub fn from_integer(value: i128) -> Self {
+        Self {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    pub fn numerator(&self) -> i128 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> i128 {
+        self.denominator
+    }
+
+    /// Approximate this rational as a float
+    pub fn to_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        // Denominators are always positive, so cross multiplying preserves ordering
+        if let (Some(left), Some(right)) = (
+            self.numerator.checked_mul(other.denominator),
+            other.numerator.checked_mul(self.denominator),
+        ) {
+            return left.cmp(&right);
+        }
+
+        match (self.numerator < 0, other.numerator < 0) {
+            (false, false) => cmp_unsigned(
+                self.numerator.unsigned_abs(),
+                self.denominator.unsigned_abs(),
+                other.numerator.unsigned_abs(),
+                other.denominator.unsigned_abs(),
+            ),
+            (true, true) => cmp_unsigned(
+                other.numerator.unsigned_abs(),
+                other.denominator.unsigned_abs(),
+                self.numerator.unsigned_abs(),
+                self.denominator.unsigned_abs(),
+            ),
+            (true, false) => cmp::Ordering::Less,
+            (false, true) => cmp::Ordering::Greater,
+        }
+    }
+}
+
+/// Compare `a / b` with `c / d` without multiplying, by comparing their integer parts and then
+/// the remainders, which is the same as comparing the inverted remainders the other way around
+fn cmp_unsigned(mut a: u128, mut b: u128, mut c: u128, mut d: u128) -> cmp::Ordering {
+    loop {
+        let ordering = (a / b).cmp(&(c / d));
+        if ordering.is_ne() {
+            return ordering;
+        }
+
+        let (left, right) = (a % b, c % d);
+        match (left, right) {
+            (0, 0) => return cmp::Ordering::Equal,
+            (0, _) => return cmp::Ordering::Less,
+            (_, 0) => return cmp::Ordering::Greater,
+            // left / b < right / d exactly when d / right < b / left
+            _ => (a, b, c, d) = (d, right, b, left),
+        }
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_and_simplify() {
+        assert_eq!(
+            Rational::new(6, 9),
+            Rational {
+                numerator: 2,
+                denominator: 3
+            }
+        );
+
+        assert_eq!(
+            Rational::new(3, -6),
+            Rational {
+                numerator: -1,
+                denominator: 2
+            }
+        );
+
+        assert_eq!(
+            Rational::new(-4, -2),
+            Rational {
+                numerator: 2,
+                denominator: 1
+            }
+        );
+
+        assert_eq!(
+            Rational::new(0, -5),
+            Rational {
+                numerator: 0,
+                denominator: 1
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_zero_denominator_panics() {
+        Rational::new(1, 0);
+    }
+
+    #[test]
+    fn test_ordering() {
+        assert!(Rational::new(1, 3) < Rational::new(1, 2));
+        assert!(Rational::new(-1, 2) < Rational::new(-1, 3));
+        assert_eq!(
+            Rational::new(2, 4).cmp(&Rational::new(1, 2)),
+            cmp::Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_ordering_large_denominators() {
+        let (large, larger) = (i128::MAX - 2, i128::MAX - 1);
+        let just_above_one = Rational::new(i128::MAX, larger);
+        let further_above_one = Rational::new(larger, large);
+
+        assert!(just_above_one < further_above_one);
+        assert!(Rational::new(-i128::MAX, larger) > Rational::new(-larger, large));
+        assert!(Rational::new(-1, larger) < Rational::new(1, large));
+        assert!(Rational::new(1, larger) < Rational::new(1, large));
+        assert!(Rational::new(large, i128::MAX) < Rational::from_integer(1));
+        assert_eq!(
+            just_above_one.cmp(&Rational::new(i128::MAX, larger)),
+            cmp::Ordering::Equal
+        );
+        assert_eq!(
+            [further_above_one, just_above_one].iter().max(),
+            Some(&further_above_one)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Rational::new(7, 2).to_string(), "7/2");
+        assert_eq!(Rational::new(-6, 3).to_string(), "-2");
+    }
+
+    #[test]
+    fn test_to_f64() {
+        assert_eq!(Rational::new(7, 2).to_f64(), 3.5);
+        assert_eq!(Rational::new(-1, 4).to_f64(), -0.25);
+    }
+}
@@ -0,0 +1,320 @@
+//! Rendering of [outputs][0] as text bar charts for terminals
+//!
+//! [0]: super::output
+
+use std::cmp;
+use std::collections;
+use std::fmt::Write;
+
+use super::output::{Output, Outputs};
+use super::rational::Rational;
+
+/// Which chance to show for each value, matching the views offered by AnyDice
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Chance of rolling exactly the value
+    #[default]
+    Normal,
+    /// Chance of rolling the value or higher
+    AtLeast,
+    /// Chance of rolling the value or lower
+    AtMost,
+}
+
+impl Mode {
+    /// Chances for each distinct value of an output in this mode, in ascending order of value
+    pub fn chances(&self, output: &Output) -> Vec<(i32, Rational)> {
+        match self {
+            Mode::Normal => output.probabilities(),
+            Mode::AtLeast => output.at_least(),
+            Mode::AtMost => output.at_most(),
+        }
+    }
+}
+
+/// Draws outputs as horizontal bar charts made of Unicode block characters
+///
+/// Bars are scaled so that the largest chance in a chart fills the full width.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BarChart {
+    mode: Mode,
+    width: usize,
+}
+
+impl Default for BarChart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Partial blocks from one eighth to seven eighths of a character
+const PARTIAL_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+const FULL_BLOCK: char = '█';
+
+// Width of a formatted percentage like ` 16.67%`
+const PERCENT_WIDTH: usize = 7;
+
+impl BarChart {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Normal,
+            width: 40,
+        }
+    }
+
+    /// Set which chances to show
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the width in characters of the longest bar
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Render a single output, headed by its name if it has one
+    pub fn render_output(&self, output: &Output) -> String {
+        self.render_named(output.name(), output)
+    }
+
+    /// Render each output one after another, headed by their names and separated by blank lines
+    pub fn render_outputs(&self, outputs: &Outputs) -> String {
+        outputs
+            .names()
+            .zip(outputs.outcomes())
+            .map(|(name, output)| self.render_named(Some(&name), output))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Render all outputs side by side, with one row per value and one column per output
+    pub fn render_comparison(&self, outputs: &Outputs) -> String {
+        let names = outputs.names().collect::<Vec<_>>();
+        let columns = outputs
+            .outcomes()
+            .iter()
+            .map(|output| {
+                self.mode
+                    .chances(output)
+                    .into_iter()
+                    .collect::<collections::BTreeMap<_, _>>()
+            })
+            .collect::<Vec<_>>();
+
+        let values = columns
+            .iter()
+            .flat_map(|column| column.keys().copied())
+            .collect::<collections::BTreeSet<_>>();
+        let value_width = value_width(values.iter().copied());
+        let column_width = PERCENT_WIDTH + 1 + self.width;
+        let scale = columns
+            .iter()
+            .flat_map(|column| column.values().copied())
+            .max();
+
+        let mut chart = format!("{:value_width$}", "");
+        for name in &names {
+            write!(chart, " | {:column_width$}", truncate(name, column_width))
+                .expect("writing to a string to succeed");
+        }
+        chart = chart.trim_end().to_owned();
+        chart.push('\n');
+
+        for value in values {
+            let mut row = format!("{:>value_width$}", value);
+            for column in &columns {
+                let cell = match column.get(&value) {
+                    Some(&chance) => self.cell(chance, scale),
+                    None => String::new(),
+                };
+                write!(row, " | {:column_width$}", cell).expect("writing to a string to succeed");
+            }
+            chart.push_str(row.trim_end());
+            chart.push('\n');
+        }
+
+        chart
+    }
+
+    fn render_named(&self, name: Option<&str>, output: &Output) -> String {
+        let chances = self.mode.chances(output);
+        let value_width = value_width(chances.iter().map(|&(value, _)| value));
+        let scale = chances.iter().map(|&(_, chance)| chance).max();
+
+        let mut chart = String::new();
+        if let Some(name) = name {
+            writeln!(chart, "{}", name).expect("writing to a string to succeed");
+        }
+        for (value, chance) in chances {
+            let row = format!("{:>value_width$} {}", value, self.cell(chance, scale));
+            writeln!(chart, "{}", row.trim_end()).expect("writing to a string to succeed");
+        }
+
+        chart
+    }
+
+    fn cell(&self, chance: Rational, scale: Option<Rational>) -> String {
+        let fraction = match scale {
+            Some(scale) if scale.numerator() > 0 => chance.to_f64() / scale.to_f64(),
+            _ => 0.0,
+        };
+
+        format!(
+            "{:>6.2}% {}",
+            chance.to_f64() * 100.0,
+            bar(fraction, self.width)
+        )
+    }
+}
+
+fn value_width(values: impl Iterator<Item = i32>) -> usize {
+    values
+        .map(|value| value.to_string().len())
+        .max()
+        .unwrap_or(0)
+}
+
+fn truncate(name: &str, width: usize) -> String {
+    name.chars().take(width).collect()
+}
+
+fn bar(fraction: f64, width: usize) -> String {
+    let eighths = (fraction.clamp(0.0, 1.0) * (width * 8) as f64).round() as usize;
+    let full = cmp::min(eighths / 8, width);
+
+    let mut bar = FULL_BLOCK.to_string().repeat(full);
+    if full < width && eighths % 8 > 0 {
+        bar.push(PARTIAL_BLOCKS[eighths % 8 - 1]);
+    }
+
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outcome::Outcome;
+
+    fn two_d2() -> Output {
+        Output::new(
+            vec![
+                Outcome {
+                    value: 2,
+                    weight: 1,
+                },
+                Outcome {
+                    value: 3,
+                    weight: 2,
+                },
+                Outcome {
+                    value: 4,
+                    weight: 1,
+                },
+            ],
+            Some("2d2".to_owned()),
+        )
+    }
+
+    fn outputs() -> Outputs {
+        let mut outputs = Outputs::new();
+        outputs.add_output(two_d2());
+        outputs.add_output(Output::new(
+            vec![
+                Outcome {
+                    value: 1,
+                    weight: 1,
+                },
+                Outcome {
+                    value: 2,
+                    weight: 1,
+                },
+            ],
+            None,
+        ));
+
+        outputs
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(0.0, 4), "");
+        assert_eq!(bar(1.0, 4), "████");
+        assert_eq!(bar(0.5, 4), "██");
+        assert_eq!(bar(0.5, 3), "█▌");
+        assert_eq!(bar(1.0 / 32.0, 4), "▏");
+        assert_eq!(bar(2.0, 4), "████");
+    }
+
+    #[test]
+    fn test_render_output() {
+        assert_eq!(
+            BarChart::new().width(8).render_output(&two_d2()),
+            "2d2\n\
+             2  25.00% ████\n\
+             3  50.00% ████████\n\
+             4  25.00% ████\n"
+        );
+    }
+
+    #[test]
+    fn test_render_output_modes() {
+        assert_eq!(
+            BarChart::new()
+                .width(4)
+                .mode(Mode::AtLeast)
+                .render_output(&two_d2()),
+            "2d2\n\
+             2 100.00% ████\n\
+             3  75.00% ███\n\
+             4  25.00% █\n"
+        );
+
+        assert_eq!(
+            BarChart::new()
+                .width(4)
+                .mode(Mode::AtMost)
+                .render_output(&two_d2()),
+            "2d2\n\
+             2  25.00% █\n\
+             3  75.00% ███\n\
+             4 100.00% ████\n"
+        );
+    }
+
+    #[test]
+    fn test_render_output_without_weight() {
+        assert_eq!(
+            BarChart::new().render_output(&Output::new(vec![], None)),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_render_outputs() {
+        assert_eq!(
+            BarChart::new().width(4).render_outputs(&outputs()),
+            "2d2\n\
+             2  25.00% ██\n\
+             3  50.00% ████\n\
+             4  25.00% ██\n\
+             \n\
+             output 2\n\
+             1  50.00% ████\n\
+             2  50.00% ████\n"
+        );
+    }
+
+    #[test]
+    fn test_render_comparison() {
+        assert_eq!(
+            BarChart::new().width(4).render_comparison(&outputs()),
+            "  | 2d2          | output 2\n\
+             1 |              |  50.00% ████\n\
+             2 |  25.00% ██   |  50.00% ████\n\
+             3 |  50.00% ████ |\n\
+             4 |  25.00% ██   |\n"
+        );
+    }
+}
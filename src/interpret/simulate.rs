@@ -0,0 +1,363 @@
+//! Estimating outputs by running a program many times with random rolls
+//!
+//! Exact evaluation keeps the chance of every possible result, which grows quickly for things like
+//! large pools of dice. Simulation only ever keeps the results it has seen, so it can handle any
+//! program the [exact interpreter][0] can, at the cost of answers that are only estimates. Like
+//! the exact interpreter, every use of a variable with dice is a separate roll, so `X - X` is not
+//! always 0.
+//!
+//! [0]: super::interpreter::Interpreter
+
+use std::num;
+
+use super::error::InterpretError;
+use super::joint::JointSampler;
+use super::output;
+use crate::ast;
+use crate::sample::{histogram::Histogram, rng, stats};
+
+/// An interpreter that estimates outputs from many random trials of a program
+///
+/// Every trial runs the program once with [`JointSampler`], rolling the dice of a variable again
+/// each time it is used, and the outputs count how many trials had each value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulatedInterpreter {
+    trials: usize,
+    confidence: f64,
+}
+
+impl SimulatedInterpreter {
+    /// Run `trials` trials, with 95% confidence intervals
+    pub fn new(trials: num::NonZeroUsize) -> Self {
+        Self {
+            trials: trials.get(),
+            confidence: 0.95,
+        }
+    }
+
+    /// The fraction of the time that a confidence interval should contain the true value
+    ///
+    /// Fails unless it is between 0 and 1 exclusive.
+    pub fn confidence(mut self, confidence: f64) -> Result<Self, InterpretError> {
+        if !(confidence > 0.0 && confidence < 1.0) {
+            return Err(InterpretError::InvalidConfidence);
+        }
+
+        self.confidence = confidence;
+        Ok(self)
+    }
+
+    /// Run every trial of a program
+    ///
+    /// Stops at the first trial that fails.
+    pub fn run_program<R: rand::Rng>(
+        &self,
+        program: ast::Program,
+        rng: &mut R,
+    ) -> Result<Simulation, InterpretError> {
+        let sampler = JointSampler::new(program).reroll_variables();
+        let names = sampler.output_names();
+
+        let mut histograms = vec![Histogram::new(); names.len()];
+        for _ in 0..self.trials {
+            for (value, histogram) in sampler.sample(rng)?.into_iter().zip(&mut histograms) {
+                histogram.add(value);
+            }
+        }
+
+        let mut outputs = output::Outputs::new();
+        let mut intervals = vec![];
+        for (histogram, name) in histograms.into_iter().zip(names) {
+            intervals.push(self.intervals(&histogram));
+            outputs.add_output(histogram.to_output(name));
+        }
+
+        Ok(Simulation {
+            outputs,
+            intervals,
+            trials: self.trials,
+            confidence: self.confidence,
+        })
+    }
+
+    /// Run every trial of a program using a [seeded generator][0], giving the same estimates for
+    /// the same seed
+    ///
+    /// [0]: crate::sample::rng::seeded
+    pub fn run_program_seeded(
+        &self,
+        program: ast::Program,
+        seed: u64,
+    ) -> Result<Simulation, InterpretError> {
+        self.run_program(program, &mut rng::seeded(seed))
+    }
+
+    fn intervals(&self, histogram: &Histogram) -> Intervals {
+        let z = stats::two_sided_z(self.confidence);
+        let trials = self.trials as f64;
+
+        let mean = histogram.mean().unwrap_or(f64::NAN);
+        // With a single trial there is nothing to tell how far off the mean could be
+        let spread = histogram
+            .sample_variance()
+            .map_or(f64::INFINITY, |variance| z * (variance / trials).sqrt());
+
+        Intervals {
+            mean: Interval {
+                estimate: mean,
+                low: mean - spread,
+                high: mean + spread,
+            },
+            chances: histogram
+                .iter()
+                .map(|(&value, &count)| (value, wilson(count, self.trials, z)))
+                .collect(),
+        }
+    }
+}
+
+/// The Wilson score interval for a chance, which stays sensible for chances near 0 or 1
+fn wilson(successes: usize, trials: usize, z: f64) -> Interval {
+    let (successes, trials) = (successes as f64, trials as f64);
+    let estimate = successes / trials;
+    let z2 = z * z;
+
+    let center = (estimate + z2 / (2.0 * trials)) / (1.0 + z2 / trials);
+    let spread = z / (1.0 + z2 / trials)
+        * (estimate * (1.0 - estimate) / trials + z2 / (4.0 * trials * trials)).sqrt();
+
+    Interval {
+        estimate,
+        low: (center - spread).max(0.0),
+        high: (center + spread).min(1.0),
+    }
+}
+
+/// The results of a simulation, shaped like the results of an exact run
+#[derive(Clone, Debug, PartialEq)]
+pub struct Simulation {
+    outputs: output::Outputs,
+    intervals: Vec<Intervals>,
+    trials: usize,
+    confidence: f64,
+}
+
+impl Simulation {
+    /// Each output, where the weight of each value is the number of trials that had it
+    pub fn outputs(&self) -> &output::Outputs {
+        &self.outputs
+    }
+
+    /// Confidence intervals for each output, in the same order as the outputs
+    pub fn intervals(&self) -> &Vec<Intervals> {
+        &self.intervals
+    }
+
+    pub fn trials(&self) -> usize {
+        self.trials
+    }
+
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+}
+
+/// Confidence intervals for a single output
+#[derive(Clone, Debug, PartialEq)]
+pub struct Intervals {
+    /// The interval for the mean, based on a normal approximation
+    pub mean: Interval,
+    /// The interval for the chance of each value that was seen, in ascending order of value
+    pub chances: Vec<(i32, Interval)>,
+}
+
+/// An estimate with the range that the true value is likely within
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interval {
+    pub estimate: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Interval {
+    pub fn contains(&self, value: f64) -> bool {
+        self.low <= value && value <= self.high
+    }
+}
+
+#[cfg(all(test, feature = "parse"))]
+mod tests {
+    use super::*;
+    use crate::interpret::interpreter::Interpreter;
+    use crate::outcome::Outcome;
+    use crate::parse;
+
+    fn trials(count: usize) -> num::NonZeroUsize {
+        num::NonZeroUsize::new(count).unwrap()
+    }
+
+    fn simulate(program: &str, trials: usize) -> Simulation {
+        SimulatedInterpreter::new(self::trials(trials))
+            .run_program_seeded(parse::parse_program(program).unwrap(), 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_run_program_matches_exact() {
+        let program = "X: 3d6 output X output X >= 10 output 1@4d6";
+        let simulation = simulate(program, 10_000);
+
+        let mut exact = Interpreter::new();
+        exact
+            .run_program(parse::parse_program(program).unwrap())
+            .unwrap();
+
+        assert_eq!(simulation.trials(), 10_000);
+        assert_eq!(simulation.outputs().outcomes().len(), 3);
+        for ((simulated, exact), intervals) in simulation
+            .outputs()
+            .outcomes()
+            .iter()
+            .zip(exact.outputs().outcomes())
+            .zip(simulation.intervals())
+        {
+            let total = simulated
+                .outcomes()
+                .iter()
+                .map(|outcome| outcome.weight)
+                .sum::<usize>();
+            assert_eq!(total, 10_000);
+
+            assert!(intervals.mean.contains(exact.mean().unwrap().to_f64()));
+            assert!((intervals.mean.estimate - simulated.mean().unwrap().to_f64()).abs() < 1e-9);
+
+            // Each chance may miss its interval 5% of the time, so only most need to contain it
+            let exact_chances = exact.probabilities();
+            let contained = intervals
+                .chances
+                .iter()
+                .filter(|(value, interval)| {
+                    exact_chances.iter().any(|(exact_value, chance)| {
+                        exact_value == value && interval.contains(chance.to_f64())
+                    })
+                })
+                .count();
+            assert!(contained * 10 >= intervals.chances.len() * 8);
+        }
+    }
+
+    #[test]
+    fn test_run_program_reuses_variables_like_exact() {
+        let program = "X: 1d6 output X - X output X * X";
+        let simulation = simulate(program, 10_000);
+
+        let mut exact = Interpreter::new();
+        exact
+            .run_program(parse::parse_program(program).unwrap())
+            .unwrap();
+
+        for ((simulated, exact), intervals) in simulation
+            .outputs()
+            .outcomes()
+            .iter()
+            .zip(exact.outputs().outcomes())
+            .zip(simulation.intervals())
+        {
+            assert!(intervals.mean.contains(exact.mean().unwrap().to_f64()));
+            assert_eq!(simulated.minimum(), exact.minimum());
+            assert_eq!(simulated.maximum(), exact.maximum());
+            let deviation = |output: &output::Output| output.standard_deviation().unwrap();
+            assert!((deviation(simulated) - deviation(exact)).abs() < 0.2);
+        }
+    }
+
+    #[test]
+    fn test_run_program_names() {
+        use crate::ast::functional::*;
+
+        let simulation = SimulatedInterpreter::new(trials(10))
+            .run_program_seeded(
+                program(vec![
+                    output(integer(1), None),
+                    output(integer(2), Some("two".to_owned())),
+                ]),
+                0,
+            )
+            .unwrap();
+
+        assert_eq!(
+            simulation.outputs().names().collect::<Vec<_>>(),
+            vec!["output 1", "two"]
+        );
+        assert_eq!(
+            simulation.outputs().outcomes()[1].outcomes(),
+            &vec![Outcome {
+                value: 2,
+                weight: 10
+            }]
+        );
+        assert_eq!(
+            simulation.intervals()[1].chances,
+            vec![(
+                2,
+                Interval {
+                    estimate: 1.0,
+                    low: wilson(10, 10, stats::two_sided_z(0.95)).low,
+                    high: 1.0
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_confidence() {
+        let narrow = SimulatedInterpreter::new(trials(1000))
+            .confidence(0.5)
+            .unwrap()
+            .run_program_seeded(parse::parse_program("output 1d6").unwrap(), 0)
+            .unwrap();
+        let wide = simulate("output 1d6", 1000);
+
+        assert_eq!(narrow.confidence(), 0.5);
+        assert!(
+            narrow.intervals()[0].mean.high - narrow.intervals()[0].mean.low
+                < wide.intervals()[0].mean.high - wide.intervals()[0].mean.low
+        );
+    }
+
+    #[test]
+    fn test_invalid_confidence() {
+        for confidence in [0.0, 1.0, -0.5, 1.5, f64::NAN] {
+            assert_eq!(
+                SimulatedInterpreter::new(trials(10)).confidence(confidence),
+                Err(InterpretError::InvalidConfidence)
+            );
+        }
+    }
+
+    #[test]
+    fn test_single_trial() {
+        let simulation = simulate("output 1d6", 1);
+
+        assert_eq!(simulation.intervals()[0].mean.low, f64::NEG_INFINITY);
+        assert_eq!(simulation.intervals()[0].mean.high, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_wilson() {
+        let interval = wilson(50, 100, 1.959964);
+
+        assert_eq!(interval.estimate, 0.5);
+        assert!((interval.low - 0.4038).abs() < 1e-4);
+        assert!((interval.high - 0.5962).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_run_program_errors() {
+        assert_eq!(
+            SimulatedInterpreter::new(trials(10))
+                .run_program_seeded(parse::parse_program("output 1 / (1d2 - 1)").unwrap(), 0),
+            Err(InterpretError::DivisionByZero)
+        );
+    }
+}
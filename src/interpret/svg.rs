@@ -0,0 +1,461 @@
+//! Rendering of [outputs][0] as SVG charts
+//!
+//! The generated documents are standalone and do not rely on any external resources.
+//!
+//! [0]: super::output
+
+use std::collections;
+use std::fmt::Write;
+
+use super::output::Outputs;
+use super::rational::Rational;
+use super::render::Mode;
+
+/// How each output is drawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Kind {
+    /// A bar per value, with bars of different outputs overlaid
+    #[default]
+    Bar,
+    /// A line through the chance of each value
+    Line,
+}
+
+/// Draws outputs as an SVG chart
+///
+/// Values from all outputs share the horizontal axis and each output is drawn in its own color.
+/// Cumulative charts are drawn by using [`Mode::AtLeast`] or [`Mode::AtMost`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgChart {
+    kind: Kind,
+    mode: Mode,
+    width: u32,
+    height: u32,
+    title: Option<String>,
+}
+
+impl Default for SvgChart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Colors for each output, reused in order if there are more outputs than colors
+const PALETTE: [&str; 8] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#9c755f",
+];
+
+const MARGIN_TOP: f64 = 40.0;
+const MARGIN_RIGHT: f64 = 20.0;
+const MARGIN_BOTTOM: f64 = 60.0;
+const MARGIN_LEFT: f64 = 60.0;
+const Y_TICKS: u32 = 5;
+// Most value labels to show before skipping some to avoid overlap
+const MAX_X_LABELS: usize = 30;
+
+impl SvgChart {
+    pub fn new() -> Self {
+        Self {
+            kind: Kind::Bar,
+            mode: Mode::Normal,
+            width: 640,
+            height: 400,
+            title: None,
+        }
+    }
+
+    /// Set how each output is drawn
+    pub fn kind(mut self, kind: Kind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Set which chances to show
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the size of the document in pixels
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Set the title, which otherwise is taken from the output names
+    pub fn title(mut self, title: String) -> Self {
+        self.title = Some(title);
+        self
+    }
+
+    /// Render all outputs into a single SVG document
+    pub fn render(&self, outputs: &Outputs) -> String {
+        let names = outputs.names().collect::<Vec<_>>();
+        let series = outputs
+            .outcomes()
+            .iter()
+            .map(|output| {
+                self.mode
+                    .chances(output)
+                    .into_iter()
+                    .collect::<collections::BTreeMap<_, _>>()
+            })
+            .collect::<Vec<_>>();
+        let values = series
+            .iter()
+            .flat_map(|chances| chances.keys().copied())
+            .collect::<collections::BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let largest = series
+            .iter()
+            .flat_map(|chances| chances.values().copied())
+            .max()
+            .unwrap_or(Rational::from_integer(0));
+
+        let plot = Plot {
+            left: MARGIN_LEFT,
+            top: MARGIN_TOP,
+            width: (self.width as f64 - MARGIN_LEFT - MARGIN_RIGHT).max(0.0),
+            height: (self.height as f64 - MARGIN_TOP - MARGIN_BOTTOM).max(0.0),
+            slots: values.len(),
+            y_max: y_max(largest),
+        };
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="12">"#,
+            self.width, self.height, self.width, self.height
+        )
+        .expect("writing to a string to succeed");
+        writeln!(
+            svg,
+            r#"<rect width="{}" height="{}" fill="white"/>"#,
+            self.width, self.height
+        )
+        .expect("writing to a string to succeed");
+
+        let title = self.title.clone().unwrap_or_else(|| names.join(", "));
+        writeln!(
+            svg,
+            r#"<text x="{}" y="24" text-anchor="middle" font-size="16">{}</text>"#,
+            fmt_number(self.width as f64 / 2.0),
+            escape(&title)
+        )
+        .expect("writing to a string to succeed");
+
+        write_axes(&mut svg, &plot, &values);
+
+        for (index, chances) in series.iter().enumerate() {
+            let color = PALETTE[index % PALETTE.len()];
+            match self.kind {
+                Kind::Bar => write_bars(&mut svg, &plot, &values, chances, color),
+                Kind::Line => write_line(&mut svg, &plot, &values, chances, color),
+            }
+        }
+
+        write_legend(&mut svg, &plot, &names);
+
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+}
+
+/// The area inside the margins where outputs are drawn
+struct Plot {
+    left: f64,
+    top: f64,
+    width: f64,
+    height: f64,
+    slots: usize,
+    y_max: f64,
+}
+
+impl Plot {
+    fn slot_width(&self) -> f64 {
+        if self.slots == 0 {
+            0.0
+        } else {
+            self.width / self.slots as f64
+        }
+    }
+
+    fn center(&self, slot: usize) -> f64 {
+        self.left + self.slot_width() * (slot as f64 + 0.5)
+    }
+
+    fn y(&self, chance: Rational) -> f64 {
+        self.top + self.height - self.height * chance.to_f64() / self.y_max
+    }
+}
+
+fn write_axes(svg: &mut String, plot: &Plot, values: &[i32]) {
+    let bottom = plot.top + plot.height;
+
+    svg.push_str("<g stroke=\"#cccccc\">\n");
+    for tick in 0..=Y_TICKS {
+        let y = bottom - plot.height * tick as f64 / Y_TICKS as f64;
+        writeln!(
+            svg,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+            fmt_number(plot.left),
+            fmt_number(y),
+            fmt_number(plot.left + plot.width),
+            fmt_number(y)
+        )
+        .expect("writing to a string to succeed");
+    }
+    svg.push_str("</g>\n");
+
+    svg.push_str("<g text-anchor=\"end\">\n");
+    for tick in 0..=Y_TICKS {
+        let y = bottom - plot.height * tick as f64 / Y_TICKS as f64;
+        let percent = plot.y_max * 100.0 * tick as f64 / Y_TICKS as f64;
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}">{}%</text>"#,
+            fmt_number(plot.left - 6.0),
+            fmt_number(y + 4.0),
+            fmt_number(percent)
+        )
+        .expect("writing to a string to succeed");
+    }
+    svg.push_str("</g>\n");
+
+    let step = values.len().div_ceil(MAX_X_LABELS).max(1);
+    svg.push_str("<g text-anchor=\"middle\">\n");
+    for (slot, value) in values.iter().enumerate().step_by(step) {
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}">{}</text>"#,
+            fmt_number(plot.center(slot)),
+            fmt_number(bottom + 16.0),
+            value
+        )
+        .expect("writing to a string to succeed");
+    }
+    svg.push_str("</g>\n");
+
+    writeln!(
+        svg,
+        r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="#333333"/>"##,
+        fmt_number(plot.left),
+        fmt_number(bottom),
+        fmt_number(plot.left + plot.width),
+        fmt_number(bottom)
+    )
+    .expect("writing to a string to succeed");
+}
+
+/// Round the largest chance up to a whole percent so the axis labels stay readable
+fn y_max(largest: Rational) -> f64 {
+    let percent = (largest.to_f64() * 100.0).ceil().clamp(1.0, 100.0);
+
+    percent / 100.0
+}
+
+fn write_bars(
+    svg: &mut String,
+    plot: &Plot,
+    values: &[i32],
+    chances: &collections::BTreeMap<i32, Rational>,
+    color: &str,
+) {
+    let bar_width = plot.slot_width() * 0.8;
+
+    writeln!(svg, r#"<g fill="{}" fill-opacity="0.6">"#, color)
+        .expect("writing to a string to succeed");
+    for (slot, value) in values.iter().enumerate() {
+        if let Some(&chance) = chances.get(value) {
+            let y = plot.y(chance);
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}"/>"#,
+                fmt_number(plot.center(slot) - bar_width / 2.0),
+                fmt_number(y),
+                fmt_number(bar_width),
+                fmt_number(plot.top + plot.height - y)
+            )
+            .expect("writing to a string to succeed");
+        }
+    }
+    svg.push_str("</g>\n");
+}
+
+fn write_line(
+    svg: &mut String,
+    plot: &Plot,
+    values: &[i32],
+    chances: &collections::BTreeMap<i32, Rational>,
+    color: &str,
+) {
+    let points = values
+        .iter()
+        .enumerate()
+        .filter_map(|(slot, value)| {
+            chances.get(value).map(|&chance| {
+                format!(
+                    "{},{}",
+                    fmt_number(plot.center(slot)),
+                    fmt_number(plot.y(chance))
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    writeln!(
+        svg,
+        r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+        points.join(" "),
+        color
+    )
+    .expect("writing to a string to succeed");
+}
+
+fn write_legend(svg: &mut String, plot: &Plot, names: &[String]) {
+    let y = plot.top + plot.height + 40.0;
+    let entry_width = if names.is_empty() {
+        0.0
+    } else {
+        plot.width / names.len() as f64
+    };
+
+    svg.push_str("<g>\n");
+    for (index, name) in names.iter().enumerate() {
+        let x = plot.left + entry_width * index as f64;
+        writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="12" height="12" fill="{}"/>"#,
+            fmt_number(x),
+            fmt_number(y - 10.0),
+            PALETTE[index % PALETTE.len()]
+        )
+        .expect("writing to a string to succeed");
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}">{}</text>"#,
+            fmt_number(x + 16.0),
+            fmt_number(y),
+            escape(name)
+        )
+        .expect("writing to a string to succeed");
+    }
+    svg.push_str("</g>\n");
+}
+
+/// Format coordinates with limited precision so documents are stable and compact
+fn fmt_number(number: f64) -> String {
+    let formatted = format!("{:.2}", number);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+
+    match trimmed {
+        "-0" => "0".to_owned(),
+        _ => trimmed.to_owned(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path;
+
+    use super::*;
+    use crate::interpret::output::Output;
+    use crate::outcome::Outcome;
+
+    /// Compare against a file in `src/interpret/snapshots`, rewriting it instead if `UPDATE_SNAPSHOTS` is set
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/interpret/snapshots")
+            .join(name);
+
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::write(&path, actual).unwrap();
+        }
+
+        assert_eq!(actual, fs::read_to_string(&path).unwrap());
+    }
+
+    fn outputs() -> Outputs {
+        let mut outputs = Outputs::new();
+        outputs.add_output(Output::new(
+            (1..=6).map(|value| Outcome { value, weight: 1 }).collect(),
+            Some("d6".to_owned()),
+        ));
+        outputs.add_output(Output::new(
+            vec![
+                Outcome {
+                    value: 2,
+                    weight: 1,
+                },
+                Outcome {
+                    value: 3,
+                    weight: 2,
+                },
+                Outcome {
+                    value: 4,
+                    weight: 1,
+                },
+            ],
+            Some("2d2 & <more>".to_owned()),
+        ));
+
+        outputs
+    }
+
+    #[test]
+    fn test_fmt_number() {
+        assert_eq!(fmt_number(1.0), "1");
+        assert_eq!(fmt_number(1.5), "1.5");
+        assert_eq!(fmt_number(1.234), "1.23");
+        assert_eq!(fmt_number(-0.001), "0");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<a & 'b'>"), "&lt;a &amp; &apos;b&apos;&gt;");
+    }
+
+    #[test]
+    fn test_render_bar() {
+        assert_snapshot("bar.svg", &SvgChart::new().render(&outputs()));
+    }
+
+    #[test]
+    fn test_render_line() {
+        assert_snapshot(
+            "line.svg",
+            &SvgChart::new().kind(Kind::Line).render(&outputs()),
+        );
+    }
+
+    #[test]
+    fn test_render_cumulative() {
+        assert_snapshot(
+            "at_least.svg",
+            &SvgChart::new()
+                .kind(Kind::Line)
+                .mode(Mode::AtLeast)
+                .size(320, 200)
+                .title("At least".to_owned())
+                .render(&outputs()),
+        );
+    }
+
+    #[test]
+    fn test_render_empty() {
+        assert_snapshot("empty.svg", &SvgChart::new().render(&Outputs::new()));
+    }
+}
@@ -0,0 +1,408 @@
+//! Records of every step taken while rolling a program
+//!
+//! A [`Transcript`] shows how a result came about, like each of the four dice in `{1, 2, 3}@4d6`
+//! and which of them were kept, rather than only the final number.
+
+use std::fmt;
+
+#[cfg(feature = "export")]
+use super::export::json_string;
+use super::value::rolled::Rolled;
+use crate::ast;
+
+/// A value as it was during a single roll of a program
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(i32),
+    Sequence(Vec<i32>),
+    /// Dice with the side each die rolled, in the order they were rolled
+    ///
+    /// A negative count means the sum of the dice is negated.
+    Dice {
+        count: i32,
+        sides: Vec<i32>,
+    },
+}
+
+impl From<&Rolled> for Value {
+    fn from(rolled: &Rolled) -> Self {
+        match rolled {
+            Rolled::Number(value) => Value::Number(*value),
+            Rolled::Sequence(values) => Value::Sequence(values.clone()),
+            Rolled::Dice { count, sides } => Value::Dice {
+                count: *count,
+                sides: sides.clone(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(value) => write!(f, "{}", value),
+            Value::Sequence(values) => write!(f, "{{{}}}", list(values)),
+            Value::Dice { count, sides } if *count < 0 => write!(f, "-[{}]", list(sides)),
+            Value::Dice { sides, .. } => write!(f, "[{}]", list(sides)),
+        }
+    }
+}
+
+/// A single step of rolling a program, in the order it happened
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// New dice were rolled
+    Roll {
+        count: Value,
+        sides: Value,
+        rolled: Value,
+    },
+    /// An operator with one operand was applied
+    Unary {
+        operator: ast::UnaryOperator,
+        operand: Value,
+        result: Value,
+    },
+    /// Dice were accessed by position, keeping only the dice at those positions
+    Access {
+        positions: Value,
+        dice: Value,
+        /// Whether each die, in the order they were rolled, was kept
+        kept: Vec<bool>,
+        result: Value,
+    },
+    /// An operator with two operands, other than rolling or accessing dice, was applied
+    Binary {
+        operator: ast::BinaryOperator,
+        left: Value,
+        right: Value,
+        result: Value,
+    },
+    /// A function was called after rolling its arguments
+    FunctionCall { name: String, arguments: Vec<Value> },
+    /// A variable was given a value
+    Assignment { name: String, value: Value },
+    /// An output showed a value
+    Output { name: String, value: i32 },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Roll {
+                count,
+                sides,
+                rolled,
+            } => write!(f, "roll {}d{}: {}", count, sides, rolled),
+            Step::Unary {
+                operator,
+                operand,
+                result,
+            } => write!(f, "{}{} = {}", operator.symbol(), operand, result),
+            Step::Access {
+                positions,
+                dice,
+                kept,
+                result,
+            } => {
+                write!(f, "{} @ {} = {}", positions, dice, result)?;
+                let dropped = dropped(dice, kept);
+                if !dropped.is_empty() {
+                    write!(f, ", dropping [{}]", list(&dropped))?;
+                }
+
+                Ok(())
+            }
+            Step::Binary {
+                operator,
+                left,
+                right,
+                result,
+            } => write!(f, "{} {} {} = {}", left, operator.symbol(), right, result),
+            Step::FunctionCall { name, arguments } => write!(
+                f,
+                "call [{}] with {}",
+                name,
+                arguments
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Step::Assignment { name, value } => write!(f, "{}: {}", name, value),
+            Step::Output { name, value } => write!(f, "{} = {}", name, value),
+        }
+    }
+}
+
+/// Every step taken during a single roll of a program, and the value of each output
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transcript {
+    steps: Vec<Step>,
+    outputs: Vec<i32>,
+}
+
+impl Transcript {
+    pub(super) fn new(steps: Vec<Step>, outputs: Vec<i32>) -> Self {
+        Self { steps, outputs }
+    }
+
+    pub fn steps(&self) -> &Vec<Step> {
+        &self.steps
+    }
+
+    /// The value of each output, in the order they were output
+    pub fn outputs(&self) -> &Vec<i32> {
+        &self.outputs
+    }
+
+    /// One line for each step
+    pub fn to_text(&self) -> String {
+        self.steps
+            .iter()
+            .map(|step| format!("{}\n", step))
+            .collect()
+    }
+
+    /// An object with a `steps` array, where each step has a `type`, and an `outputs` array
+    ///
+    /// Numbers are JSON numbers, sequences are `{"sequence": [...]}`, and dice are
+    /// `{"count": n, "dice": [...]}`.
+    #[cfg(feature = "export")]
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"steps\":[{}],\"outputs\":[{}]}}",
+            self.steps
+                .iter()
+                .map(step_to_json)
+                .collect::<Vec<_>>()
+                .join(","),
+            list(&self.outputs).replace(' ', "")
+        )
+    }
+}
+
+/// The dice that weren't kept, in the order they were rolled
+fn dropped(dice: &Value, kept: &[bool]) -> Vec<i32> {
+    match dice {
+        Value::Dice { sides, .. } => sides
+            .iter()
+            .zip(kept)
+            .filter(|(_, &kept)| !kept)
+            .map(|(&side, _)| side)
+            .collect(),
+        Value::Number(_) | Value::Sequence(_) => vec![],
+    }
+}
+
+fn list(values: &[i32]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(feature = "export")]
+fn value_to_json(value: &Value) -> String {
+    match value {
+        Value::Number(value) => value.to_string(),
+        Value::Sequence(values) => format!("{{\"sequence\":[{}]}}", list(values).replace(' ', "")),
+        Value::Dice { count, sides } => format!(
+            "{{\"count\":{},\"dice\":[{}]}}",
+            count,
+            list(sides).replace(' ', "")
+        ),
+    }
+}
+
+#[cfg(feature = "export")]
+fn step_to_json(step: &Step) -> String {
+    match step {
+        Step::Roll {
+            count,
+            sides,
+            rolled,
+        } => format!(
+            "{{\"type\":\"roll\",\"count\":{},\"sides\":{},\"rolled\":{}}}",
+            value_to_json(count),
+            value_to_json(sides),
+            value_to_json(rolled)
+        ),
+        Step::Unary {
+            operator,
+            operand,
+            result,
+        } => format!(
+            "{{\"type\":\"unary\",\"operator\":{},\"operand\":{},\"result\":{}}}",
+            json_string(operator.symbol()),
+            value_to_json(operand),
+            value_to_json(result)
+        ),
+        Step::Access {
+            positions,
+            dice,
+            kept,
+            result,
+        } => format!(
+            "{{\"type\":\"access\",\"positions\":{},\"dice\":{},\"kept\":[{}],\"result\":{}}}",
+            value_to_json(positions),
+            value_to_json(dice),
+            kept.iter()
+                .map(|kept| kept.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            value_to_json(result)
+        ),
+        Step::Binary {
+            operator,
+            left,
+            right,
+            result,
+        } => format!(
+            "{{\"type\":\"binary\",\"operator\":{},\"left\":{},\"right\":{},\"result\":{}}}",
+            json_string(operator.symbol()),
+            value_to_json(left),
+            value_to_json(right),
+            value_to_json(result)
+        ),
+        Step::FunctionCall { name, arguments } => format!(
+            "{{\"type\":\"function_call\",\"name\":{},\"arguments\":[{}]}}",
+            json_string(name),
+            arguments
+                .iter()
+                .map(value_to_json)
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        Step::Assignment { name, value } => format!(
+            "{{\"type\":\"assignment\",\"name\":{},\"value\":{}}}",
+            json_string(name),
+            value_to_json(value)
+        ),
+        Step::Output { name, value } => format!(
+            "{{\"type\":\"output\",\"name\":{},\"value\":{}}}",
+            json_string(name),
+            value
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dice(sides: &[i32]) -> Value {
+        Value::Dice {
+            count: sides.len() as i32,
+            sides: sides.to_vec(),
+        }
+    }
+
+    fn transcript() -> Transcript {
+        Transcript::new(
+            vec![
+                Step::Roll {
+                    count: Value::Number(4),
+                    sides: Value::Number(6),
+                    rolled: dice(&[5, 3, 2, 6]),
+                },
+                Step::Access {
+                    positions: Value::Sequence(vec![1, 2, 3]),
+                    dice: dice(&[5, 3, 2, 6]),
+                    kept: vec![true, true, false, true],
+                    result: dice(&[14]),
+                },
+                Step::Assignment {
+                    name: "X".to_owned(),
+                    value: dice(&[14]),
+                },
+                Step::Binary {
+                    operator: ast::BinaryOperator::Add,
+                    left: dice(&[14]),
+                    right: Value::Number(1),
+                    result: dice(&[15]),
+                },
+                Step::Unary {
+                    operator: ast::UnaryOperator::Negate,
+                    operand: Value::Dice {
+                        count: -1,
+                        sides: vec![14],
+                    },
+                    result: Value::Number(14),
+                },
+                Step::FunctionCall {
+                    name: "highest ? of ?".to_owned(),
+                    arguments: vec![Value::Number(3), dice(&[1, 2])],
+                },
+                Step::Output {
+                    name: "say \"hi\"".to_owned(),
+                    value: 14,
+                },
+            ],
+            vec![14],
+        )
+    }
+
+    #[test]
+    fn test_to_text() {
+        assert_eq!(
+            transcript().to_text(),
+            "roll 4d6: [5, 3, 2, 6]\n\
+             {1, 2, 3} @ [5, 3, 2, 6] = [14], dropping [2]\n\
+             X: [14]\n\
+             [14] + 1 = [15]\n\
+             --[14] = 14\n\
+             call [highest ? of ?] with 3, [1, 2]\n\
+             say \"hi\" = 14\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "export")]
+    fn test_to_json() {
+        let json = transcript().to_json();
+
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!({
+                "steps": [
+                    {
+                        "type": "roll",
+                        "count": 4,
+                        "sides": 6,
+                        "rolled": {"count": 4, "dice": [5, 3, 2, 6]},
+                    },
+                    {
+                        "type": "access",
+                        "positions": {"sequence": [1, 2, 3]},
+                        "dice": {"count": 4, "dice": [5, 3, 2, 6]},
+                        "kept": [true, true, false, true],
+                        "result": {"count": 1, "dice": [14]},
+                    },
+                    {"type": "assignment", "name": "X", "value": {"count": 1, "dice": [14]}},
+                    {
+                        "type": "binary",
+                        "operator": "+",
+                        "left": {"count": 1, "dice": [14]},
+                        "right": 1,
+                        "result": {"count": 1, "dice": [15]},
+                    },
+                    {
+                        "type": "unary",
+                        "operator": "-",
+                        "operand": {"count": -1, "dice": [14]},
+                        "result": 14,
+                    },
+                    {
+                        "type": "function_call",
+                        "name": "highest ? of ?",
+                        "arguments": [3, {"count": 2, "dice": [1, 2]}],
+                    },
+                    {"type": "output", "name": "say \"hi\"", "value": 14},
+                ],
+                "outputs": [14],
+            })
+        );
+    }
+}
@@ -1,9 +1,9 @@
-use std::cmp;
 use std::collections;
 use std::iter;
 use std::ops;
 
 use crate::interpret::chance;
+use crate::interpret::error::InterpretError;
 
 #[derive(Clone, Debug, PartialEq)]
 pub(in crate::interpret) struct Dice {
@@ -18,101 +18,189 @@ impl Dice {
 }
 
 impl Dice {
-    pub(in crate::interpret) fn flatten(self) -> Self {
-        Self::new(1, self.into())
+    pub(in crate::interpret) fn flatten(self) -> Result<Self, InterpretError> {
+        Ok(Self::new(1, self.try_into()?))
+    }
+
+    /// Every possible set of rolled dice, ordered highest first, with its chance
+    pub(in crate::interpret) fn pools(
+        &self,
+    ) -> Result<Vec<(Vec<i32>, chance::Chance)>, InterpretError> {
+        fn choose(
+            sides: &[(i32, chance::Chance)],
+            remaining: usize,
+            pool: &mut Vec<i32>,
+            pool_chance: chance::Chance,
+            pools: &mut Vec<(Vec<i32>, chance::Chance)>,
+        ) -> Result<(), InterpretError> {
+            let Some((&(side, side_chance), rest)) = sides.split_first() else {
+                if remaining == 0 {
+                    pools.push((pool.clone(), pool_chance));
+                }
+                return Ok(());
+            };
+
+            // The last side must take all remaining dice
+            let fewest = if rest.is_empty() { remaining } else { 0 };
+            for taken in fewest..=remaining {
+                let taken_chance = pool_chance
+                    .checked_mul(power(side_chance, taken)?)
+                    .and_then(|chance| chance.checked_scale(binomial(remaining, taken)?))
+                    .ok_or(InterpretError::Overflow)?;
+
+                pool.extend(iter::repeat(side).take(taken));
+                choose(rest, remaining - taken, pool, taken_chance, pools)?;
+                pool.truncate(pool.len() - taken);
+            }
+
+            Ok(())
+        }
+
+        let sides = self
+            .die
+            .chances
+            .iter()
+            .rev()
+            .map(|(&side, &chance)| (side, chance))
+            .collect::<Vec<_>>();
+        let mut pools = vec![];
+        choose(
+            &sides,
+            self.count.unsigned_abs() as usize,
+            &mut vec![],
+            chance::Chance::new(1, 1),
+            &mut pools,
+        )?;
+
+        Ok(pools)
     }
 }
 
+fn power(chance: chance::Chance, exponent: usize) -> Result<chance::Chance, InterpretError> {
+    iter::repeat(chance)
+        .take(exponent)
+        .try_fold(chance::Chance::new(1, 1), |acc, curr| acc.checked_mul(curr))
+        .ok_or(InterpretError::Overflow)
+}
+
+fn binomial(n: usize, k: usize) -> Option<usize> {
+    (0..k.min(n - k)).try_fold(1usize, |acc, i| Some(acc.checked_mul(n - i)? / (i + 1)))
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(in crate::interpret) struct Die {
     pub(in crate::interpret) chances: collections::BTreeMap<i32, chance::Chance>,
 }
 
-impl From<Dice> for Die {
-    fn from(val: Dice) -> Self {
+impl TryFrom<Dice> for Die {
+    type Error = InterpretError;
+
+    fn try_from(val: Dice) -> Result<Self, Self::Error> {
         if val.count == 0 {
             return Die::new(vec![]);
         }
 
-        let flattened = iter::repeat_n(
-            val.die,
-            usize::try_from(val.count.abs()).expect("to be an absolute value"),
-        )
-        .reduce(|acc, curr| {
-            Die::new(curr.chances.iter().flat_map(|(&curr_side, &curr_chance)| {
-                acc.chances
-                    .iter()
-                    .map(|(&acc_side, &acc_chance)| {
-                        (curr_side + acc_side, curr_chance * acc_chance)
-                    })
-                    .collect::<Vec<_>>()
-            }))
-        })
-        .expect("dice count to be non-zero");
+        let count = val.count.checked_abs().ok_or(InterpretError::Overflow)?;
+        let mut flattened = Die::new_constant(0);
+        for _ in 0..count {
+            let mut chances = vec![];
+            for (&side, &chance) in &val.die.chances {
+                for (&acc_side, &acc_chance) in &flattened.chances {
+                    chances.push((
+                        side.checked_add(acc_side).ok_or(InterpretError::Overflow)?,
+                        chance
+                            .checked_mul(acc_chance)
+                            .ok_or(InterpretError::Overflow)?,
+                    ));
+                }
+            }
+            flattened = Die::new(chances)?;
+        }
 
         if val.count < 0 {
             flattened * -1
         } else {
-            flattened
+            Ok(flattened)
         }
     }
 }
 
 macro_rules! die_side_arithmetic_impl {
-    ($trait: ty, $fn: ident, $op: tt) => {
+    ($trait: ty, $fn: ident, $checked: ident) => {
         impl $trait for Die {
-            type Output = Self;
+            type Output = Result<Self, InterpretError>;
 
             fn $fn(self, rhs: i32) -> Self::Output {
-                Self::Output::new(
-                    self.chances
-                        .into_iter()
-                        .map(|(side, chance)| (side $op rhs, chance)),
-                )
+                let chances = self
+                    .chances
+                    .into_iter()
+                    .map(|(side, chance)| {
+                        let side = side.$checked(rhs).ok_or(InterpretError::Overflow)?;
+                        Ok((side, chance))
+                    })
+                    .collect::<Result<Vec<_>, InterpretError>>()?;
+
+                Self::new(chances)
             }
         }
     };
 }
 
-die_side_arithmetic_impl!(ops::Add<i32>, add, +);
-die_side_arithmetic_impl!(ops::Sub<i32>, sub, -);
-die_side_arithmetic_impl!(ops::Mul<i32>, mul, *);
-die_side_arithmetic_impl!(ops::Div<i32>, div, /);
+die_side_arithmetic_impl!(ops::Add<i32>, add, checked_add);
+die_side_arithmetic_impl!(ops::Sub<i32>, sub, checked_sub);
+die_side_arithmetic_impl!(ops::Mul<i32>, mul, checked_mul);
+die_side_arithmetic_impl!(ops::Div<i32>, div, checked_div);
 
 impl Die {
+    /// A die with the chance of each side, where the chances of repeated sides are combined
     pub(in crate::interpret) fn new(
         chances: impl iter::IntoIterator<Item = (i32, chance::Chance)>,
-    ) -> Self {
+    ) -> Result<Self, InterpretError> {
+        let mut combined = collections::BTreeMap::<i32, chance::Chance>::new();
+        for (side, chance) in chances {
+            if chance.numerator == 0 {
+                continue;
+            }
+
+            let chance = match combined.get(&side) {
+                Some(previous) => previous
+                    .checked_add(chance)
+                    .ok_or(InterpretError::Overflow)?,
+                None => chance,
+            };
+            combined.insert(side, chance);
+        }
+
+        Ok(Self { chances: combined })
+    }
+
+    /// A die that always rolls the same side
+    pub(in crate::interpret) fn new_constant(side: i32) -> Self {
         Self {
-            chances: chances
-                .into_iter()
-                .filter(|(_, chance)| chance.numerator != 0)
-                .fold(collections::BTreeMap::new(), |mut acc, (value, chance)| {
-                    acc.entry(value)
-                        .and_modify(|prev| *prev = *prev + chance)
-                        .or_insert(chance);
-                    acc
-                }),
+            chances: collections::BTreeMap::from([(side, chance::Chance::new(1, 1))]),
         }
     }
 
-    pub(in crate::interpret) fn new_regular(largest_side: i32) -> Self {
+    /// A die where each value in the sequence is a side with equal chance
+    pub(in crate::interpret) fn new_sequence(sides: &[i32]) -> Self {
+        // Repeated sides add up to at most one, over the same denominator
         Self::new(
-            match largest_side.cmp(&0) {
-                cmp::Ordering::Less => largest_side..0,
-                cmp::Ordering::Equal => 0..0,
-                cmp::Ordering::Greater => 1..(largest_side + 1),
-            }
-            .map(|side| {
-                (
-                    side,
-                    chance::Chance::new(
-                        1,
-                        usize::try_from(largest_side.abs()).expect("to be an absolute value"),
-                    ),
-                )
-            }),
+            sides
+                .iter()
+                .map(|&side| (side, chance::Chance::new(1, sides.len()))),
         )
+        .expect("chances of a sequence to add up to at most one")
+    }
+
+    pub(in crate::interpret) fn new_regular(largest_side: i32) -> Self {
+        // From 1 up to a positive side, or from a negative side up to -1, and empty for 0
+        Self::new((largest_side.min(1)..=largest_side.max(-1)).map(|side| {
+            (
+                side,
+                chance::Chance::new(1, largest_side.unsigned_abs() as usize),
+            )
+        }))
+        .expect("sides of a regular die to all be different")
     }
 }
 
@@ -128,7 +216,8 @@ mod tests {
                 (2, chance::Chance::new(1, 4)),
                 (3, chance::Chance::new(1, 4)),
                 (4, chance::Chance::new(1, 4)),
-            ]),
+            ])
+            .unwrap(),
             Die::new_regular(4)
         );
     }
@@ -144,7 +233,8 @@ mod tests {
                 (3, chance::Chance::new(1, 8)),
                 (2, chance::Chance::new(2, 16)),
                 (1, chance::Chance::new(4, 32)),
-            ]),
+            ])
+            .unwrap(),
             Die::new_regular(4)
         );
     }
@@ -158,7 +248,8 @@ mod tests {
                 (3, chance::Chance::new(1, 4)),
                 (4, chance::Chance::new(1, 4)),
                 (5, chance::Chance::new(0, 4)),
-            ]),
+            ])
+            .unwrap(),
             Die::new_regular(4)
         );
     }
@@ -175,7 +266,10 @@ mod tests {
         chances.insert(8, chance::Chance::new(1, 16));
         let die = Die { chances };
 
-        assert_eq!(Into::<Die>::into(Dice::new(2, Die::new_regular(4))), die);
+        assert_eq!(
+            Die::try_from(Dice::new(2, Die::new_regular(4))).unwrap(),
+            die
+        );
     }
 
     #[test]
@@ -191,92 +285,141 @@ mod tests {
         let die = Die { chances };
 
         assert_eq!(
-            Dice::new(2, Die::new_regular(4)).flatten(),
+            Dice::new(2, Die::new_regular(4)).flatten().unwrap(),
             Dice::new(1, die)
         );
     }
 
     #[test]
+    #[allow(clippy::erasing_op)]
     fn test_die_add_scalar() {
-        assert_eq!(Die::new_regular(4) * 1, Die::new_regular(4));
+        assert_eq!((Die::new_regular(4) * 1).unwrap(), Die::new_regular(4));
 
         assert_eq!(
-            Die::new_regular(4) * 2,
+            (Die::new_regular(4) * 2).unwrap(),
             Die::new(vec![
                 (2, chance::Chance::new(1, 4)),
                 (4, chance::Chance::new(1, 4)),
                 (6, chance::Chance::new(1, 4)),
                 (8, chance::Chance::new(1, 4)),
             ])
+            .unwrap()
         );
 
         assert_eq!(
-            Die::new_regular(4) * -1,
+            (Die::new_regular(4) * -1).unwrap(),
             Die::new(vec![
                 (-1, chance::Chance::new(1, 4)),
                 (-2, chance::Chance::new(1, 4)),
                 (-3, chance::Chance::new(1, 4)),
                 (-4, chance::Chance::new(1, 4)),
             ])
+            .unwrap()
         );
 
         assert_eq!(
-            Die::new_regular(4) * 0,
-            Die::new(vec![(0, chance::Chance::new(1, 1))])
+            (Die::new_regular(4) * 0).unwrap(),
+            Die::new(vec![(0, chance::Chance::new(1, 1))]).unwrap()
         );
     }
 
     #[test]
+    #[allow(clippy::erasing_op)]
     fn test_die_mul_scalar() {
-        assert_eq!(Die::new_regular(4) * 1, Die::new_regular(4));
+        assert_eq!((Die::new_regular(4) * 1).unwrap(), Die::new_regular(4));
 
         assert_eq!(
-            Die::new_regular(4) * 2,
+            (Die::new_regular(4) * 2).unwrap(),
             Die::new(vec![
                 (2, chance::Chance::new(1, 4)),
                 (4, chance::Chance::new(1, 4)),
                 (6, chance::Chance::new(1, 4)),
                 (8, chance::Chance::new(1, 4)),
             ])
+            .unwrap()
         );
 
         assert_eq!(
-            Die::new_regular(4) * -1,
+            (Die::new_regular(4) * -1).unwrap(),
             Die::new(vec![
                 (-1, chance::Chance::new(1, 4)),
                 (-2, chance::Chance::new(1, 4)),
                 (-3, chance::Chance::new(1, 4)),
                 (-4, chance::Chance::new(1, 4)),
             ])
+            .unwrap()
         );
 
         assert_eq!(
-            Die::new_regular(4) * 0,
-            Die::new(vec![(0, chance::Chance::new(1, 1))])
+            (Die::new_regular(4) * 0).unwrap(),
+            Die::new(vec![(0, chance::Chance::new(1, 1))]).unwrap()
         );
     }
 
     #[test]
     fn test_die_div_scalar() {
-        assert_eq!(Die::new_regular(4) / 1, Die::new_regular(4));
+        assert_eq!((Die::new_regular(4) / 1).unwrap(), Die::new_regular(4));
 
         assert_eq!(
-            Die::new_regular(4) / 2,
+            (Die::new_regular(4) / 2).unwrap(),
             Die::new(vec![
                 (0, chance::Chance::new(1, 4)),
                 (1, chance::Chance::new(1, 2)),
                 (2, chance::Chance::new(1, 4)),
             ])
+            .unwrap()
         );
 
         assert_eq!(
-            Die::new_regular(4) / -1,
+            (Die::new_regular(4) / -1).unwrap(),
             Die::new(vec![
                 (-1, chance::Chance::new(1, 4)),
                 (-2, chance::Chance::new(1, 4)),
                 (-3, chance::Chance::new(1, 4)),
                 (-4, chance::Chance::new(1, 4)),
             ])
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let large = || Die::new_sequence(&[2_000_000_000]);
+        let smallest = || Die::new_constant(i32::MIN);
+
+        assert_eq!(
+            Die::try_from(Dice::new(2, large())),
+            Err(InterpretError::Overflow)
+        );
+        assert_eq!(
+            Die::try_from(Dice::new(i32::MIN, Die::new_regular(6))),
+            Err(InterpretError::Overflow)
+        );
+        assert_eq!(smallest() * -1, Err(InterpretError::Overflow));
+        assert_eq!(smallest() / -1, Err(InterpretError::Overflow));
+        assert_eq!(smallest() - 1, Err(InterpretError::Overflow));
+        assert_eq!(large() + 200_000_000, Err(InterpretError::Overflow));
+    }
+
+    #[test]
+    fn test_pools() {
+        assert_eq!(
+            Dice::new(2, Die::new_regular(2)).pools().unwrap(),
+            vec![
+                (vec![1, 1], chance::Chance::new(1, 4)),
+                (vec![2, 1], chance::Chance::new(1, 2)),
+                (vec![2, 2], chance::Chance::new(1, 4)),
+            ]
+        );
+
+        assert_eq!(
+            Dice::new(0, Die::new_regular(2)).pools().unwrap(),
+            vec![(vec![], chance::Chance::new(1, 1))]
+        );
+
+        assert_eq!(
+            Dice::new(2, Die::new(vec![]).unwrap()).pools().unwrap(),
+            vec![]
         );
     }
 }
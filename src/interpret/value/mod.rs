@@ -1,7 +1,11 @@
 mod dice;
 mod number;
+mod operation;
+#[cfg(feature = "sample")]
+pub(super) mod rolled;
 mod sequence;
 
+use super::error::InterpretError;
 use super::factor;
 use crate::outcome;
 
@@ -12,15 +16,14 @@ pub(super) enum Value {
     Sequence(sequence::Sequence),
 }
 
-impl From<Value> for Vec<outcome::Outcome> {
-    fn from(val: Value) -> Self {
-        match val {
-            Value::Number(number::Number { value }) => vec![outcome::Outcome {
-                value: value,
-                weight: 1,
-            }],
+impl TryFrom<Value> for Vec<outcome::Outcome> {
+    type Error = InterpretError;
+
+    fn try_from(val: Value) -> Result<Self, Self::Error> {
+        Ok(match val {
+            Value::Number(number::Number { value }) => vec![outcome::Outcome { value, weight: 1 }],
             Value::Dice(dice) => {
-                let die: dice::Die = dice.into();
+                let die: dice::Die = dice.try_into()?;
 
                 if die.chances.is_empty() {
                     vec![outcome::Outcome {
@@ -32,16 +35,22 @@ impl From<Value> for Vec<outcome::Outcome> {
                     let lcm = die
                         .chances
                         .iter()
-                        .fold(1, |acc, (_, chance)| factor::lcm(acc, chance.denominator));
+                        .try_fold(1, |acc, (_, chance)| {
+                            factor::checked_lcm(acc, chance.denominator)
+                        })
+                        .ok_or(InterpretError::Overflow)?;
 
                     let outcomes = die
                         .chances
                         .iter()
-                        .map(|(&side, chance)| outcome::Outcome {
-                            value: side,
-                            weight: chance.numerator * lcm / chance.denominator,
+                        .map(|(&side, chance)| {
+                            Some(outcome::Outcome {
+                                value: side,
+                                weight: chance.numerator.checked_mul(lcm / chance.denominator)?,
+                            })
                         })
-                        .collect::<Vec<_>>();
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or(InterpretError::Overflow)?;
 
                     // Determine GCD of weights to scale down to smallest whole weights
                     let gcd = outcomes.iter().fold(outcomes[0].weight, |acc, outcome| {
@@ -67,14 +76,11 @@ impl From<Value> for Vec<outcome::Outcome> {
                     sequence
                         .values
                         .iter()
-                        .map(|&value| outcome::Outcome {
-                            value: value,
-                            weight: 1,
-                        })
+                        .map(|&value| outcome::Outcome { value, weight: 1 })
                         .collect()
                 }
             }
-        }
+        })
     }
 }
 
@@ -86,7 +92,8 @@ mod tests {
     #[test]
     fn test_number_into_outcomes() {
         assert_eq!(
-            Into::<Vec<outcome::Outcome>>::into(Value::Number(number::Number { value: 1 })),
+            TryInto::<Vec<outcome::Outcome>>::try_into(Value::Number(number::Number { value: 1 }))
+                .unwrap(),
             vec![outcome::Outcome {
                 value: 1,
                 weight: 1,
@@ -97,7 +104,7 @@ mod tests {
     #[test]
     fn test_dice_into_outcomes() {
         assert_eq!(
-            Into::<Vec<outcome::Outcome>>::into(Value::Dice(dice::Dice {
+            TryInto::<Vec<outcome::Outcome>>::try_into(Value::Dice(dice::Dice {
                 count: 1,
                 die: dice::Die::new(vec![
                     (1, chance::Chance::new(1, 4)),
@@ -105,7 +112,9 @@ mod tests {
                     (3, chance::Chance::new(1, 4)),
                     (4, chance::Chance::new(1, 4)),
                 ])
-            })),
+                .unwrap()
+            }))
+            .unwrap(),
             vec![
                 outcome::Outcome {
                     value: 1,
@@ -127,14 +136,16 @@ mod tests {
         );
 
         assert_eq!(
-            Into::<Vec<outcome::Outcome>>::into(Value::Dice(dice::Dice {
+            TryInto::<Vec<outcome::Outcome>>::try_into(Value::Dice(dice::Dice {
                 count: 1,
                 die: dice::Die::new(vec![
                     (1, chance::Chance::new(1, 2)),
                     (2, chance::Chance::new(1, 3)),
                     (3, chance::Chance::new(1, 6)),
                 ])
-            })),
+                .unwrap()
+            }))
+            .unwrap(),
             vec![
                 outcome::Outcome {
                     value: 1,
@@ -155,10 +166,11 @@ mod tests {
     #[test]
     fn test_empty_dice_into_outcomes() {
         assert_eq!(
-            Into::<Vec<outcome::Outcome>>::into(Value::Dice(dice::Dice {
+            TryInto::<Vec<outcome::Outcome>>::try_into(Value::Dice(dice::Dice {
                 count: 1,
-                die: dice::Die::new(vec![])
-            })),
+                die: dice::Die::new(vec![]).unwrap()
+            }))
+            .unwrap(),
             vec![outcome::Outcome {
                 value: 0,
                 weight: 1,
@@ -166,7 +178,7 @@ mod tests {
         );
 
         assert_eq!(
-            Into::<Vec<outcome::Outcome>>::into(Value::Dice(dice::Dice {
+            TryInto::<Vec<outcome::Outcome>>::try_into(Value::Dice(dice::Dice {
                 count: 0,
                 die: dice::Die::new(vec![
                     (1, chance::Chance::new(1, 4)),
@@ -174,7 +186,9 @@ mod tests {
                     (3, chance::Chance::new(1, 4)),
                     (4, chance::Chance::new(1, 4)),
                 ])
-            })),
+                .unwrap()
+            }))
+            .unwrap(),
             vec![outcome::Outcome {
                 value: 0,
                 weight: 1,
@@ -185,9 +199,10 @@ mod tests {
     #[test]
     fn test_sequence_into_outcomes() {
         assert_eq!(
-            Into::<Vec<outcome::Outcome>>::into(Value::Sequence(sequence::Sequence {
+            TryInto::<Vec<outcome::Outcome>>::try_into(Value::Sequence(sequence::Sequence {
                 values: vec![1, 2, 3, 4]
-            })),
+            }))
+            .unwrap(),
             vec![
                 outcome::Outcome {
                     value: 1,
@@ -212,9 +227,10 @@ mod tests {
     #[test]
     fn test_empty_sequence_into_outcomes() {
         assert_eq!(
-            Into::<Vec<outcome::Outcome>>::into(Value::Sequence(sequence::Sequence {
+            TryInto::<Vec<outcome::Outcome>>::try_into(Value::Sequence(sequence::Sequence {
                 values: vec![]
-            })),
+            }))
+            .unwrap(),
             vec![outcome::Outcome {
                 value: 0,
                 weight: 1,
@@ -0,0 +1,646 @@
+use super::{dice, number, sequence, Value};
+use crate::ast;
+use crate::interpret::{chance, config, error::InterpretError};
+
+/// A single roll of a value, where any dice have already been replaced by one of their sides
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Operand {
+    Number(i32),
+    Sequence(Vec<i32>),
+}
+
+impl Operand {
+    /// Sequences are summed when used as a number
+    pub(super) fn sum(&self) -> Result<i32, InterpretError> {
+        match self {
+            Operand::Number(value) => Ok(*value),
+            Operand::Sequence(values) => values
+                .iter()
+                .try_fold(0i32, |acc, &value| acc.checked_add(value))
+                .ok_or(InterpretError::Overflow),
+        }
+    }
+}
+
+/// Every possible roll of a value with its chance
+fn rolls(value: Value) -> Result<Vec<(Operand, chance::Chance)>, InterpretError> {
+    let certain = chance::Chance::new(1, 1);
+
+    Ok(match value {
+        Value::Number(number::Number { value }) => vec![(Operand::Number(value), certain)],
+        Value::Sequence(sequence::Sequence { values }) => {
+            vec![(Operand::Sequence(values), certain)]
+        }
+        Value::Dice(dice) if dice.count == 0 => vec![(Operand::Number(0), certain)],
+        Value::Dice(dice) => dice
+            .flatten()?
+            .die
+            .chances
+            .into_iter()
+            .map(|(side, chance)| (Operand::Number(side), chance))
+            .collect(),
+    })
+}
+
+/// Apply an operation to every roll of a value, resulting in dice
+fn map_rolls(
+    value: Value,
+    operation: impl Fn(Operand) -> Result<i32, InterpretError>,
+) -> Result<Value, InterpretError> {
+    let chances = rolls(value)?
+        .into_iter()
+        .map(|(operand, chance)| Ok((operation(operand)?, chance)))
+        .collect::<Result<Vec<_>, InterpretError>>()?;
+
+    Ok(Value::Dice(dice::Dice::new(1, dice::Die::new(chances)?)))
+}
+
+pub(super) fn boolean(value: bool) -> i32 {
+    if value {
+        1
+    } else {
+        0
+    }
+}
+
+pub(super) fn digits(value: i32) -> Vec<i32> {
+    value
+        .unsigned_abs()
+        .to_string()
+        .chars()
+        .map(|digit| digit.to_digit(10).expect("to be a decimal digit") as i32)
+        .collect()
+}
+
+fn exponent(base: i32, exponent: i32) -> Result<i32, InterpretError> {
+    match u32::try_from(exponent) {
+        Ok(exponent) => base.checked_pow(exponent).ok_or(InterpretError::Overflow),
+        // Negative exponents are a division, which is truncated toward zero
+        Err(_) => match base {
+            0 => Err(InterpretError::DivisionByZero),
+            1 => Ok(1),
+            -1 if exponent % 2 == 0 => Ok(1),
+            -1 => Ok(-1),
+            _ => Ok(0),
+        },
+    }
+}
+
+/// Sum the entries at 1-based positions, counting from the end instead when reversed
+///
+/// Any position out of range is skipped.
+pub(super) fn select(
+    positions: &[i32],
+    entries: &[i32],
+    reverse: bool,
+) -> Result<i32, InterpretError> {
+    positions
+        .iter()
+        .filter_map(|&position| usize::try_from(position).ok())
+        .filter(|&position| position > 0 && position <= entries.len())
+        .map(|position| match reverse {
+            false => entries[position - 1],
+            true => entries[entries.len() - position],
+        })
+        .try_fold(0i32, |acc, entry| acc.checked_add(entry))
+        .ok_or(InterpretError::Overflow)
+}
+
+fn compare(operator: ast::BinaryOperator, left: i32, right: i32) -> bool {
+    match operator {
+        ast::BinaryOperator::Equal => left == right,
+        ast::BinaryOperator::NotEqual => left != right,
+        ast::BinaryOperator::Less => left < right,
+        ast::BinaryOperator::Greater => left > right,
+        ast::BinaryOperator::LessOrEqual => left <= right,
+        ast::BinaryOperator::GreaterOrEqual => left >= right,
+        _ => unreachable!("only comparison operators are passed"),
+    }
+}
+
+fn is_comparison(operator: ast::BinaryOperator) -> bool {
+    matches!(
+        operator,
+        ast::BinaryOperator::Equal
+            | ast::BinaryOperator::NotEqual
+            | ast::BinaryOperator::Less
+            | ast::BinaryOperator::Greater
+            | ast::BinaryOperator::LessOrEqual
+            | ast::BinaryOperator::GreaterOrEqual
+    )
+}
+
+/// Apply an arithmetic, boolean, or comparison operator to a single roll of each operand
+pub(super) fn apply(
+    operator: ast::BinaryOperator,
+    left: &Operand,
+    right: &Operand,
+) -> Result<i32, InterpretError> {
+    if is_comparison(operator) {
+        // Comparing a number with a sequence counts the matching entries
+        let count = |matches: usize| i32::try_from(matches).map_err(|_| InterpretError::Overflow);
+
+        return match (left, right) {
+            (Operand::Number(left), Operand::Sequence(right)) => count(
+                right
+                    .iter()
+                    .filter(|&&right| compare(operator, *left, right))
+                    .count(),
+            ),
+            (Operand::Sequence(left), Operand::Number(right)) => count(
+                left.iter()
+                    .filter(|&&left| compare(operator, left, *right))
+                    .count(),
+            ),
+            _ => Ok(boolean(compare(operator, left.sum()?, right.sum()?))),
+        };
+    }
+
+    let (left, right) = (left.sum()?, right.sum()?);
+
+    match operator {
+        ast::BinaryOperator::Exponent => exponent(left, right),
+        ast::BinaryOperator::Multiply => left.checked_mul(right).ok_or(InterpretError::Overflow),
+        ast::BinaryOperator::Divide if right == 0 => Err(InterpretError::DivisionByZero),
+        ast::BinaryOperator::Divide => left.checked_div(right).ok_or(InterpretError::Overflow),
+        ast::BinaryOperator::Add => left.checked_add(right).ok_or(InterpretError::Overflow),
+        ast::BinaryOperator::Subtract => left.checked_sub(right).ok_or(InterpretError::Overflow),
+        ast::BinaryOperator::And => Ok(boolean(left != 0 && right != 0)),
+        ast::BinaryOperator::Or => Ok(boolean(left != 0 || right != 0)),
+        _ => unreachable!("dice and access operators are handled separately"),
+    }
+}
+
+impl Value {
+    pub(in crate::interpret) fn number(value: i32) -> Value {
+        Value::Number(number::Number { value })
+    }
+
+    pub(in crate::interpret) fn sequence(values: Vec<i32>) -> Value {
+        Value::Sequence(sequence::Sequence { values })
+    }
+
+    /// The numbers this value contributes to a sequence, where nested sequences are flattened
+    pub(in crate::interpret) fn into_sequence_entries(self) -> Result<Vec<i32>, InterpretError> {
+        match self {
+            Value::Number(number::Number { value }) => Ok(vec![value]),
+            Value::Sequence(sequence::Sequence { values }) => Ok(values),
+            Value::Dice(_) => Err(InterpretError::UnexpectedDice),
+        }
+    }
+
+    /// A single number, or an error for any other value
+    pub(in crate::interpret) fn as_number(&self) -> Result<i32, InterpretError> {
+        match self {
+            Value::Number(number::Number { value }) => Ok(*value),
+            Value::Dice(_) => Err(InterpretError::UnexpectedDice),
+            Value::Sequence(_) => Err(InterpretError::ExpectedNumber),
+        }
+    }
+
+    /// Apply an operator with only one operand
+    pub(in crate::interpret) fn unary(
+        self,
+        operator: ast::UnaryOperator,
+    ) -> Result<Value, InterpretError> {
+        match (operator, self) {
+            (ast::UnaryOperator::Length, Value::Number(number::Number { value })) => {
+                Ok(Value::number(digits(value).len() as i32))
+            }
+            (ast::UnaryOperator::Length, Value::Sequence(sequence::Sequence { values })) => {
+                i32::try_from(values.len())
+                    .map(Value::number)
+                    .map_err(|_| InterpretError::Overflow)
+            }
+            (ast::UnaryOperator::Length, Value::Dice(dice)) => Ok(Value::number(
+                dice.count.checked_abs().ok_or(InterpretError::Overflow)?,
+            )),
+            (ast::UnaryOperator::Negate, Value::Dice(dice)) => {
+                Ok(Value::Dice(dice::Dice::new(dice.count, (dice.die * -1)?)))
+            }
+            (ast::UnaryOperator::Negate, value @ (Value::Number(_) | Value::Sequence(_))) => {
+                rolls(value)?[0]
+                    .0
+                    .sum()?
+                    .checked_neg()
+                    .map(Value::number)
+                    .ok_or(InterpretError::Overflow)
+            }
+            (ast::UnaryOperator::Not, value @ Value::Dice(_)) => {
+                map_rolls(value, |operand| Ok(boolean(operand.sum()? == 0)))
+            }
+            (ast::UnaryOperator::Not, value) => {
+                Ok(Value::number(boolean(rolls(value)?[0].0.sum()? == 0)))
+            }
+        }
+    }
+
+    /// Apply an operator with a left and right operand
+    pub(in crate::interpret) fn binary(
+        operator: ast::BinaryOperator,
+        left: Value,
+        right: Value,
+        position_order: &config::PositionOrder,
+    ) -> Result<Value, InterpretError> {
+        match operator {
+            ast::BinaryOperator::Dice => Self::roll(left, right),
+            ast::BinaryOperator::Access => Self::access(left, right, position_order),
+            _ if matches!(left, Value::Dice(_)) || matches!(right, Value::Dice(_)) => {
+                let right = rolls(right)?;
+                let mut chances = vec![];
+                for (left, left_chance) in rolls(left)? {
+                    for (right, right_chance) in &right {
+                        let chance = left_chance
+                            .checked_mul(*right_chance)
+                            .ok_or(InterpretError::Overflow)?;
+                        chances.push((apply(operator, &left, right)?, chance));
+                    }
+                }
+
+                Ok(Value::Dice(dice::Dice::new(1, dice::Die::new(chances)?)))
+            }
+            _ => {
+                let left = rolls(left)?.remove(0).0;
+                let right = rolls(right)?.remove(0).0;
+
+                apply(operator, &left, &right).map(Value::number)
+            }
+        }
+    }
+
+    /// Roll a number of dice, where the count and sides may both be any value
+    fn roll(count: Value, sides: Value) -> Result<Value, InterpretError> {
+        let die = match sides {
+            Value::Number(number::Number { value }) => dice::Die::new_regular(value),
+            Value::Sequence(sequence::Sequence { values }) => dice::Die::new_sequence(&values),
+            Value::Dice(dice) if dice.count == 0 => dice::Die::new_constant(0),
+            Value::Dice(dice) => dice.try_into()?,
+        };
+
+        match count {
+            Value::Dice(_) => {
+                // A random count of dice is the combination of every possible count
+                let mut chances = vec![];
+                for (operand, count_chance) in rolls(count)? {
+                    let count = operand.sum()?;
+                    let rolled: dice::Die = if count == 0 {
+                        dice::Die::new_constant(0)
+                    } else {
+                        dice::Dice::new(count, die.clone()).try_into()?
+                    };
+
+                    for (side, chance) in rolled.chances {
+                        let chance = chance
+                            .checked_mul(count_chance)
+                            .ok_or(InterpretError::Overflow)?;
+                        chances.push((side, chance));
+                    }
+                }
+
+                Ok(Value::Dice(dice::Dice::new(1, dice::Die::new(chances)?)))
+            }
+            count => Ok(Value::Dice(dice::Dice::new(rolls(count)?[0].0.sum()?, die))),
+        }
+    }
+
+    /// Get the value at a 1-based index of a collection
+    ///
+    /// Many indexes sum the value at each index, and any index out of range is 0.
+    fn access(
+        index: Value,
+        collection: Value,
+        position_order: &config::PositionOrder,
+    ) -> Result<Value, InterpretError> {
+        let positions = match index {
+            Value::Number(number::Number { value }) => vec![value],
+            Value::Sequence(sequence::Sequence { values }) => values,
+            Value::Dice(_) => return Err(InterpretError::UnexpectedDice),
+        };
+
+        let select = |entries: &[i32], reverse: bool| select(&positions, entries, reverse);
+        let lowest_first = *position_order == config::PositionOrder::LowestFirst;
+
+        match collection {
+            // Sequences are always accessed in their written order
+            Value::Sequence(sequence::Sequence { values }) => {
+                select(&values, false).map(Value::number)
+            }
+            Value::Number(number::Number { value }) => {
+                select(&digits(value), lowest_first).map(Value::number)
+            }
+            Value::Dice(dice) => {
+                let chances = dice
+                    .pools()?
+                    .into_iter()
+                    .map(|(pool, chance)| Ok((select(&pool, lowest_first)?, chance)))
+                    .collect::<Result<Vec<_>, InterpretError>>()?;
+
+                Ok(Value::Dice(dice::Dice::new(1, dice::Die::new(chances)?)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcomes(value: Value) -> Vec<(i32, usize)> {
+        TryInto::<Vec<crate::outcome::Outcome>>::try_into(value)
+            .unwrap()
+            .into_iter()
+            .map(|outcome| (outcome.value, outcome.weight))
+            .collect()
+    }
+
+    fn d(sides: i32) -> Value {
+        Value::Dice(dice::Dice::new(1, dice::Die::new_regular(sides)))
+    }
+
+    fn dice(count: i32, sides: i32) -> Value {
+        Value::Dice(dice::Dice::new(count, dice::Die::new_regular(sides)))
+    }
+
+    fn seq(values: Vec<i32>) -> Value {
+        Value::sequence(values)
+    }
+
+    fn binary(operator: ast::BinaryOperator, left: Value, right: Value) -> Value {
+        Value::binary(operator, left, right, &config::PositionOrder::HighestFirst).unwrap()
+    }
+
+    #[test]
+    fn test_number_arithmetic() {
+        use ast::BinaryOperator::*;
+
+        assert_eq!(
+            binary(Exponent, Value::number(2), Value::number(3)),
+            Value::number(8)
+        );
+        assert_eq!(
+            binary(Multiply, Value::number(2), Value::number(3)),
+            Value::number(6)
+        );
+        assert_eq!(
+            binary(Divide, Value::number(-5), Value::number(3)),
+            Value::number(-1)
+        );
+        assert_eq!(
+            binary(Add, Value::number(1), Value::number(2)),
+            Value::number(3)
+        );
+        assert_eq!(
+            binary(Subtract, Value::number(3), Value::number(2)),
+            Value::number(1)
+        );
+        assert_eq!(
+            binary(And, Value::number(3), Value::number(-2)),
+            Value::number(1)
+        );
+        assert_eq!(
+            binary(Or, Value::number(0), Value::number(0)),
+            Value::number(0)
+        );
+        assert_eq!(
+            binary(LessOrEqual, Value::number(2), Value::number(2)),
+            Value::number(1)
+        );
+    }
+
+    #[test]
+    fn test_number_arithmetic_errors() {
+        let order = config::PositionOrder::HighestFirst;
+
+        assert_eq!(
+            Value::binary(
+                ast::BinaryOperator::Divide,
+                Value::number(1),
+                Value::number(0),
+                &order
+            ),
+            Err(InterpretError::DivisionByZero)
+        );
+        assert_eq!(
+            Value::binary(
+                ast::BinaryOperator::Add,
+                Value::number(i32::MAX),
+                Value::number(1),
+                &order
+            ),
+            Err(InterpretError::Overflow)
+        );
+        assert_eq!(
+            Value::binary(ast::BinaryOperator::Access, d(6), seq(vec![1, 2]), &order),
+            Err(InterpretError::UnexpectedDice)
+        );
+    }
+
+    #[test]
+    fn test_negative_exponent() {
+        assert_eq!(exponent(2, -1), Ok(0));
+        assert_eq!(exponent(-1, -3), Ok(-1));
+        assert_eq!(exponent(0, -1), Err(InterpretError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_sequence_is_summed() {
+        assert_eq!(
+            binary(
+                ast::BinaryOperator::Add,
+                seq(vec![1, 2, 3]),
+                Value::number(1)
+            ),
+            Value::number(7)
+        );
+        assert_eq!(
+            seq(vec![1, 2]).unary(ast::UnaryOperator::Negate),
+            Ok(Value::number(-3))
+        );
+    }
+
+    #[test]
+    fn test_sequence_comparison_counts() {
+        assert_eq!(
+            binary(
+                ast::BinaryOperator::Greater,
+                Value::number(3),
+                seq(vec![1, 2, 3, 4])
+            ),
+            Value::number(2)
+        );
+        assert_eq!(
+            binary(
+                ast::BinaryOperator::Greater,
+                seq(vec![1, 2, 3, 4]),
+                Value::number(3)
+            ),
+            Value::number(1)
+        );
+    }
+
+    #[test]
+    fn test_length() {
+        assert_eq!(
+            Value::number(-123).unary(ast::UnaryOperator::Length),
+            Ok(Value::number(3))
+        );
+        assert_eq!(
+            seq(vec![2, 4, 6]).unary(ast::UnaryOperator::Length),
+            Ok(Value::number(3))
+        );
+        assert_eq!(
+            dice(3, 6).unary(ast::UnaryOperator::Length),
+            Ok(Value::number(3))
+        );
+    }
+
+    #[test]
+    fn test_dice_arithmetic() {
+        assert_eq!(
+            outcomes(binary(ast::BinaryOperator::Add, d(2), Value::number(1))),
+            vec![(2, 1), (3, 1)]
+        );
+        assert_eq!(
+            outcomes(binary(ast::BinaryOperator::Add, d(2), d(2))),
+            vec![(2, 1), (3, 2), (4, 1)]
+        );
+        assert_eq!(
+            outcomes(binary(
+                ast::BinaryOperator::GreaterOrEqual,
+                d(6),
+                Value::number(5)
+            )),
+            vec![(0, 2), (1, 1)]
+        );
+        assert_eq!(
+            outcomes(binary(ast::BinaryOperator::Equal, d(4), seq(vec![1, 1, 2]))),
+            vec![(0, 2), (1, 1), (2, 1)]
+        );
+        assert_eq!(
+            outcomes(d(2).unary(ast::UnaryOperator::Not).unwrap()),
+            vec![(0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_roll() {
+        assert_eq!(
+            outcomes(binary(
+                ast::BinaryOperator::Dice,
+                Value::number(2),
+                Value::number(2)
+            )),
+            vec![(2, 1), (3, 2), (4, 1)]
+        );
+        assert_eq!(
+            outcomes(binary(
+                ast::BinaryOperator::Dice,
+                Value::number(1),
+                seq(vec![1, 1, 3])
+            )),
+            vec![(1, 2), (3, 1)]
+        );
+        assert_eq!(
+            outcomes(binary(ast::BinaryOperator::Dice, d(2), Value::number(2))),
+            vec![(1, 2), (2, 3), (3, 2), (4, 1)]
+        );
+        assert_eq!(
+            outcomes(binary(ast::BinaryOperator::Dice, Value::number(2), d(2))),
+            vec![(2, 1), (3, 2), (4, 1)]
+        );
+    }
+
+    #[test]
+    fn test_access() {
+        assert_eq!(
+            binary(
+                ast::BinaryOperator::Access,
+                Value::number(3),
+                seq(vec![2, 4, 6])
+            ),
+            Value::number(6)
+        );
+        assert_eq!(
+            binary(
+                ast::BinaryOperator::Access,
+                Value::number(4),
+                seq(vec![2, 4, 6])
+            ),
+            Value::number(0)
+        );
+        assert_eq!(
+            binary(
+                ast::BinaryOperator::Access,
+                Value::number(1),
+                Value::number(246)
+            ),
+            Value::number(2)
+        );
+        assert_eq!(
+            Value::binary(
+                ast::BinaryOperator::Access,
+                Value::number(1),
+                Value::number(246),
+                &config::PositionOrder::LowestFirst
+            ),
+            Ok(Value::number(6))
+        );
+        assert_eq!(
+            binary(
+                ast::BinaryOperator::Access,
+                seq(vec![1, 3]),
+                seq(vec![2, 4, 6])
+            ),
+            Value::number(8)
+        );
+    }
+
+    #[test]
+    fn test_access_dice() {
+        // Documented on `ast::BinaryOperator::Access`
+        assert_eq!(
+            outcomes(binary(
+                ast::BinaryOperator::Access,
+                Value::number(1),
+                dice(3, 6)
+            )),
+            vec![(1, 1), (2, 7), (3, 19), (4, 37), (5, 61), (6, 91)]
+        );
+        assert_eq!(
+            outcomes(binary(
+                ast::BinaryOperator::Access,
+                Value::number(3),
+                dice(3, 6)
+            )),
+            vec![(1, 91), (2, 61), (3, 37), (4, 19), (5, 7), (6, 1)]
+        );
+        assert_eq!(
+            outcomes(
+                Value::binary(
+                    ast::BinaryOperator::Access,
+                    Value::number(1),
+                    dice(3, 6),
+                    &config::PositionOrder::LowestFirst
+                )
+                .unwrap()
+            ),
+            vec![(1, 91), (2, 61), (3, 37), (4, 19), (5, 7), (6, 1)]
+        );
+        assert_eq!(
+            outcomes(binary(
+                ast::BinaryOperator::Access,
+                Value::number(4),
+                dice(3, 6)
+            )),
+            vec![(0, 1)]
+        );
+        assert_eq!(
+            outcomes(binary(
+                ast::BinaryOperator::Access,
+                seq(vec![1, 2]),
+                dice(2, 2)
+            )),
+            vec![(2, 1), (3, 2), (4, 1)]
+        );
+    }
+}
@@ -0,0 +1,418 @@
+use rand::seq::SliceRandom;
+
+use super::operation::{apply, boolean, digits, select, Operand};
+use crate::ast;
+use crate::interpret::{config, error::InterpretError};
+
+/// A value from a single run of a program, where every die has been replaced by one of its sides
+///
+/// Operators behave exactly as they do on [values][0], except that dice are known sides instead
+/// of chances. A die without any sides rolls a 0.
+///
+/// [0]: super::Value
+#[derive(Clone, Debug, PartialEq)]
+pub(in crate::interpret) enum Rolled {
+    Number(i32),
+    Sequence(Vec<i32>),
+    /// Rolled dice keep the side of each die so that they can still be accessed by position
+    Dice {
+        count: i32,
+        sides: Vec<i32>,
+    },
+}
+
+/// The sides a die can roll, each with an equal chance
+enum Faces {
+    Range(i32, i32),
+    List(Vec<i32>),
+}
+
+impl Faces {
+    fn roll<R: rand::Rng>(&self, rng: &mut R) -> i32 {
+        match self {
+            Faces::Range(low, high) if low <= high => rng.gen_range(*low..=*high),
+            Faces::Range(..) => 0,
+            Faces::List(sides) => sides.choose(rng).copied().unwrap_or(0),
+        }
+    }
+}
+
+impl Rolled {
+    /// A single die that rolled the given side
+    fn die(side: i32) -> Rolled {
+        Rolled::Dice {
+            count: 1,
+            sides: vec![side],
+        }
+    }
+
+    /// Dice are summed as they are when used as a number
+    fn operand(&self) -> Result<Operand, InterpretError> {
+        match self {
+            Rolled::Number(value) => Ok(Operand::Number(*value)),
+            Rolled::Sequence(values) => Ok(Operand::Sequence(values.clone())),
+            Rolled::Dice { count, sides } => {
+                let sum = Operand::Sequence(sides.clone()).sum()?;
+
+                if *count < 0 {
+                    sum.checked_neg()
+                        .map(Operand::Number)
+                        .ok_or(InterpretError::Overflow)
+                } else {
+                    Ok(Operand::Number(sum))
+                }
+            }
+        }
+    }
+
+    /// The numbers this value contributes to a sequence, where nested sequences are flattened
+    pub(in crate::interpret) fn into_sequence_entries(self) -> Result<Vec<i32>, InterpretError> {
+        match self {
+            Rolled::Number(value) => Ok(vec![value]),
+            Rolled::Sequence(values) => Ok(values),
+            Rolled::Dice { .. } => Err(InterpretError::UnexpectedDice),
+        }
+    }
+
+    /// A single number, or an error for any other value
+    pub(in crate::interpret) fn as_number(&self) -> Result<i32, InterpretError> {
+        match self {
+            Rolled::Number(value) => Ok(*value),
+            Rolled::Dice { .. } => Err(InterpretError::UnexpectedDice),
+            Rolled::Sequence(_) => Err(InterpretError::ExpectedNumber),
+        }
+    }
+
+    /// The number this value shows as an output
+    ///
+    /// An output of a sequence is one of its entries picked at random, like a die with those sides.
+    pub(in crate::interpret) fn output<R: rand::Rng>(
+        &self,
+        rng: &mut R,
+    ) -> Result<i32, InterpretError> {
+        match self {
+            Rolled::Sequence(values) => Ok(values.choose(rng).copied().unwrap_or(0)),
+            value => value.operand()?.sum(),
+        }
+    }
+
+    /// Apply an operator with only one operand
+    pub(in crate::interpret) fn unary(
+        self,
+        operator: ast::UnaryOperator,
+    ) -> Result<Rolled, InterpretError> {
+        match (operator, self) {
+            (ast::UnaryOperator::Length, Rolled::Number(value)) => {
+                Ok(Rolled::Number(digits(value).len() as i32))
+            }
+            (ast::UnaryOperator::Length, Rolled::Sequence(values)) => i32::try_from(values.len())
+                .map(Rolled::Number)
+                .map_err(|_| InterpretError::Overflow),
+            (ast::UnaryOperator::Length, Rolled::Dice { count, .. }) => Ok(Rolled::Number(
+                count.checked_abs().ok_or(InterpretError::Overflow)?,
+            )),
+            (ast::UnaryOperator::Negate, Rolled::Dice { count, sides }) => Ok(Rolled::Dice {
+                count,
+                sides: sides
+                    .into_iter()
+                    .map(|side| side.checked_neg().ok_or(InterpretError::Overflow))
+                    .collect::<Result<_, _>>()?,
+            }),
+            (ast::UnaryOperator::Negate, value) => value
+                .operand()?
+                .sum()?
+                .checked_neg()
+                .map(Rolled::Number)
+                .ok_or(InterpretError::Overflow),
+            (ast::UnaryOperator::Not, value @ Rolled::Dice { .. }) => {
+                Ok(Rolled::die(boolean(value.operand()?.sum()? == 0)))
+            }
+            (ast::UnaryOperator::Not, value) => {
+                Ok(Rolled::Number(boolean(value.operand()?.sum()? == 0)))
+            }
+        }
+    }
+
+    /// Apply an operator with a left and right operand, rolling any new dice
+    pub(in crate::interpret) fn binary<R: rand::Rng>(
+        operator: ast::BinaryOperator,
+        left: Rolled,
+        right: Rolled,
+        position_order: &config::PositionOrder,
+        rng: &mut R,
+    ) -> Result<Rolled, InterpretError> {
+        match operator {
+            ast::BinaryOperator::Dice => Self::roll(left, right, rng),
+            ast::BinaryOperator::Access => Self::access(left, right, position_order),
+            _ => {
+                let result = apply(operator, &left.operand()?, &right.operand()?)?;
+
+                if matches!(left, Rolled::Dice { .. }) || matches!(right, Rolled::Dice { .. }) {
+                    Ok(Rolled::die(result))
+                } else {
+                    Ok(Rolled::Number(result))
+                }
+            }
+        }
+    }
+
+    /// Roll a number of dice, where the count and sides may both be any value
+    fn roll<R: rand::Rng>(
+        count: Rolled,
+        sides: Rolled,
+        rng: &mut R,
+    ) -> Result<Rolled, InterpretError> {
+        let faces = match sides {
+            Rolled::Number(value) if value < 0 => Faces::Range(value, -1),
+            Rolled::Number(value) => Faces::Range(1, value),
+            Rolled::Sequence(values) => Faces::List(values),
+            // Dice that were already rolled are a die that always shows their sum
+            dice @ Rolled::Dice { .. } => Faces::List(vec![dice.operand()?.sum()?]),
+        };
+        let mut roll = |count: i32| {
+            (0..count.unsigned_abs())
+                .map(|_| faces.roll(rng))
+                .collect::<Vec<_>>()
+        };
+
+        match count {
+            // A random count of dice is rolled as a single die of their sum
+            dice @ Rolled::Dice { .. } => {
+                let count = dice.operand()?.sum()?;
+                let total = Rolled::Dice {
+                    count,
+                    sides: roll(count),
+                }
+                .operand()?
+                .sum()?;
+
+                Ok(Rolled::die(total))
+            }
+            count => {
+                let count = count.operand()?.sum()?;
+
+                Ok(Rolled::Dice {
+                    count,
+                    sides: roll(count),
+                })
+            }
+        }
+    }
+
+    /// Get the value at a 1-based index of a collection
+    ///
+    /// Many indexes sum the value at each index, and any index out of range is 0.
+    /// Whether each die, in the order they were rolled, is at one of the positions accessed
+    pub(in crate::interpret) fn kept(
+        positions: &[i32],
+        sides: &[i32],
+        position_order: &config::PositionOrder,
+    ) -> Vec<bool> {
+        // The same order as accessing sorts the dice in, keeping tied dice in the order rolled
+        let mut order = (0..sides.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| sides[b].cmp(&sides[a]));
+        if *position_order == config::PositionOrder::LowestFirst {
+            order.reverse();
+        }
+
+        let mut kept = vec![false; sides.len()];
+        for position in positions
+            .iter()
+            .filter_map(|&position| usize::try_from(position).ok())
+        {
+            if let Some(&index) = position.checked_sub(1).and_then(|index| order.get(index)) {
+                kept[index] = true;
+            }
+        }
+
+        kept
+    }
+
+    fn access(
+        index: Rolled,
+        collection: Rolled,
+        position_order: &config::PositionOrder,
+    ) -> Result<Rolled, InterpretError> {
+        let positions = match index {
+            Rolled::Number(value) => vec![value],
+            Rolled::Sequence(values) => values,
+            Rolled::Dice { .. } => return Err(InterpretError::UnexpectedDice),
+        };
+        let lowest_first = *position_order == config::PositionOrder::LowestFirst;
+
+        match collection {
+            // Sequences are always accessed in their written order
+            Rolled::Sequence(values) => select(&positions, &values, false).map(Rolled::Number),
+            Rolled::Number(value) => {
+                select(&positions, &digits(value), lowest_first).map(Rolled::Number)
+            }
+            Rolled::Dice { mut sides, .. } => {
+                sides.sort_unstable_by(|a, b| b.cmp(a));
+
+                select(&positions, &sides, lowest_first).map(Rolled::die)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample::rng;
+
+    fn binary(operator: ast::BinaryOperator, left: Rolled, right: Rolled) -> Rolled {
+        Rolled::binary(
+            operator,
+            left,
+            right,
+            &config::PositionOrder::HighestFirst,
+            &mut rng::seeded(0),
+        )
+        .unwrap()
+    }
+
+    fn dice(sides: &[i32]) -> Rolled {
+        Rolled::Dice {
+            count: sides.len() as i32,
+            sides: sides.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_roll() {
+        let mut rng = rng::seeded(0);
+
+        for _ in 0..100 {
+            let Rolled::Dice { count, sides } =
+                Rolled::roll(Rolled::Number(3), Rolled::Number(6), &mut rng).unwrap()
+            else {
+                panic!("expected dice");
+            };
+            assert_eq!(count, 3);
+            assert_eq!(sides.len(), 3);
+            assert!(sides.iter().all(|side| (1..=6).contains(side)));
+
+            let Rolled::Dice { sides, .. } =
+                Rolled::roll(Rolled::Number(2), Rolled::Number(-4), &mut rng).unwrap()
+            else {
+                panic!("expected dice");
+            };
+            assert!(sides.iter().all(|side| (-4..=-1).contains(side)));
+        }
+
+        assert_eq!(
+            Rolled::roll(Rolled::Number(2), Rolled::Sequence(vec![5]), &mut rng),
+            Ok(dice(&[5, 5]))
+        );
+        assert_eq!(
+            Rolled::roll(Rolled::Number(2), dice(&[3, 4]), &mut rng),
+            Ok(dice(&[7, 7]))
+        );
+        assert_eq!(
+            Rolled::roll(dice(&[1, 2]), Rolled::Sequence(vec![2]), &mut rng),
+            Ok(Rolled::die(6))
+        );
+        assert_eq!(
+            Rolled::roll(Rolled::Number(1), Rolled::Number(0), &mut rng),
+            Ok(dice(&[0]))
+        );
+    }
+
+    #[test]
+    fn test_dice_operations() {
+        use ast::BinaryOperator::*;
+
+        assert_eq!(
+            binary(Add, dice(&[6, 2]), Rolled::Number(1)),
+            Rolled::die(9)
+        );
+        assert_eq!(
+            binary(GreaterOrEqual, dice(&[6, 2]), Rolled::Number(8)),
+            Rolled::die(1)
+        );
+        assert_eq!(
+            binary(Equal, Rolled::Number(2), Rolled::Sequence(vec![2, 1, 2])),
+            Rolled::Number(2)
+        );
+        assert_eq!(
+            binary(Access, Rolled::Number(1), dice(&[2, 6, 4])),
+            Rolled::die(6)
+        );
+        assert_eq!(
+            binary(Access, Rolled::Sequence(vec![2, 3]), dice(&[2, 6, 4])),
+            Rolled::die(6)
+        );
+        assert_eq!(
+            Rolled::access(
+                Rolled::Number(1),
+                dice(&[2, 6, 4]),
+                &config::PositionOrder::LowestFirst
+            ),
+            Ok(Rolled::die(2))
+        );
+        assert_eq!(
+            Rolled::access(dice(&[1]), dice(&[2]), &config::PositionOrder::HighestFirst),
+            Err(InterpretError::UnexpectedDice)
+        );
+    }
+
+    #[test]
+    fn test_unary() {
+        assert_eq!(
+            dice(&[3, 4]).unary(ast::UnaryOperator::Length),
+            Ok(Rolled::Number(2))
+        );
+        assert_eq!(
+            dice(&[3, 4]).unary(ast::UnaryOperator::Negate),
+            Ok(dice(&[-3, -4]))
+        );
+        assert_eq!(
+            dice(&[3, 4]).unary(ast::UnaryOperator::Not),
+            Ok(Rolled::die(0))
+        );
+        assert_eq!(
+            Rolled::Sequence(vec![1, 2]).unary(ast::UnaryOperator::Negate),
+            Ok(Rolled::Number(-3))
+        );
+    }
+
+    #[test]
+    fn test_kept() {
+        let highest_first = config::PositionOrder::HighestFirst;
+        let lowest_first = config::PositionOrder::LowestFirst;
+
+        assert_eq!(
+            Rolled::kept(&[1, 2, 3], &[5, 3, 2, 6], &highest_first),
+            vec![true, true, false, true]
+        );
+        assert_eq!(
+            Rolled::kept(&[1, 2, 3], &[5, 3, 2, 6], &lowest_first),
+            vec![true, true, true, false]
+        );
+        assert_eq!(
+            Rolled::kept(&[1, 2, 3], &[5, 5, 4, 5], &highest_first),
+            vec![true, true, false, true]
+        );
+        assert_eq!(
+            Rolled::kept(&[0, 1, 1, 5, -1], &[2, 4], &highest_first),
+            vec![false, true]
+        );
+    }
+
+    #[test]
+    fn test_output() {
+        let mut rng = rng::seeded(0);
+
+        assert_eq!(Rolled::Number(3).output(&mut rng), Ok(3));
+        assert_eq!(
+            Rolled::Dice {
+                count: -2,
+                sides: vec![3, 4]
+            }
+            .output(&mut rng),
+            Ok(-7)
+        );
+        assert_eq!(Rolled::Sequence(vec![]).output(&mut rng), Ok(0));
+        assert_eq!(Rolled::Sequence(vec![4, 4]).output(&mut rng), Ok(4));
+    }
+}
@@ -5,6 +5,7 @@
 
 /// A value with a weight
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Outcome {
     pub value: i32,
     pub weight: usize,
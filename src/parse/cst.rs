@@ -0,0 +1,405 @@
+//! A lossless syntax tree that keeps comments and whitespace
+//!
+//! The [AST][0] only has what a program means, so anything built on it loses the comments that
+//! explain it. A [`SyntaxTree`] keeps every token of each statement with the trivia around it, the
+//! whitespace and `\...\` comments, so writing it back out gives exactly the source it came from.
+//!
+//! Trivia is attached to tokens the way people tend to write it:
+//!
+//! - Trivia on the same line after a token trails that token, like a note after a statement
+//! - Everything else leads the next token, like a note on the lines above a statement
+//! - Anything after the last token's line is kept at the end of the tree
+//!
+//! [0]: crate::ast
+
+use std::fmt;
+
+use super::lexer;
+use crate::ast;
+
+/// Whether trivia is whitespace or a comment
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    /// A comment, including the `\` on either side
+    Comment,
+}
+
+/// Text between tokens that doesn't change what a program means
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trivia {
+    kind: TriviaKind,
+    text: String,
+    span: ast::Span,
+}
+
+/// A token with the exact text it was written as and the trivia around it
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxToken {
+    text: String,
+    span: ast::Span,
+    leading: Vec<Trivia>,
+    trailing: Vec<Trivia>,
+}
+
+/// A statement with every token it was written with
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxStatement {
+    statement: ast::Statement,
+    tokens: Vec<SyntaxToken>,
+}
+
+/// Every statement of a program with all of the trivia in its source
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxTree {
+    statements: Vec<SyntaxStatement>,
+    end: Vec<Trivia>,
+}
+
+impl Trivia {
+    pub fn kind(&self) -> TriviaKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn span(&self) -> ast::Span {
+        self.span
+    }
+
+    pub fn is_comment(&self) -> bool {
+        self.kind == TriviaKind::Comment
+    }
+}
+
+impl SyntaxToken {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn span(&self) -> ast::Span {
+        self.span
+    }
+
+    /// Trivia before the token, from the end of the previous token's line
+    pub fn leading(&self) -> &[Trivia] {
+        &self.leading
+    }
+
+    /// Trivia after the token on the same line
+    pub fn trailing(&self) -> &[Trivia] {
+        &self.trailing
+    }
+}
+
+impl SyntaxStatement {
+    pub fn statement(&self) -> &ast::Statement {
+        &self.statement
+    }
+
+    pub fn tokens(&self) -> &[SyntaxToken] {
+        &self.tokens
+    }
+
+    /// Every comment around the tokens of the statement, in the order they were written
+    pub fn comments(&self) -> impl Iterator<Item = &Trivia> {
+        self.tokens
+            .iter()
+            .flat_map(|token| token.leading.iter().chain(&token.trailing))
+            .filter(|trivia| trivia.is_comment())
+    }
+}
+
+impl SyntaxTree {
+    /// Split the source of a parsed program into the tokens of each statement
+    ///
+    /// The program must have been parsed from the source, so every token is part of a statement.
+    pub(super) fn new(source: &str, program: ast::Program) -> Self {
+        let spans = lexer::Lexer::new(source)
+            .map(|token| {
+                let (start, _, end) = token.expect("a parsed program to lex without errors");
+                ast::Span::new(start, end)
+            })
+            .collect::<Vec<_>>();
+
+        let mut tokens = vec![];
+        let mut leading = trivia(source, 0, spans.first().map_or(source.len(), |s| s.start));
+        for (index, &span) in spans.iter().enumerate() {
+            let next = spans.get(index + 1).map_or(source.len(), |next| next.start);
+            let mut trailing = trivia(source, span.end, next);
+            // Trailing trivia stops before the first line break, which leads the next token
+            let line_break = trailing
+                .iter()
+                .position(|trivia| {
+                    trivia.kind == TriviaKind::Whitespace && trivia.text.contains('\n')
+                })
+                .unwrap_or(trailing.len());
+            let next_leading = trailing.split_off(line_break);
+
+            tokens.push(SyntaxToken {
+                text: source[span.range()].to_owned(),
+                span,
+                leading,
+                trailing,
+            });
+            leading = next_leading;
+        }
+
+        let mut tokens = tokens.into_iter().peekable();
+        let statements = program
+            .statements
+            .into_iter()
+            .map(|statement| {
+                let end = statement.span().end;
+                let mut statement_tokens = vec![];
+                while let Some(token) = tokens.next_if(|token| token.span.end <= end) {
+                    statement_tokens.push(token);
+                }
+
+                SyntaxStatement {
+                    statement,
+                    tokens: statement_tokens,
+                }
+            })
+            .collect();
+
+        SyntaxTree {
+            statements,
+            end: leading,
+        }
+    }
+
+    pub fn statements(&self) -> &[SyntaxStatement] {
+        &self.statements
+    }
+
+    /// Trivia after the line of the last token, or all of it if there are no statements
+    pub fn end(&self) -> &[Trivia] {
+        &self.end
+    }
+
+    /// The program without any of the trivia
+    pub fn to_program(&self) -> ast::Program {
+        ast::functional::program(
+            self.statements
+                .iter()
+                .map(|statement| statement.statement.clone())
+                .collect(),
+        )
+    }
+
+    /// Canonical source for the program that keeps every comment
+    ///
+    /// Each statement is [formatted][0] on its own line, with the comments that were on their own
+    /// lines above it kept there. Comments within or after a statement go after it on the same line.
+    /// Blank lines between statements and comments are kept, but only one in a row.
+    ///
+    /// [0]: crate::ast::Statement
+    pub fn format(&self) -> String {
+        let mut lines = Lines::default();
+        for statement in &self.statements {
+            let Some((first, rest)) = statement.tokens.split_first() else {
+                continue;
+            };
+            lines.write_leading(&first.leading);
+
+            let mut line = statement.statement.to_string();
+            let comments = first.trailing.iter().chain(
+                rest.iter()
+                    .flat_map(|token| token.leading.iter().chain(&token.trailing)),
+            );
+            for comment in comments.filter(|trivia| trivia.is_comment()) {
+                line.push(' ');
+                line.push_str(&comment.text);
+            }
+            lines.write_line(&line);
+        }
+        lines.write_leading(&self.end);
+
+        lines.formatted
+    }
+}
+
+/// Formatted lines, keeping track of where there were blank lines
+#[derive(Default)]
+struct Lines {
+    formatted: String,
+    /// Line breaks seen since the last line was written
+    line_breaks: usize,
+}
+
+impl Lines {
+    fn write_line(&mut self, line: &str) {
+        if self.line_breaks > 1 && !self.formatted.is_empty() {
+            self.formatted.push('\n');
+        }
+        self.formatted.push_str(line);
+        self.formatted.push('\n');
+        self.line_breaks = 0;
+    }
+
+    /// Write each comment on its own line, counting the line breaks between them
+    fn write_leading(&mut self, leading: &[Trivia]) {
+        for trivia in leading {
+            match trivia.kind {
+                TriviaKind::Whitespace => self.line_breaks += trivia.text.matches('\n').count(),
+                TriviaKind::Comment => self.write_line(&trivia.text),
+            }
+        }
+    }
+}
+
+/// Split the text between two tokens into runs of whitespace and comments
+fn trivia(source: &str, start: usize, end: usize) -> Vec<Trivia> {
+    let mut trivia = vec![];
+    let mut position = start;
+    while position < end {
+        let rest = &source[position..end];
+        let (kind, length) = match rest.strip_prefix('\\') {
+            // The lexer only skips closed comments, so the closing `\` is always there
+            Some(comment) => (
+                TriviaKind::Comment,
+                comment.find('\\').map_or(rest.len(), |length| length + 2),
+            ),
+            None => (
+                TriviaKind::Whitespace,
+                rest.find('\\').unwrap_or(rest.len()),
+            ),
+        };
+
+        trivia.push(Trivia {
+            kind,
+            text: rest[..length].to_owned(),
+            span: ast::Span::new(position, position + length),
+        });
+        position += length;
+    }
+
+    trivia
+}
+
+impl fmt::Display for SyntaxTree {
+    /// The exact source the tree was parsed from
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self
+            .statements
+            .iter()
+            .flat_map(|statement| &statement.tokens)
+        {
+            for trivia in &token.leading {
+                write!(f, "{}", trivia.text)?;
+            }
+            write!(f, "{}", token.text)?;
+            for trivia in &token.trailing {
+                write!(f, "{}", trivia.text)?;
+            }
+        }
+        for trivia in &self.end {
+            write!(f, "{}", trivia.text)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{parse_program, parse_syntax_tree};
+
+    const SOURCE: &str = "\\ Shared library \\\n\
+                          \n\
+                          \\ Rolls for stats \\\n\
+                          X:3d6   \\ three dice \\\n\
+                          output   X+\\ plus \\ 1\n\
+                          \n\
+                          \n\
+                          output {1,2 : 3} \\ end \\\n\
+                          \\ trailing\n\
+                          notes \\\n";
+
+    #[test]
+    fn test_lossless() {
+        let tree = parse_syntax_tree(SOURCE).unwrap();
+        assert_eq!(tree.to_string(), SOURCE);
+        assert_eq!(tree.to_program(), parse_program(SOURCE).unwrap());
+
+        for source in [
+            "",
+            "  \n",
+            "\\ only a comment \\",
+            "output 1",
+            " output 1 \\a\\\\b\\",
+        ] {
+            assert_eq!(parse_syntax_tree(source).unwrap().to_string(), source);
+        }
+    }
+
+    #[test]
+    fn test_attachment() {
+        let tree = parse_syntax_tree(SOURCE).unwrap();
+        let statements = tree.statements();
+        assert_eq!(statements.len(), 3);
+
+        let first = &statements[0].tokens()[0];
+        assert_eq!(first.text(), "X");
+        assert_eq!(
+            first
+                .leading()
+                .iter()
+                .map(|trivia| (trivia.kind(), trivia.text()))
+                .collect::<Vec<_>>(),
+            vec![
+                (TriviaKind::Comment, "\\ Shared library \\"),
+                (TriviaKind::Whitespace, "\n\n"),
+                (TriviaKind::Comment, "\\ Rolls for stats \\"),
+                (TriviaKind::Whitespace, "\n"),
+            ]
+        );
+        let last = statements[0].tokens().last().unwrap();
+        assert_eq!(last.text(), "6");
+        assert_eq!(last.trailing()[1].text(), "\\ three dice \\");
+        assert_eq!(last.trailing()[1].span(), ast::Span::new(48, 62));
+
+        assert_eq!(
+            statements[1]
+                .comments()
+                .map(|comment| comment.text())
+                .collect::<Vec<_>>(),
+            vec!["\\ plus \\"]
+        );
+        assert_eq!(tree.end()[1].text(), "\\ trailing\nnotes \\");
+    }
+
+    #[test]
+    fn test_format() {
+        let tree = parse_syntax_tree(SOURCE).unwrap();
+        let formatted = tree.format();
+        assert_eq!(
+            formatted,
+            "\\ Shared library \\\n\
+             \n\
+             \\ Rolls for stats \\\n\
+             X: 3d6 \\ three dice \\\n\
+             output X + 1 \\ plus \\\n\
+             \n\
+             output {1, 2:3} \\ end \\\n\
+             \\ trailing\n\
+             notes \\\n"
+        );
+
+        let reformatted = parse_syntax_tree(&formatted).unwrap();
+        assert_eq!(reformatted.to_program(), tree.to_program());
+        assert_eq!(reformatted.format(), formatted);
+
+        assert_eq!(parse_syntax_tree("").unwrap().format(), "");
+        assert_eq!(
+            parse_syntax_tree("output 1 output 2\n\n\n")
+                .unwrap()
+                .format(),
+            "output 1\noutput 2\n"
+        );
+    }
+}
@@ -0,0 +1,352 @@
+//! Showing where parse errors are in the source they came from
+//!
+//! A [`Diagnostic`] turns the byte offsets of a [parse error][0] into a line and column, and
+//! renders the line with the problem underlined:
+//!
+//! ```text
+//! unexpected `}`, expected an expression
+//!  --> line 1, column 5
+//!   |
+//! 1 | {1, }
+//!   |     ^
+//! ```
+//!
+//! [0]: super::error::ParseError
+
+use std::fmt;
+
+use crate::ast;
+
+/// Tokens that can start an expression, which are listed together as "an expression"
+const EXPRESSION_STARTS: [&str; 7] = ["integer", "variable", "(", "{", "-", "!", "#"];
+
+/// Tokens that can join two expressions, which are listed together as "an operator"
+const BINARY_OPERATORS: [&str; 15] = [
+    "d", "@", "^", "*", "/", "+", "-", "=", "!=", "<", ">", "<=", ">=", "&", "|",
+];
+
+/// A parse error placed in its source
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    message: String,
+    expected: Vec<String>,
+    span: Option<ast::Span>,
+    location: Option<Location>,
+}
+
+/// The line of the source an error is on, and where on it
+#[derive(Clone, Debug, PartialEq)]
+struct Location {
+    line: usize,
+    column: usize,
+    text: String,
+    /// The characters before the error, kept to line the underline up with tabs
+    prefix: String,
+    width: usize,
+}
+
+impl Diagnostic {
+    pub(super) fn new(
+        message: String,
+        span: Option<ast::Span>,
+        expected: Vec<String>,
+        source: &str,
+    ) -> Self {
+        Self {
+            message,
+            expected,
+            span,
+            location: span.map(|span| Location::new(span, source)),
+        }
+    }
+
+    /// A short description of what went wrong
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// What could have come instead, in human terms
+    pub fn expected(&self) -> &Vec<String> {
+        &self.expected
+    }
+
+    pub fn span(&self) -> Option<ast::Span> {
+        self.span
+    }
+
+    /// The line of the error, starting from 1
+    pub fn line(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.line)
+    }
+
+    /// The column of the error in characters, starting from 1
+    pub fn column(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.column)
+    }
+}
+
+impl Location {
+    fn new(span: ast::Span, source: &str) -> Self {
+        let start = floor_char_boundary(source, span.start);
+        let end = floor_char_boundary(source, span.end.max(start));
+
+        let line_start = source[..start].rfind('\n').map_or(0, |index| index + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |index| start + index);
+        let prefix = &source[line_start..start];
+
+        Location {
+            line: source[..start].matches('\n').count() + 1,
+            column: prefix.chars().count() + 1,
+            text: source[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_owned(),
+            prefix: prefix.to_owned(),
+            // Only the first line of a span is shown, and even an empty span gets a caret
+            width: source[start..end.min(line_end)].chars().count().max(1),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(expected) = join_alternatives(&self.expected) {
+            write!(f, ", expected {}", expected)?;
+        }
+
+        let Some(location) = &self.location else {
+            return Ok(());
+        };
+        let gutter = " ".repeat(location.line.to_string().len());
+        let padding = location
+            .prefix
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{} --> line {}, column {}",
+            &gutter[1..],
+            location.line,
+            location.column
+        )?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", location.line, location.text)?;
+        write!(f, "{} | {}{}", gutter, padding, "^".repeat(location.width))
+    }
+}
+
+/// Offsets from the parser are always on boundaries, but any span can be given here
+fn floor_char_boundary(source: &str, index: usize) -> usize {
+    let mut index = index.min(source.len());
+    while !source.is_char_boundary(index) {
+        index -= 1;
+    }
+
+    index
+}
+
+/// Turn the names of expected tokens from the parser into human terms
+///
+/// Whole groups of tokens are named by what they start, like "an expression", and are listed first.
+pub(super) fn describe_expected(expected: &[String]) -> Vec<String> {
+    let names = expected
+        .iter()
+        .map(|name| {
+            // Terminals are named by their quoted, escaped form in the grammar
+            name.strip_prefix('"')
+                .and_then(|name| name.strip_suffix('"'))
+                .unwrap_or(name)
+                .replace("\\\"", "\"")
+        })
+        .collect::<Vec<_>>();
+    let contains_all = |group: &[&str]| group.iter().all(|token| names.iter().any(|n| n == token));
+
+    let mut described = vec![];
+    let mut grouped = vec![];
+    if contains_all(&EXPRESSION_STARTS) {
+        described.push("an expression".to_owned());
+        grouped.extend(EXPRESSION_STARTS);
+    }
+    if contains_all(&BINARY_OPERATORS) {
+        described.push("an operator".to_owned());
+        grouped.extend(BINARY_OPERATORS);
+    }
+
+    described.extend(
+        names
+            .iter()
+            .filter(|name| !grouped.contains(&name.as_str()))
+            .map(|name| match name.as_str() {
+                "integer" => "a number".to_owned(),
+                "variable" => "a variable name".to_owned(),
+                "config" => "a quoted string".to_owned(),
+                name => format!("`{}`", name),
+            }),
+    );
+
+    described
+}
+
+/// List alternatives like "a, b, or c", or `None` if there are none
+pub(super) fn join_alternatives(alternatives: &[String]) -> Option<String> {
+    match alternatives {
+        [] => None,
+        [only] => Some(only.clone()),
+        [first, second] => Some(format!("{} or {}", first, second)),
+        [rest @ .., last] => Some(format!("{}, or {}", rest.join(", "), last)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::error::ParseErrorKind;
+    use crate::parse::{parse_expression, parse_program, parse_statement};
+
+    #[test]
+    fn test_unexpected_token() {
+        let source = "{1, }";
+        let err = parse_expression(source).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnexpectedToken);
+        assert_eq!(err.expected(), vec!["an expression"]);
+
+        let diagnostic = err.diagnostic(source);
+        assert_eq!(diagnostic.line(), Some(1));
+        assert_eq!(diagnostic.column(), Some(5));
+        assert_eq!(diagnostic.span(), Some(ast::Span::new(4, 5)));
+        assert_eq!(
+            diagnostic.to_string(),
+            "unexpected `}`, expected an expression\n\
+             \x20--> line 1, column 5\n\
+             \x20 |\n\
+             1 | {1, }\n\
+             \x20 |     ^"
+        );
+    }
+
+    #[test]
+    fn test_unexpected_eof() {
+        let source = "X: 3d6\noutput X +";
+        let err = parse_program(source).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnexpectedEof);
+
+        assert_eq!(
+            err.diagnostic(source).to_string(),
+            "unexpected end of input, expected an expression\n\
+             \x20--> line 2, column 11\n\
+             \x20 |\n\
+             2 | output X +\n\
+             \x20 |           ^"
+        );
+    }
+
+    #[test]
+    fn test_expected_alternatives() {
+        let err = parse_expression("(1 2").unwrap_err();
+        assert_eq!(err.expected(), vec!["an operator", "`)`"]);
+        assert_eq!(
+            err.to_string(),
+            "Unexpected token (2) encountered from characters 3 to 4, expected an operator or `)`"
+        );
+
+        let err = parse_program("1").unwrap_err();
+        assert_eq!(err.expected(), vec!["`output`", "`set`", "a variable name"]);
+        assert_eq!(err.diagnostic("1").message(), "unexpected `1`");
+
+        let err = parse_statement("set \"position order\" \"x\"").unwrap_err();
+        assert_eq!(err.expected(), vec!["`to`"]);
+    }
+
+    #[test]
+    fn test_wide_spans_and_tabs() {
+        let source = "output 1\n\toutput 2 3d6";
+        // Columns count characters rather than bytes
+        let comment = "\\caf\u{e9}\\ 1 23";
+        let diagnostic = parse_expression(comment).unwrap_err().diagnostic(comment);
+        assert_eq!(diagnostic.column(), Some(10));
+        assert!(diagnostic
+            .to_string()
+            .ends_with("1 | \\caf\u{e9}\\ 1 23\n  |          ^^"));
+
+        let err = parse_program(source).unwrap_err();
+        let diagnostic = err.diagnostic(source);
+        assert_eq!(diagnostic.line(), Some(2));
+        assert_eq!(diagnostic.column(), Some(11));
+        assert!(diagnostic
+            .to_string()
+            .ends_with("2 | \toutput 2 3d6\n  | \t         ^"));
+    }
+
+    #[test]
+    fn test_lex_errors() {
+        let source = "99999999999";
+        let err = parse_expression(source).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::InvalidInteger);
+        assert_eq!(err.span(), Some(ast::Span::new(0, 11)));
+        assert_eq!(err.text(), Some("99999999999"));
+        assert_eq!(
+            err.diagnostic(source).to_string(),
+            "invalid integer `99999999999`: number too large to fit in target type\n\
+             \x20--> line 1, column 1\n\
+             \x20 |\n\
+             1 | 99999999999\n\
+             \x20 | ^^^^^^^^^^^"
+        );
+
+        // Only the first line of an unterminated comment is underlined
+        let source = "output 1 \\ note\noutput 2";
+        let err = parse_program(source).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnterminatedComment);
+        assert_eq!(
+            err.diagnostic(source).to_string(),
+            "unterminated comment, missing a closing `\\`\n\
+             \x20--> line 1, column 10\n\
+             \x20 |\n\
+             1 | output 1 \\ note\n\
+             \x20 |          ^^^^^^"
+        );
+
+        let source = "output Sword";
+        let err = parse_program(source).unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::InvalidIdentifier);
+        assert_eq!(err.text(), Some("Sword"));
+        assert_eq!(err.span(), Some(ast::Span::new(7, 12)));
+        assert_eq!(
+            err.to_string(),
+            "Invalid name (Sword) encountered from characters 7 to 12"
+        );
+
+        let err = parse_program("output 1 % 2").unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnexpectedSymbol);
+        assert_eq!(err.text(), Some("%"));
+        assert_eq!(
+            err.diagnostic("output 1 % 2").message(),
+            "unexpected symbol `%`"
+        );
+
+        let err = parse_statement("set \"position order\" to \"lowest").unwrap_err();
+        assert_eq!(err.kind(), ParseErrorKind::UnterminatedString);
+        assert_eq!(err.span(), Some(ast::Span::new(24, 31)));
+    }
+
+    #[test]
+    fn test_join_alternatives() {
+        let strings = |values: &[&str]| values.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(join_alternatives(&[]), None);
+        assert_eq!(join_alternatives(&strings(&["a"])), Some("a".to_owned()));
+        assert_eq!(
+            join_alternatives(&strings(&["a", "b"])),
+            Some("a or b".to_owned())
+        );
+        assert_eq!(
+            join_alternatives(&strings(&["a", "b", "c"])),
+            Some("a, b, or c".to_owned())
+        );
+    }
+}
@@ -5,8 +5,10 @@ use std::fmt;
 
 use lalrpop_util;
 
+use super::diagnostic;
 use super::lexer;
 use super::token;
+use crate::ast;
 
 /// The kind of error encountered while parsing.
 ///
@@ -15,6 +17,22 @@ use super::token;
 #[derive(Debug, Clone, PartialEq)]
 pub enum ParseErrorKind {
     InvalidInteger,
+    /// A string without a closing `"`
+    UnterminatedString,
+    /// A comment without a closing `\`
+    UnterminatedComment,
+    /// A name with characters other than uppercase letters and underscores
+    InvalidIdentifier,
+    /// A character that isn't part of the language
+    UnexpectedSymbol,
+    /// Text that isn't any token of the language
+    InvalidToken,
+    /// The input ended before a statement or expression was finished
+    UnexpectedEof,
+    /// A token that can't come at this point
+    UnexpectedToken,
+    /// A token after the end of what was being parsed, like a second expression
+    ExtraToken,
     Other,
 }
 
@@ -28,31 +46,53 @@ impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self.source {
             lalrpop_util::ParseError::User { error } => match error {
-                lexer::LexError::InvalidInteger(parse_error) => {
-                    write!(f, "Failed to parse integer: {}", parse_error)
+                lexer::LexError::InvalidInteger { error, span, .. } => write!(
+                    f,
+                    "Failed to parse integer from characters {} to {}: {}",
+                    span.start, span.end, error
+                ),
+                lexer::LexError::UnterminatedString { span, .. } => {
+                    write!(
+                        f,
+                        "Unterminated string starting at character {}",
+                        span.start
+                    )
                 }
-                lexer::LexError::Other => {
-                    write!(f, "Unexpected error parsing: {:?}", self.source)
+                lexer::LexError::UnterminatedComment { span, .. } => {
+                    write!(
+                        f,
+                        "Unterminated comment starting at character {}",
+                        span.start
+                    )
                 }
+                lexer::LexError::InvalidIdentifier { span, text } => write!(
+                    f,
+                    "Invalid name ({}) encountered from characters {} to {}",
+                    text, span.start, span.end
+                ),
+                lexer::LexError::UnexpectedSymbol { span, text } => write!(
+                    f,
+                    "Unexpected symbol ({}) encountered at character {}",
+                    text, span.start
+                ),
             },
             lalrpop_util::ParseError::InvalidToken { location } => {
                 write!(f, "Invalid token encountered at character {}", location)
             }
-            lalrpop_util::ParseError::UnrecognizedEof {
-                location,
-                expected: _,
-            } => {
-                write!(f, "Unexpected EOF encountered at character {}", location)
+            lalrpop_util::ParseError::UnrecognizedEof { location, .. } => {
+                write!(f, "Unexpected EOF encountered at character {}", location)?;
+                self.write_expected(f)
             }
             lalrpop_util::ParseError::UnrecognizedToken {
                 token: (start, token, end),
-                expected: _,
+                ..
             } => {
                 write!(
                     f,
                     "Unexpected token ({}) encountered from characters {} to {}",
                     token, start, end
-                )
+                )?;
+                self.write_expected(f)
             }
             lalrpop_util::ParseError::ExtraToken {
                 token: (start, token, end),
@@ -83,10 +123,85 @@ impl ParseError {
     pub fn kind(&self) -> ParseErrorKind {
         match &self.source {
             lalrpop_util::ParseError::User { error } => match error {
-                lexer::LexError::InvalidInteger(_) => ParseErrorKind::InvalidInteger,
-                lexer::LexError::Other => ParseErrorKind::Other,
+                lexer::LexError::InvalidInteger { .. } => ParseErrorKind::InvalidInteger,
+                lexer::LexError::UnterminatedString { .. } => ParseErrorKind::UnterminatedString,
+                lexer::LexError::UnterminatedComment { .. } => ParseErrorKind::UnterminatedComment,
+                lexer::LexError::InvalidIdentifier { .. } => ParseErrorKind::InvalidIdentifier,
+                lexer::LexError::UnexpectedSymbol { .. } => ParseErrorKind::UnexpectedSymbol,
             },
-            _ => ParseErrorKind::Other,
+            lalrpop_util::ParseError::InvalidToken { .. } => ParseErrorKind::InvalidToken,
+            lalrpop_util::ParseError::UnrecognizedEof { .. } => ParseErrorKind::UnexpectedEof,
+            lalrpop_util::ParseError::UnrecognizedToken { .. } => ParseErrorKind::UnexpectedToken,
+            lalrpop_util::ParseError::ExtraToken { .. } => ParseErrorKind::ExtraToken,
+        }
+    }
+
+    /// Where in the source the error is, if known
+    ///
+    /// The end of the input is an empty span after the last character.
+    pub fn span(&self) -> Option<ast::Span> {
+        match &self.source {
+            lalrpop_util::ParseError::User { error } => Some(error.span()),
+            lalrpop_util::ParseError::InvalidToken { location }
+            | lalrpop_util::ParseError::UnrecognizedEof { location, .. } => {
+                Some(ast::Span::new(*location, *location))
+            }
+            lalrpop_util::ParseError::UnrecognizedToken {
+                token: (start, _, end),
+                ..
+            }
+            | lalrpop_util::ParseError::ExtraToken {
+                token: (start, _, end),
+            } => Some(ast::Span::new(*start, *end)),
+        }
+    }
+
+    /// What could have come instead, in human terms like "an expression" or "`}`"
+    pub fn expected(&self) -> Vec<String> {
+        match &self.source {
+            lalrpop_util::ParseError::UnrecognizedEof { expected, .. }
+            | lalrpop_util::ParseError::UnrecognizedToken { expected, .. } => {
+                diagnostic::describe_expected(expected)
+            }
+            _ => vec![],
+        }
+    }
+
+    /// The text the error is about, if it was text that couldn't be split into tokens
+    pub fn text(&self) -> Option<&str> {
+        match &self.source {
+            lalrpop_util::ParseError::User { error } => Some(error.text()),
+            _ => None,
+        }
+    }
+
+    /// A short description of what went wrong, without any position
+    pub fn message(&self) -> String {
+        match &self.source {
+            lalrpop_util::ParseError::User { error } => error.to_string(),
+            lalrpop_util::ParseError::InvalidToken { .. } => "invalid token".to_owned(),
+            lalrpop_util::ParseError::UnrecognizedEof { .. } => {
+                "unexpected end of input".to_owned()
+            }
+            lalrpop_util::ParseError::UnrecognizedToken {
+                token: (_, token, _),
+                ..
+            } => format!("unexpected `{}`", token),
+            lalrpop_util::ParseError::ExtraToken {
+                token: (_, token, _),
+            } => format!("unexpected `{}` after the end", token),
+        }
+    }
+
+    /// The error with the line and column it happened at in the source that was parsed
+    pub fn diagnostic(&self, source: &str) -> diagnostic::Diagnostic {
+        diagnostic::Diagnostic::new(self.message(), self.span(), self.expected(), source)
+    }
+
+    fn write_expected(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match diagnostic::join_alternatives(&self.expected()) {
+            Some(expected) => write!(f, ", expected {}", expected),
+            None => Ok(()),
         }
     }
 }
@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::error;
 use std::fmt;
 use std::num;
@@ -5,48 +6,470 @@ use std::num;
 use logos::{Logos, SpannedIter};
 
 use super::token;
+use crate::ast;
 
 pub(super) type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
 
 pub(super) struct Lexer<'input> {
     token_stream: SpannedIter<'input, token::Token>,
+    /// Words running straight on from each other are held back, so that one running into other
+    /// letters can be reported as part of the whole invalid name
+    words: Vec<(usize, token::Token, usize)>,
+    /// Tokens and errors that are ready to be returned, in the order they were written
+    ready: VecDeque<Spanned<token::Token, usize, LexError>>,
 }
 
 impl<'input> Lexer<'input> {
     pub fn new(input: &'input str) -> Self {
         Self {
             token_stream: token::Token::lexer(input).spanned(),
+            words: vec![],
+            ready: VecDeque::new(),
         }
     }
+
+    /// Turn text that isn't a token into the most specific error
+    ///
+    /// Unterminated strings and comments take the rest of the input, as everything after the
+    /// opening quote or backslash would have been inside them.
+    fn classify(&mut self, error: TokenError, start: usize, end: usize) -> LexError {
+        let source = self.token_stream.source();
+        if let TokenError::InvalidInteger(error) = error {
+            return LexError::InvalidInteger {
+                error,
+                span: ast::Span::new(start, end),
+                text: source[start..end].to_owned(),
+            };
+        }
+
+        let first = source[start..].chars().next().unwrap_or_default();
+        let end = match first {
+            '"' | '\\' => source.len(),
+            c if is_name_character(c) => return self.invalid_name(start, end),
+            _ => end,
+        };
+        self.bump_to(end);
+
+        let span = ast::Span::new(start, end);
+        let text = source[start..end].to_owned();
+        match first {
+            '"' => LexError::UnterminatedString { span, text },
+            '\\' => LexError::UnterminatedComment { span, text },
+            _ => LexError::UnexpectedSymbol { span, text },
+        }
+    }
+
+    /// A name made of the held back words, the text from `start` to `end`, and every name
+    /// character after it
+    fn invalid_name(&mut self, start: usize, end: usize) -> LexError {
+        let source = self.token_stream.source();
+        let start = self.words.first().map_or(start, |&(start, _, _)| start);
+        self.words.clear();
+        let end = source[end..]
+            .find(|c| !is_name_character(c))
+            .map_or(source.len(), |length| end + length);
+        self.bump_to(end);
+
+        LexError::InvalidIdentifier {
+            span: ast::Span::new(start, end),
+            text: source[start..end].to_owned(),
+        }
+    }
+
+    /// Skip the rest of the input up to `end`
+    fn bump_to(&mut self, end: usize) {
+        let consumed = self.token_stream.span().end;
+        self.token_stream.bump(end.saturating_sub(consumed));
+    }
+
+    /// Make the held back words ready to be returned as they are
+    fn release_words(&mut self) {
+        self.ready.extend(self.words.drain(..).map(Ok));
+    }
+}
+
+/// Characters that look like they were meant to be part of a variable name
+fn is_name_character(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Tokens made of letters, which make an invalid name when written without a space between them
+fn is_word(token: &token::Token) -> bool {
+    is_lowercase_word(token) || matches!(token, token::Token::VariableName(_))
+}
+
+/// Keywords and types, which can't be written straight after one another
+fn is_lowercase_word(token: &token::Token) -> bool {
+    matches!(
+        token,
+        token::Token::OutputKeyword
+            | token::Token::IfKeyword
+            | token::Token::ElseKeyword
+            | token::Token::LoopKeyword
+            | token::Token::OverKeyword
+            | token::Token::FunctionKeyword
+            | token::Token::ResultKeyword
+            | token::Token::SetKeyword
+            | token::Token::ToKeyword
+            | token::Token::Number
+            | token::Token::Dice
+            | token::Token::Sequence
+    )
 }
 
 impl Iterator for Lexer<'_> {
     type Item = Spanned<token::Token, usize, LexError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.token_stream
-            .next()
-            .map(|(token, span)| Ok((span.start, token?, span.end)))
+        loop {
+            if let Some(ready) = self.ready.pop_front() {
+                return Some(ready);
+            }
+
+            let Some((token, span)) = self.token_stream.next() else {
+                self.release_words();
+                return self.ready.pop_front();
+            };
+
+            // Only words that the next token runs straight on from can be part of an invalid name
+            let last_word = self
+                .words
+                .last()
+                .map(|(_, word, end)| (is_lowercase_word(word), *end));
+            let runs_on = last_word.is_some_and(|(_, end)| end == span.start);
+            if !runs_on {
+                self.release_words();
+            }
+
+            match token {
+                Ok(token) if is_word(&token) => {
+                    let repeated = runs_on
+                        && is_lowercase_word(&token)
+                        && last_word.is_some_and(|(lowercase, _)| lowercase);
+                    if repeated {
+                        let error = self.invalid_name(span.start, span.end);
+                        self.ready.push_back(Err(error));
+                    } else {
+                        self.words.push((span.start, token, span.end));
+                    }
+                }
+                Ok(token) => {
+                    self.release_words();
+                    self.ready.push_back(Ok((span.start, token, span.end)));
+                }
+                Err(error) => {
+                    let error = self.classify(error, span.start, span.end);
+                    self.release_words();
+                    self.ready.push_back(Err(error));
+                }
+            }
+        }
+    }
+}
+
+/// Tokens from the input where errors are set aside rather than ending the input
+///
+/// An integer that is out of range stands in as 0, and an invalid name stands in as a variable,
+/// so parsing carries on as if they were valid. Anything else that isn't a token is skipped.
+pub(super) struct RecoveringLexer<'input, 'errors> {
+    lexer: Lexer<'input>,
+    errors: &'errors mut Vec<LexError>,
+}
+
+impl<'input, 'errors> RecoveringLexer<'input, 'errors> {
+    pub fn new(input: &'input str, errors: &'errors mut Vec<LexError>) -> Self {
+        Self {
+            lexer: Lexer::new(input),
+            errors,
+        }
+    }
+}
+
+impl Iterator for RecoveringLexer<'_, '_> {
+    type Item = Spanned<token::Token, usize, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let err = match self.lexer.next()? {
+                Ok(token) => return Some(Ok(token)),
+                Err(err) => err,
+            };
+
+            let span = err.span();
+            let stand_in = match &err {
+                LexError::InvalidInteger { .. } => Some(token::Token::IntegerLiteral(0)),
+                LexError::InvalidIdentifier { text, .. } => {
+                    Some(token::Token::VariableName(text.clone()))
+                }
+                _ => None,
+            };
+            self.errors.push(err);
+
+            if let Some(token) = stand_in {
+                return Some(Ok((span.start, token, span.end)));
+            }
+        }
     }
 }
 
+/// What logos reports before there is enough context to tell what went wrong
 #[derive(Default, Debug, Clone, PartialEq)]
-pub(super) enum LexError {
+pub(super) enum TokenError {
     InvalidInteger(num::ParseIntError),
     #[default]
-    Other,
+    Unrecognized,
+}
+
+impl From<num::ParseIntError> for TokenError {
+    fn from(err: num::ParseIntError) -> Self {
+        TokenError::InvalidInteger(err)
+    }
+}
+
+/// Text that isn't a valid token, with where it is and what it says
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum LexError {
+    /// Digits that don't fit in an integer
+    InvalidInteger {
+        error: num::ParseIntError,
+        span: ast::Span,
+        text: String,
+    },
+    /// A `"` without another to close it
+    UnterminatedString { span: ast::Span, text: String },
+    /// A `\` without another to close it
+    UnterminatedComment { span: ast::Span, text: String },
+    /// A name with characters other than uppercase letters and underscores
+    InvalidIdentifier { span: ast::Span, text: String },
+    /// Any other character that isn't part of the language
+    UnexpectedSymbol { span: ast::Span, text: String },
+}
+
+impl LexError {
+    pub fn span(&self) -> ast::Span {
+        match self {
+            LexError::InvalidInteger { span, .. }
+            | LexError::UnterminatedString { span, .. }
+            | LexError::UnterminatedComment { span, .. }
+            | LexError::InvalidIdentifier { span, .. }
+            | LexError::UnexpectedSymbol { span, .. } => *span,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            LexError::InvalidInteger { text, .. }
+            | LexError::UnterminatedString { text, .. }
+            | LexError::UnterminatedComment { text, .. }
+            | LexError::InvalidIdentifier { text, .. }
+            | LexError::UnexpectedSymbol { text, .. } => text,
+        }
+    }
 }
 
 impl fmt::Display for LexError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            LexError::InvalidInteger { error, text, .. } => {
+                write!(f, "invalid integer `{}`: {}", text, error)
+            }
+            LexError::UnterminatedString { .. } => {
+                write!(f, "unterminated string, missing a closing `\"`")
+            }
+            LexError::UnterminatedComment { .. } => {
+                write!(f, "unterminated comment, missing a closing `\\`")
+            }
+            LexError::InvalidIdentifier { text, .. } => write!(
+                f,
+                "invalid name `{}`, names may only have uppercase letters and underscores",
+                text
+            ),
+            LexError::UnexpectedSymbol { text, .. } => write!(f, "unexpected symbol `{}`", text),
+        }
     }
 }
 
 impl error::Error for LexError {}
 
-impl From<num::ParseIntError> for LexError {
-    fn from(err: num::ParseIntError) -> Self {
-        LexError::InvalidInteger(err)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(input: &str) -> Vec<LexError> {
+        Lexer::new(input).filter_map(Result::err).collect()
+    }
+
+    fn tokens(input: &str) -> Vec<token::Token> {
+        Lexer::new(input).map(|token| token.unwrap().1).collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            tokens("X: 3dY \\note\\ output X"),
+            vec![
+                token::Token::VariableName("X".to_owned()),
+                token::Token::Colon,
+                token::Token::IntegerLiteral(3),
+                token::Token::Dice,
+                token::Token::VariableName("Y".to_owned()),
+                token::Token::OutputKeyword,
+                token::Token::VariableName("X".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_integer() {
+        let [LexError::InvalidInteger { span, text, .. }] = &errors("1 + 99999999999")[..] else {
+            panic!("expected an invalid integer");
+        };
+
+        assert_eq!(*span, ast::Span::new(4, 15));
+        assert_eq!(text, "99999999999");
+    }
+
+    #[test]
+    fn test_unterminated() {
+        assert_eq!(
+            errors("set \"position order\" to \"lowest"),
+            vec![LexError::UnterminatedString {
+                span: ast::Span::new(24, 31),
+                text: "\"lowest".to_owned()
+            }]
+        );
+        assert_eq!(
+            errors("output 1 \\ note\noutput 2"),
+            vec![LexError::UnterminatedComment {
+                span: ast::Span::new(9, 24),
+                text: "\\ note\noutput 2".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_identifier() {
+        assert_eq!(
+            errors("output Foo_2 + x"),
+            vec![
+                LexError::InvalidIdentifier {
+                    span: ast::Span::new(7, 12),
+                    text: "Foo_2".to_owned()
+                },
+                LexError::InvalidIdentifier {
+                    span: ast::Span::new(15, 16),
+                    text: "x".to_owned()
+                },
+            ]
+        );
+        assert_eq!(
+            errors("X\u{e9}"),
+            vec![LexError::InvalidIdentifier {
+                span: ast::Span::new(0, 3),
+                text: "X\u{e9}".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_lowercase_words() {
+        assert_eq!(
+            errors("output 1 nope"),
+            vec![LexError::InvalidIdentifier {
+                span: ast::Span::new(9, 13),
+                text: "nope".to_owned()
+            }]
+        );
+        assert_eq!(
+            errors("outputs 1\nX: 3dsize"),
+            vec![
+                LexError::InvalidIdentifier {
+                    span: ast::Span::new(0, 7),
+                    text: "outputs".to_owned()
+                },
+                LexError::InvalidIdentifier {
+                    span: ast::Span::new(14, 19),
+                    text: "dsize".to_owned()
+                },
+            ]
+        );
+        assert_eq!(
+            tokens("XdY"),
+            vec![
+                token::Token::VariableName("X".to_owned()),
+                token::Token::Dice,
+                token::Token::VariableName("Y".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_after_names() {
+        let lexed = Lexer::new("X: 1\noutput X %").collect::<Vec<_>>();
+        assert_eq!(lexed.len(), 6);
+        assert_eq!(
+            lexed[4],
+            Ok((12, token::Token::VariableName("X".to_owned()), 13))
+        );
+        assert_eq!(
+            lexed[5],
+            Err(LexError::UnexpectedSymbol {
+                span: ast::Span::new(14, 15),
+                text: "%".to_owned()
+            })
+        );
+
+        assert_eq!(
+            errors("output X \\ unterminated"),
+            vec![LexError::UnterminatedComment {
+                span: ast::Span::new(9, 23),
+                text: "\\ unterminated".to_owned()
+            }]
+        );
+        assert_eq!(
+            errors("output X\"lowest"),
+            vec![LexError::UnterminatedString {
+                span: ast::Span::new(8, 15),
+                text: "\"lowest".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unexpected_symbol() {
+        assert_eq!(
+            errors("1 $ 2 ; 3"),
+            vec![
+                LexError::UnexpectedSymbol {
+                    span: ast::Span::new(2, 3),
+                    text: "$".to_owned()
+                },
+                LexError::UnexpectedSymbol {
+                    span: ast::Span::new(6, 7),
+                    text: ";".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recovering() {
+        let mut errors = vec![];
+        let recovered = RecoveringLexer::new("1 $ x 99999999999", &mut errors)
+            .map(|token| token.unwrap().1)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            recovered,
+            vec![
+                token::Token::IntegerLiteral(1),
+                token::Token::VariableName("x".to_owned()),
+                token::Token::IntegerLiteral(0),
+            ]
+        );
+        assert_eq!(
+            errors.iter().map(|err| err.text()).collect::<Vec<_>>(),
+            vec!["$", "x", "99999999999"]
+        );
     }
 }
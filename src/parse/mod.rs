@@ -10,17 +10,121 @@ use lalrpop_util::lalrpop_mod;
 
 use crate::ast;
 
+pub mod cst;
+pub mod diagnostic;
 pub mod error;
 mod lexer;
 mod token;
-lalrpop_mod!(parser, "/parse/parser.rs");
+lalrpop_mod!(
+    // The generated parser takes the recovered errors as a `Vec` to push onto
+    #[allow(clippy::ptr_arg)]
+    parser,
+    "/parse/parser.rs"
+);
 
 /// If you are looking to evaluate many statements without interaction
 pub fn parse_program(input: &str) -> Result<ast::Program, error::ParseError> {
     let lexer = lexer::Lexer::new(input);
     let parser = parser::ProgramParser::new();
+    let mut recovered = vec![];
 
-    parser.parse(lexer).map_err(|err| err.into())
+    let program = parser.parse(&mut recovered, lexer)?;
+    // Errors in statements are recovered from, but this only wants a program without any
+    match recovered.into_iter().next() {
+        Some(recovery) => Err(recovery.error.into()),
+        None => Ok(program),
+    }
+}
+
+/// If you are looking to show every syntax error in a program at once
+///
+/// Statements with errors are left out of the program, and the errors are in the order they
+/// appear in the input. If parsing can't recover at all, the program is empty.
+pub fn parse_program_recovering(input: &str) -> (ast::Program, Vec<error::ParseError>) {
+    let mut lex_errors = vec![];
+    let mut recovered = vec![];
+    let lexer = lexer::RecoveringLexer::new(input, &mut lex_errors);
+    let parser = parser::ProgramParser::new();
+
+    let result = parser.parse(&mut recovered, lexer);
+
+    // Each error is kept with where it is and whether the parser found it
+    let mut errors = lex_errors
+        .into_iter()
+        .map(|error| {
+            let location = error.span().start;
+            (
+                location,
+                false,
+                error::ParseError::from(lalrpop_util::ParseError::User { error }),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut push = |error: error::ParseError| {
+        let location = error.span().map_or(input.len(), |span| span.start);
+        errors.push((location, true, error));
+    };
+    for recovery in recovered {
+        push(recovery.error.into());
+    }
+    let program = match result {
+        Ok(program) => program,
+        Err(err) => {
+            push(err.into());
+            ast::functional::program(vec![])
+        }
+    };
+
+    // Lexing runs ahead of parsing, so errors may be out of order
+    errors.sort_by_key(|(location, _, _)| *location);
+
+    // The parser picks up again at anything that could start a statement, like a name in the
+    // middle of a broken one. Errors from that are only noise until a real statement starts.
+    let starts = statement_starts(input);
+    let mut previous = None;
+    let mut kept = vec![];
+    for (location, from_parser, error) in errors {
+        let follows_error = previous.is_some_and(|previous| {
+            !starts
+                .iter()
+                .any(|&start| previous <= start && start < location)
+        });
+        previous = Some(location);
+
+        if !(from_parser && follows_error) {
+            kept.push(error);
+        }
+    }
+
+    (program, kept)
+}
+
+/// Where each statement that is written the way one starts begins, which is at `output`, `set`,
+/// or a name followed by `:`
+fn statement_starts(input: &str) -> Vec<usize> {
+    let tokens = lexer::RecoveringLexer::new(input, &mut vec![])
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(index, (_, token, _))| match token {
+            token::Token::OutputKeyword | token::Token::SetKeyword => true,
+            token::Token::VariableName(_) => {
+                matches!(tokens.get(index + 1), Some((_, token::Token::Colon, _)))
+            }
+            _ => false,
+        })
+        .map(|(_, &(start, _, _))| start)
+        .collect()
+}
+
+/// If you are looking to reformat a program without losing its comments
+pub fn parse_syntax_tree(input: &str) -> Result<cst::SyntaxTree, error::ParseError> {
+    let program = parse_program(input)?;
+
+    Ok(cst::SyntaxTree::new(input, program))
 }
 
 /// If you are looking to evaluate statements interactively
@@ -28,7 +132,7 @@ pub fn parse_statement(input: &str) -> Result<ast::Statement, error::ParseError>
     let lexer = lexer::Lexer::new(input);
     let parser = parser::StatementParser::new();
 
-    parser.parse(lexer).map_err(|err| err.into())
+    parser.parse(&mut vec![], lexer).map_err(|err| err.into())
 }
 
 /// If you are looking to evaluate an expression without side efects
@@ -36,7 +140,7 @@ pub fn parse_expression(input: &str) -> Result<ast::Expression, error::ParseErro
     let lexer = lexer::Lexer::new(input);
     let parser = parser::ExpressionParser::new();
 
-    parser.parse(lexer).map_err(|err| err.into())
+    parser.parse(&mut vec![], lexer).map_err(|err| err.into())
 }
 
 #[cfg(test)]
@@ -215,6 +319,179 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_variable_assignment() {
+        assert_eq!(
+            parse_statement("X: 3d6").unwrap(),
+            variable_assignment(
+                "X".to_owned(),
+                dice(Box::new(integer(3)), Box::new(integer(6)))
+            )
+        );
+
+        assert_eq!(
+            parse_program("X: {1, 2} output X").unwrap(),
+            program(vec![
+                variable_assignment(
+                    "X".to_owned(),
+                    sequence(vec![
+                        single_entry(integer(1), integer(1)),
+                        single_entry(integer(2), integer(1)),
+                    ])
+                ),
+                output(variable_reference("X".to_owned()), None),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_spans() {
+        let source = "X: 3d6 + {1..2} output -X";
+        let program = parse_program(source).unwrap();
+        assert_eq!(program.span, ast::Span::new(0, 25));
+
+        let ast::Statement::VariableAssignment { value, span, .. } = &program.statements[0] else {
+            panic!("expected an assignment");
+        };
+        assert_eq!(&source[span.range()], "X: 3d6 + {1..2}");
+        assert_eq!(&source[value.span().range()], "3d6 + {1..2}");
+
+        let ast::Expression::BinaryOperation { left, right, .. } = value else {
+            panic!("expected a binary operation");
+        };
+        assert_eq!(&source[left.span().range()], "3d6");
+        assert_eq!(&source[right.span().range()], "{1..2}");
+
+        let ast::Expression::Sequence { entries, .. } = right.as_ref() else {
+            panic!("expected a sequence");
+        };
+        assert_eq!(&source[entries[0].span().range()], "1..2");
+        let ast::SequenceEntry::Range { repetitions, .. } = &entries[0] else {
+            panic!("expected a range");
+        };
+        assert_eq!(repetitions.span(), ast::Span::new(14, 14));
+
+        assert_eq!(&source[program.statements[1].span().range()], "output -X");
+    }
+
+    #[test]
+    fn test_parse_spans_include_parens_and_comments() {
+        let source = "\\first\\ (1) * 2";
+        let expression = parse_expression(source).unwrap();
+        assert_eq!(&source[expression.span().range()], "(1) * 2");
+
+        let ast::Expression::BinaryOperation { left, .. } = expression else {
+            panic!("expected a binary operation");
+        };
+        assert_eq!(&source[left.span().range()], "1");
+    }
+
+    #[test]
+    fn test_parse_program_recovering() {
+        let source = "output {1, }\nX: 3d6\noutput 1 +\noutput X\nset \"a\" 3";
+        let (parsed, errors) = parse_program_recovering(source);
+
+        assert_eq!(
+            parsed,
+            program(vec![
+                variable_assignment(
+                    "X".to_owned(),
+                    dice(Box::new(integer(3)), Box::new(integer(6)))
+                ),
+                output(variable_reference("X".to_owned()), None),
+            ])
+        );
+        assert_eq!(
+            errors
+                .iter()
+                .map(|err| {
+                    let diagnostic = err.diagnostic(source);
+                    (diagnostic.line(), diagnostic.message().to_owned())
+                })
+                .collect::<Vec<_>>(),
+            vec![
+                (Some(1), "unexpected `}`".to_owned()),
+                (Some(4), "unexpected `output`".to_owned()),
+                (Some(5), "unexpected `3`".to_owned()),
+            ]
+        );
+
+        // The first error is the same one that stops parsing without recovery
+        assert_eq!(parse_program(source).unwrap_err(), errors[0]);
+    }
+
+    #[test]
+    fn test_parse_program_recovering_lex_errors() {
+        let source = "output 99999999999\noutput 2 )\noutput 3";
+        let (parsed, errors) = parse_program_recovering(source);
+
+        // The integer that is out of range stands in as 0 so its statement isn't lost
+        assert_eq!(
+            parsed,
+            program(vec![
+                output(integer(0), None),
+                output(integer(2), None),
+                output(integer(3), None),
+            ])
+        );
+        assert_eq!(
+            errors.iter().map(|err| err.kind()).collect::<Vec<_>>(),
+            vec![
+                error::ParseErrorKind::InvalidInteger,
+                error::ParseErrorKind::UnexpectedToken
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_recovering_resumes_at_statements() {
+        let source = "output 1 + + X\noutput 3\nX: 2 *\noutput X";
+        let (parsed, errors) = parse_program_recovering(source);
+
+        assert_eq!(
+            parsed,
+            program(vec![
+                output(integer(3), None),
+                output(variable_reference("X".to_owned()), None),
+            ])
+        );
+        assert_eq!(
+            errors
+                .iter()
+                .map(|err| {
+                    let diagnostic = err.diagnostic(source);
+                    (diagnostic.line(), diagnostic.message().to_owned())
+                })
+                .collect::<Vec<_>>(),
+            vec![
+                (Some(1), "unexpected `+`".to_owned()),
+                (Some(4), "unexpected `output`".to_owned()),
+            ]
+        );
+
+        // A name that stands in for an invalid one doesn't lead to more errors
+        let (_, errors) = parse_program_recovering("outputs 1\noutput 2");
+        assert_eq!(
+            errors.iter().map(|err| err.kind()).collect::<Vec<_>>(),
+            vec![error::ParseErrorKind::InvalidIdentifier]
+        );
+    }
+
+    #[test]
+    fn test_parse_program_recovering_valid() {
+        let source = "X: 1d4 output X + 1";
+
+        assert_eq!(
+            parse_program_recovering(source),
+            (parse_program(source).unwrap(), vec![])
+        );
+        assert_eq!(parse_program_recovering(""), (program(vec![]), vec![]));
+
+        let (parsed, errors) = parse_program_recovering("output 1 +");
+        assert_eq!(parsed, program(vec![]));
+        assert_eq!(errors[0].kind(), error::ParseErrorKind::UnexpectedEof);
+    }
+
     #[test]
     fn test_parse_mismatch_paren_and_sequence_fail() {
         assert!(parse_expression("({1)}").is_err());
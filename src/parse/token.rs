@@ -5,7 +5,7 @@ use logos::Logos;
 use super::lexer;
 
 #[derive(Logos, Clone, Debug, PartialEq)]
-#[logos(error = lexer::LexError)]
+#[logos(error = lexer::TokenError)]
 #[logos(skip r"(?&whitespace)")]
 #[logos(skip r"(?&comment)")]
 #[logos(subpattern number = r"[0-9]+")]
@@ -0,0 +1,276 @@
+use std::array;
+use std::iter;
+
+use rand::distributions::{Distribution, Uniform};
+
+use super::error::SampleError;
+use super::rng;
+use crate::outcome::Outcome;
+
+/// A distribution that takes constant time for each sample, no matter how many outcomes it has
+///
+/// This uses Vose's alias method with integer thresholds, so every value is drawn with exactly
+/// the chance given by its weight. Building it takes linear time, which makes it the better choice
+/// over [`OutputDistribution`][0] when a distribution with many outcomes is sampled many times.
+///
+/// [0]: super::distribution::OutputDistribution
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasDistribution {
+    values: Vec<i32>,
+    /// Each column keeps its own value when the point drawn within it is under its threshold
+    thresholds: Vec<u128>,
+    /// Each column gives the value at this index when the point drawn is not under its threshold
+    aliases: Vec<u32>,
+    total: u128,
+    draw: Draw,
+}
+
+/// How to pick a column and a point within it, prepared ahead of time to keep sampling cheap
+#[derive(Debug, Clone, PartialEq)]
+enum Draw {
+    /// One draw across every column laid end to end
+    Single(Uniform<u64>),
+    /// Separate draws when the columns laid end to end do not fit in a u64
+    Split(Uniform<u64>, Uniform<u128>),
+}
+
+impl AliasDistribution {
+    pub fn new(outcomes: Vec<Outcome>) -> Result<Self, SampleError> {
+        if outcomes.is_empty() {
+            return Err(SampleError::NoOutcomes);
+        }
+
+        // Indexes are stored as u32, the same limit rand puts on WeightedIndex
+        let columns = u32::try_from(outcomes.len()).map_err(|_| SampleError::TooManyOutcomes)?;
+
+        // Each weight fits in a u64 and there are at most u32::MAX of them, so this can't overflow
+        let total = outcomes
+            .iter()
+            .map(|outcome| outcome.weight as u128)
+            .sum::<u128>();
+        if total == 0 {
+            return Err(SampleError::NoWeight);
+        }
+
+        // Scale every weight by the number of columns so that a full column weighs the total
+        let mut scaled = outcomes
+            .iter()
+            .map(|outcome| outcome.weight as u128 * columns as u128)
+            .collect::<Vec<_>>();
+        let (mut small, mut large): (Vec<_>, Vec<_>) =
+            (0..columns).partition(|&i| scaled[i as usize] < total);
+
+        let mut thresholds = vec![total; outcomes.len()];
+        let mut aliases = (0..columns).collect::<Vec<_>>();
+        while let (Some(&less), Some(&more)) = (small.last(), large.last()) {
+            small.pop();
+            large.pop();
+
+            thresholds[less as usize] = scaled[less as usize];
+            aliases[less as usize] = more;
+
+            // The larger weight fills the rest of the smaller column
+            scaled[more as usize] -= total - scaled[less as usize];
+            if scaled[more as usize] < total {
+                small.push(more);
+            } else {
+                large.push(more);
+            }
+        }
+
+        // Anything left over is exactly a full column, so it always keeps its own value
+
+        let draw = match u64::try_from(columns as u128 * total) {
+            Ok(span) => Draw::Single(Uniform::new(0, span)),
+            Err(_) => Draw::Split(Uniform::new(0, columns as u64), Uniform::new(0, total)),
+        };
+
+        Ok(Self {
+            values: outcomes.iter().map(|outcome| outcome.value).collect(),
+            thresholds,
+            aliases,
+            total,
+            draw,
+        })
+    }
+
+    pub fn sample<R: rand::Rng>(&self, rng: &mut R) -> i32 {
+        let (column, point) = match &self.draw {
+            Draw::Single(span) => {
+                let draw = span.sample(rng) as u128;
+                (draw / self.total, draw % self.total)
+            }
+            Draw::Split(columns, points) => (columns.sample(rng) as u128, points.sample(rng)),
+        };
+        let column = column as usize;
+
+        if point < self.thresholds[column] {
+            self.values[column]
+        } else {
+            self.values[self.aliases[column] as usize]
+        }
+    }
+
+    pub fn sample_many<R: rand::Rng>(&self, n: usize, rng: &mut R) -> Vec<i32> {
+        iter::repeat_with(|| self.sample(rng)).take(n).collect()
+    }
+
+    pub fn sample_n<const N: usize, R: rand::Rng>(&self, rng: &mut R) -> [i32; N] {
+        array::from_fn(|_| self.sample(rng))
+    }
+
+    /// Sample `n` times using a [seeded generator][0], giving the same values for the same seed
+    ///
+    /// [0]: super::rng::seeded
+    pub fn sample_many_seeded(&self, n: usize, seed: u64) -> Vec<i32> {
+        self.sample_many(n, &mut rng::seeded(seed))
+    }
+
+    pub fn sample_iter<'a, R: rand::Rng>(
+        &'a self,
+        rng: &'a mut R,
+    ) -> impl IntoIterator<Item = i32> + use<'a, R> {
+        iter::repeat_with(move || self.sample(rng))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample::verify;
+
+    fn outcomes(weights: &[usize]) -> Vec<Outcome> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(value, &weight)| Outcome {
+                value: value as i32,
+                weight,
+            })
+            .collect()
+    }
+
+    /// How much of all columns combined gives each value, which must be its weight scaled up
+    fn masses(dist: &AliasDistribution) -> Vec<u128> {
+        let mut masses = vec![0u128; dist.values.len()];
+        for (column, (&threshold, &alias)) in dist.thresholds.iter().zip(&dist.aliases).enumerate()
+        {
+            masses[column] += threshold;
+            masses[alias as usize] += dist.total - threshold;
+        }
+
+        masses
+    }
+
+    #[test]
+    fn test_new_errors() {
+        assert_eq!(AliasDistribution::new(vec![]), Err(SampleError::NoOutcomes));
+
+        // SampleError::TooManyOutcomes is impractical to test here
+
+        assert_eq!(
+            AliasDistribution::new(outcomes(&[0, 0])),
+            Err(SampleError::NoWeight)
+        );
+
+        // SampleError::TooMuchWeight is no longer returned as any total fits in a u128
+    }
+
+    #[test]
+    fn test_new_is_exact() {
+        for weights in [
+            vec![1],
+            vec![1, 1],
+            vec![1, 2, 3, 4, 5, 6, 5, 4, 3, 2, 1],
+            vec![0, 7, 0, 1],
+            vec![1000, 1, 1, 1, 1],
+            (1..=600).collect(),
+            vec![usize::MAX, 1, usize::MAX],
+        ] {
+            let dist = AliasDistribution::new(outcomes(&weights)).unwrap();
+            let columns = weights.len() as u128;
+
+            assert_eq!(
+                masses(&dist),
+                weights
+                    .iter()
+                    .map(|&weight| weight as u128 * columns)
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let mut rng = rng::seeded(0);
+        let dist = AliasDistribution::new(vec![Outcome {
+            value: 7,
+            weight: 1,
+        }])
+        .unwrap();
+
+        assert_eq!(dist.sample(&mut rng), 7);
+        assert_eq!(dist.sample_many(5, &mut rng), vec![7; 5]);
+        assert_eq!(dist.sample_n(&mut rng), [7; 5]);
+        assert_eq!(
+            dist.sample_iter(&mut rng)
+                .into_iter()
+                .take(5)
+                .collect::<Vec<_>>(),
+            vec![7; 5]
+        );
+    }
+
+    #[test]
+    fn test_sample_fits_weights() {
+        for weights in [
+            vec![1, 2, 3, 4, 5, 6, 5, 4, 3, 2, 1],
+            vec![1, 0, 100, 0, 1],
+            vec![1000, 1, 1, 1, 1],
+            (1..=200).collect(),
+            vec![usize::MAX, usize::MAX / 3, usize::MAX / 2],
+        ] {
+            let samples = AliasDistribution::new(outcomes(&weights))
+                .unwrap()
+                .sample_many_seeded(20_000, 0);
+            let verification = verify::verify_samples(&outcomes(&weights), &samples).unwrap();
+
+            assert!(verification.passes(0.001), "{}", verification);
+        }
+    }
+
+    #[test]
+    fn test_sample_never_zero_weight() {
+        let dist = AliasDistribution::new(outcomes(&[0, 3, 0, 1, 0])).unwrap();
+
+        assert!(dist
+            .sample_many_seeded(1000, 0)
+            .iter()
+            .all(|&value| value == 1 || value == 3));
+    }
+
+    #[test]
+    fn test_sample_huge_weights() {
+        let dist = AliasDistribution::new(outcomes(&[usize::MAX, usize::MAX, 0])).unwrap();
+        assert!(matches!(dist.draw, Draw::Split(..)));
+
+        let sampled = dist.sample_many_seeded(100, 0);
+        assert!(sampled.contains(&0));
+        assert!(sampled.contains(&1));
+        assert!(!sampled.contains(&2));
+    }
+
+    #[test]
+    fn test_sample_seeded() {
+        let dist = AliasDistribution::new(outcomes(&[1, 2, 3, 4, 5, 6, 5, 4, 3, 2, 1])).unwrap();
+
+        assert_eq!(
+            dist.sample_many_seeded(10, 42),
+            vec![7, 7, 4, 5, 3, 5, 3, 8, 8, 2]
+        );
+        assert_eq!(
+            dist.sample_many_seeded(10, 42),
+            dist.sample_many(10, &mut rng::seeded(42))
+        );
+    }
+}
@@ -4,12 +4,26 @@ use std::iter;
 use rand::{distributions::WeightedIndex, prelude::Distribution};
 
 use super::error::SampleError;
+use super::rng;
 use crate::outcome::Outcome;
 
+/// A distribution that samples each value with exactly the chance given by its weight
+///
+/// Weights that add up past a `u64` are sampled with `u128` instead. Since each weight fits in a
+/// `usize` and there are at most `u32::MAX` outcomes, the total always fits and there is no loss
+/// of precision.
 #[derive(Debug, Clone, PartialEq)]
 pub struct OutputDistribution {
     values: Vec<i32>,
-    index: WeightedIndex<usize>,
+    index: Index,
+}
+
+/// Weights are widened so that draws do not depend on the platform's pointer width
+#[derive(Debug, Clone, PartialEq)]
+enum Index {
+    Narrow(WeightedIndex<u64>),
+    /// Only used when needed, as drawing a `u128` takes more time and more randomness
+    Wide(WeightedIndex<u128>),
 }
 
 impl OutputDistribution {
@@ -19,28 +33,34 @@ impl OutputDistribution {
                 .iter()
                 .fold((vec![], vec![]), |(mut values, mut weights), outcome| {
                     values.push(outcome.value);
-                    weights.push(outcome.weight);
+                    weights.push(outcome.weight as u64);
 
                     (values, weights)
                 });
 
         // Check for overflows ahead of time as rand will just let this panic
-        if weights
+        let narrow = weights
             .iter()
-            .try_fold(0usize, |acc, &curr| acc.checked_add(curr))
-            .is_none()
-        {
-            return Err(SampleError::TooMuchWeight);
-        };
+            .try_fold(0u64, |acc, &curr| acc.checked_add(curr))
+            .is_some();
 
         // All other common errors are caught by rand and transformed
-        let index = WeightedIndex::new(&weights)?;
+        let index = if narrow {
+            Index::Narrow(WeightedIndex::new(weights)?)
+        } else {
+            Index::Wide(WeightedIndex::new(
+                weights.into_iter().map(|weight| weight as u128),
+            )?)
+        };
 
         Ok(Self { values, index })
     }
 
     pub fn sample<R: rand::Rng>(&self, rng: &mut R) -> i32 {
-        self.values[self.index.sample(rng)]
+        self.values[match &self.index {
+            Index::Narrow(index) => index.sample(rng),
+            Index::Wide(index) => index.sample(rng),
+        }]
     }
 
     pub fn sample_many<R: rand::Rng>(&self, n: usize, rng: &mut R) -> Vec<i32> {
@@ -51,19 +71,37 @@ impl OutputDistribution {
         array::from_fn(|_| self.sample(rng))
     }
 
+    /// Sample `n` times using a [seeded generator][0], giving the same values for the same seed
+    ///
+    /// [0]: super::rng::seeded
+    pub fn sample_many_seeded(&self, n: usize, seed: u64) -> Vec<i32> {
+        self.sample_many(n, &mut rng::seeded(seed))
+    }
+
     pub fn sample_iter<'a, R: rand::Rng>(
         &'a self,
         rng: &'a mut R,
     ) -> impl IntoIterator<Item = i32> + use<'a, R> {
-        (&self.index).sample_iter(rng).map(|i| self.values[i])
+        iter::repeat_with(move || self.sample(rng))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use rand::Rng;
-
     use super::*;
+    use crate::sample::verify;
+
+    fn two_d_six() -> OutputDistribution {
+        OutputDistribution::new(
+            (2..=12)
+                .map(|value: i32| Outcome {
+                    value,
+                    weight: 6 - (7 - value).unsigned_abs() as usize,
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
 
     #[test]
     fn test_new_errors() {
@@ -82,27 +120,67 @@ mod tests {
             Err(SampleError::NoWeight),
         );
 
-        assert_eq!(
-            OutputDistribution::new(vec![
-                Outcome {
-                    value: 1,
-                    weight: usize::MAX,
-                },
-                Outcome {
-                    value: 2,
-                    weight: 1,
-                }
-            ]),
-            Err(SampleError::TooMuchWeight),
-        );
+        // SampleError::TooMuchWeight is no longer returned as any total fits in a u128
 
         // SampleError::InvalidWeight can't be created using this module
     }
 
+    #[test]
+    fn test_new_huge_weights() {
+        let dist = OutputDistribution::new(vec![
+            Outcome {
+                value: 1,
+                weight: usize::MAX,
+            },
+            Outcome {
+                value: 2,
+                weight: usize::MAX,
+            },
+            Outcome {
+                value: 3,
+                weight: 0,
+            },
+        ])
+        .unwrap();
+        assert!(matches!(dist.index, Index::Wide(_)));
+
+        let sampled = dist.sample_many_seeded(100, 0);
+        assert!(sampled.contains(&1));
+        assert!(sampled.contains(&2));
+        assert!(!sampled.contains(&3));
+
+        assert!(matches!(two_d_six().index, Index::Narrow(_)));
+    }
+
+    #[test]
+    fn test_sample_fits_weights() {
+        for weights in [
+            vec![1, 2, 3, 4, 5, 6, 5, 4, 3, 2, 1],
+            vec![1, 0, 100, 0, 1],
+            (1..=200).collect(),
+            vec![usize::MAX, usize::MAX / 3, usize::MAX / 2],
+        ] {
+            let outcomes = weights
+                .into_iter()
+                .enumerate()
+                .map(|(value, weight)| Outcome {
+                    value: value as i32,
+                    weight,
+                })
+                .collect::<Vec<_>>();
+            let samples = OutputDistribution::new(outcomes.clone())
+                .unwrap()
+                .sample_many_seeded(20_000, 0);
+            let verification = verify::verify_samples(&outcomes, &samples).unwrap();
+
+            assert!(verification.passes(0.001), "{}", verification);
+        }
+    }
+
     #[test]
     fn test_sample() {
-        let mut rng = rand::thread_rng();
-        let value = rng.gen();
+        let mut rng = rng::seeded(0);
+        let value = 7;
         let dist = OutputDistribution::new(vec![Outcome { value, weight: 1 }]).unwrap();
 
         assert_eq!(dist.sample(&mut rng), value);
@@ -110,8 +188,8 @@ mod tests {
 
     #[test]
     fn test_sample_many() {
-        let mut rng = rand::thread_rng();
-        let value = rng.gen();
+        let mut rng = rng::seeded(0);
+        let value = 7;
         let dist = OutputDistribution::new(vec![Outcome { value, weight: 1 }]).unwrap();
 
         assert_eq!(dist.sample_many(5, &mut rng,), vec![value; 5],);
@@ -119,8 +197,8 @@ mod tests {
 
     #[test]
     fn test_sample_n() {
-        let mut rng = rand::thread_rng();
-        let value = rng.gen();
+        let mut rng = rng::seeded(0);
+        let value = 7;
         let dist = OutputDistribution::new(vec![Outcome { value, weight: 1 }]).unwrap();
 
         assert_eq!(dist.sample_n(&mut rng), [value; 5],);
@@ -128,8 +206,8 @@ mod tests {
 
     #[test]
     fn test_sample_iter() {
-        let mut rng = rand::thread_rng();
-        let value = rng.gen();
+        let mut rng = rng::seeded(0);
+        let value = 7;
         let dist = OutputDistribution::new(vec![Outcome { value, weight: 1 }]).unwrap();
 
         assert_eq!(
@@ -140,4 +218,29 @@ mod tests {
             vec![value; 5],
         );
     }
+
+    #[test]
+    fn test_sample_seeded() {
+        let dist = two_d_six();
+
+        assert_eq!(
+            dist.sample_many_seeded(10, 42),
+            vec![8, 11, 7, 8, 6, 4, 6, 9, 9, 5]
+        );
+        assert_eq!(
+            dist.sample_many_seeded(10, 7),
+            vec![4, 5, 8, 9, 8, 6, 3, 10, 6, 12]
+        );
+        assert_eq!(
+            dist.sample_many_seeded(10, 42),
+            dist.sample_many(10, &mut rng::seeded(42))
+        );
+        assert_eq!(
+            dist.sample_iter(&mut rng::seeded(42))
+                .into_iter()
+                .take(10)
+                .collect::<Vec<_>>(),
+            dist.sample_many_seeded(10, 42)
+        );
+    }
 }
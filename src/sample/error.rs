@@ -12,6 +12,7 @@ pub enum SampleError {
     /// The output contains only outcomes with weight of zero
     NoWeight,
     /// The output contains weights that overflow
+    /// This is no longer returned as weights are widened to fit any output
     TooMuchWeight,
     /// The output contains an outcome with an invalid weight
     /// This should not be possible using public methods
@@ -0,0 +1,277 @@
+//! Counting sampled values as they are drawn
+//!
+//! A [`Histogram`] keeps only a count for each distinct value, so memory grows with the number of
+//! values that can be rolled rather than the number of samples. It can be turned back into
+//! [outcomes][0], where each weight is a count, to use anything that works on exact outputs.
+//!
+//! [0]: crate::outcome::Outcome
+
+use std::collections::{btree_map, BTreeMap};
+
+#[cfg(feature = "interpret")]
+use crate::interpret::output::Output;
+use crate::outcome::Outcome;
+
+/// How many times each value was sampled
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Histogram {
+    counts: BTreeMap<i32, usize>,
+    total: usize,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a single sample
+    pub fn add(&mut self, value: i32) {
+        self.add_count(value, 1);
+    }
+
+    /// Count the same value sampled many times
+    pub fn add_count(&mut self, value: i32, count: usize) {
+        if count == 0 {
+            return;
+        }
+
+        *self.counts.entry(value).or_default() += count;
+        self.total += count;
+    }
+
+    /// Add every count from another histogram, like one filled on another thread
+    pub fn merge(&mut self, other: &Histogram) {
+        for (&value, &count) in &other.counts {
+            self.add_count(value, count);
+        }
+    }
+
+    /// How many times a value was sampled
+    pub fn count(&self, value: i32) -> usize {
+        self.counts.get(&value).copied().unwrap_or(0)
+    }
+
+    /// How many samples were counted in total
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// How many distinct values were sampled
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Each value that was sampled with its count, in ascending order of value
+    pub fn iter(&self) -> btree_map::Iter<'_, i32, usize> {
+        self.counts.iter()
+    }
+
+    /// Each value that was sampled, weighted by its count
+    pub fn to_outcomes(&self) -> Vec<Outcome> {
+        self.counts
+            .iter()
+            .map(|(&value, &weight)| Outcome { value, weight })
+            .collect()
+    }
+
+    /// An output of the counts, to be rendered or summarized like an exact output
+    #[cfg(feature = "interpret")]
+    pub fn to_output(&self, name: Option<String>) -> Output {
+        Output::new(self.to_outcomes(), name)
+    }
+
+    /// The average of every sample, or `None` if there are none
+    pub fn mean(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+
+        // Summing as i128 keeps this exact until the final division
+        let sum = self
+            .counts
+            .iter()
+            .map(|(&value, &count)| value as i128 * count as i128)
+            .sum::<i128>();
+
+        Some(sum as f64 / self.total as f64)
+    }
+
+    /// The average squared distance of every sample from the mean, or `None` if there are none
+    ///
+    /// This treats the samples as the whole population, just as [`Output::variance`][0] treats
+    /// weights.
+    ///
+    /// [0]: crate::interpret::output::Output::variance
+    pub fn variance(&self) -> Option<f64> {
+        self.squared_deviations()
+            .map(|squares| squares / self.total as f64)
+    }
+
+    /// An unbiased estimate of the variance of what was sampled, or `None` with fewer than two
+    /// samples
+    pub fn sample_variance(&self) -> Option<f64> {
+        if self.total < 2 {
+            return None;
+        }
+
+        self.squared_deviations()
+            .map(|squares| squares / (self.total - 1) as f64)
+    }
+
+    fn squared_deviations(&self) -> Option<f64> {
+        let mean = self.mean()?;
+
+        Some(
+            self.counts
+                .iter()
+                .map(|(&value, &count)| (value as f64 - mean).powi(2) * count as f64)
+                .sum(),
+        )
+    }
+}
+
+impl Extend<i32> for Histogram {
+    fn extend<T: IntoIterator<Item = i32>>(&mut self, iter: T) {
+        for value in iter {
+            self.add(value);
+        }
+    }
+}
+
+impl FromIterator<i32> for Histogram {
+    fn from_iter<T: IntoIterator<Item = i32>>(iter: T) -> Self {
+        let mut histogram = Histogram::new();
+        histogram.extend(iter);
+
+        histogram
+    }
+}
+
+impl From<Histogram> for Vec<Outcome> {
+    fn from(histogram: Histogram) -> Self {
+        histogram.to_outcomes()
+    }
+}
+
+impl<'a> IntoIterator for &'a Histogram {
+    type Item = (&'a i32, &'a usize);
+    type IntoIter = btree_map::Iter<'a, i32, usize>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::sample::distribution::OutputDistribution;
+    use crate::sample::rng;
+
+    #[test]
+    fn test_add() {
+        let mut histogram = Histogram::new();
+        assert!(histogram.is_empty());
+
+        histogram.add(3);
+        histogram.add(1);
+        histogram.add(3);
+        histogram.add_count(5, 0);
+
+        assert_eq!(histogram.count(3), 2);
+        assert_eq!(histogram.count(1), 1);
+        assert_eq!(histogram.count(5), 0);
+        assert_eq!(histogram.total(), 3);
+        assert_eq!(histogram.len(), 2);
+        assert_eq!(
+            histogram.to_outcomes(),
+            vec![
+                Outcome {
+                    value: 1,
+                    weight: 1
+                },
+                Outcome {
+                    value: 3,
+                    weight: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mean_and_variance() {
+        let histogram = [2, 4, 4, 4, 5, 5, 7, 9].into_iter().collect::<Histogram>();
+
+        assert_eq!(histogram.mean(), Some(5.0));
+        assert_eq!(histogram.variance(), Some(4.0));
+        assert_eq!(histogram.sample_variance(), Some(32.0 / 7.0));
+
+        let single = Histogram::from_iter([i32::MAX]);
+        assert_eq!(single.mean(), Some(i32::MAX as f64));
+        assert_eq!(single.variance(), Some(0.0));
+        assert_eq!(single.sample_variance(), None);
+
+        assert_eq!(Histogram::new().mean(), None);
+        assert_eq!(Histogram::new().variance(), None);
+    }
+
+    #[test]
+    fn test_merge() {
+        let dist = OutputDistribution::new(vec![
+            Outcome {
+                value: 1,
+                weight: 1,
+            },
+            Outcome {
+                value: 2,
+                weight: 3,
+            },
+        ])
+        .unwrap();
+
+        let merged = thread::scope(|scope| {
+            let handles = (0..4)
+                .map(|seed| {
+                    let dist = &dist;
+                    scope.spawn(move || {
+                        dist.sample_iter(&mut rng::seeded(seed))
+                            .into_iter()
+                            .take(1000)
+                            .collect::<Histogram>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .fold(Histogram::new(), |mut merged, handle| {
+                    merged.merge(&handle.join().unwrap());
+                    merged
+                })
+        });
+
+        let serial = (0..4)
+            .flat_map(|seed| dist.sample_many_seeded(1000, seed))
+            .collect::<Histogram>();
+
+        assert_eq!(merged, serial);
+        assert_eq!(merged.total(), 4000);
+    }
+
+    #[cfg(feature = "interpret")]
+    #[test]
+    fn test_to_output() {
+        let histogram = Histogram::from_iter([1, 2, 2, 3]);
+        let output = histogram.to_output(Some("rolls".to_owned()));
+
+        assert_eq!(output.name(), Some("rolls"));
+        assert_eq!(output.outcomes(), &Vec::from(histogram.clone()));
+        assert_eq!(output.mean().unwrap().to_f64(), histogram.mean().unwrap());
+    }
+}
@@ -2,5 +2,10 @@
 //!
 //! [0]: crate::outcome
 
+pub mod alias;
 pub mod distribution;
 pub mod error;
+pub mod histogram;
+pub mod rng;
+pub(crate) mod stats;
+pub mod verify;
@@ -0,0 +1,260 @@
+//! Statistical functions needed to judge samples
+//!
+//! These are approximations that are accurate to far more digits than any sample can justify.
+
+/// The value below which the given fraction of a standard normal distribution lies
+///
+/// Uses Acklam's rational approximation, with a relative error below 1.2e-9.
+#[cfg_attr(not(feature = "interpret"), allow(dead_code))]
+pub(crate) fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.38357751867269e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    const LOW: f64 = 0.02425;
+
+    if p <= 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f64::INFINITY;
+    }
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// How many standard deviations either side of the mean cover the given fraction of a normal
+/// distribution, like 1.96 for 0.95
+#[cfg_attr(not(feature = "interpret"), allow(dead_code))]
+pub(crate) fn two_sided_z(confidence: f64) -> f64 {
+    normal_quantile(0.5 + confidence / 2.0)
+}
+
+/// The natural log of the gamma function, for positive `x`
+///
+/// Uses the Lanczos approximation, with a relative error below 1e-14.
+fn ln_gamma(x: f64) -> f64 {
+    const G: f64 = 7.0;
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.5203681218851,
+        -1259.1392167224028,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507343278686905,
+        -0.13857109526572012,
+        9.984_369_578_019_572e-6,
+        1.5056327351493116e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection keeps the approximation accurate for small values
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + G + 0.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |acc, (i, coefficient)| {
+            acc + coefficient / (x + i as f64 + 1.0)
+        });
+
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// The regularized upper incomplete gamma function Q(a, x)
+///
+/// Uses a series when `x` is small and a continued fraction otherwise, as both converge quickly
+/// in those ranges.
+fn upper_incomplete_gamma(a: f64, x: f64) -> f64 {
+    const ITERATIONS: usize = 1000;
+    const EPSILON: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+
+    let scale = (-x + a * x.ln() - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+
+        (1.0 - sum * scale).clamp(0.0, 1.0)
+    } else {
+        // Lentz's method for the continued fraction
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut fraction = d;
+        for n in 1..ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            fraction *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+
+        (fraction * scale).clamp(0.0, 1.0)
+    }
+}
+
+/// The chance of a chi-square statistic at least this large when the hypothesis is true
+pub(crate) fn chi_square_p_value(statistic: f64, degrees_of_freedom: usize) -> f64 {
+    if degrees_of_freedom == 0 {
+        return 1.0;
+    }
+
+    upper_incomplete_gamma(degrees_of_freedom as f64 / 2.0, statistic / 2.0)
+}
+
+/// The chance of a Kolmogorov–Smirnov statistic at least this large when the hypothesis is true
+///
+/// Uses the asymptotic Kolmogorov distribution with Stephens' correction for the sample size.
+pub(crate) fn kolmogorov_smirnov_p_value(statistic: f64, samples: usize) -> f64 {
+    if samples == 0 || statistic <= 0.0 {
+        return 1.0;
+    }
+
+    let root = (samples as f64).sqrt();
+    let lambda = (root + 0.12 + 0.11 / root) * statistic;
+
+    // The series alternates and converges very quickly, except close to 0 where it is 1 anyway
+    if lambda < 0.2 {
+        return 1.0;
+    }
+    let p = (1..=100)
+        .map(|k| {
+            let k = k as f64;
+            let sign = if k as u32 % 2 == 1 { 1.0 } else { -1.0 };
+
+            2.0 * sign * (-2.0 * k * k * lambda * lambda).exp()
+        })
+        .sum::<f64>();
+
+    p.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_normal_quantile() {
+        assert_close(normal_quantile(0.5), 0.0);
+        assert_close(normal_quantile(0.975), 1.959964);
+        assert_close(normal_quantile(0.025), -1.959964);
+        assert_close(normal_quantile(0.999), 3.090232);
+        assert_close(normal_quantile(0.01), -2.326348);
+        assert_eq!(normal_quantile(0.0), f64::NEG_INFINITY);
+        assert_eq!(normal_quantile(1.0), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert_close(ln_gamma(1.0), 0.0);
+        assert_close(ln_gamma(5.0), 24f64.ln());
+        assert_close(ln_gamma(0.5), std::f64::consts::PI.sqrt().ln());
+        assert_close(ln_gamma(0.1), 2.252712651734206);
+    }
+
+    #[test]
+    fn test_chi_square_p_value() {
+        assert_close(chi_square_p_value(3.841459, 1), 0.05);
+        assert_close(chi_square_p_value(18.307038, 10), 0.05);
+        assert_close(chi_square_p_value(2.0, 2), (-1.0f64).exp());
+        assert_close(chi_square_p_value(100.0, 50), 0.000035);
+        assert_close(chi_square_p_value(0.0, 5), 1.0);
+        assert_close(chi_square_p_value(5.0, 0), 1.0);
+    }
+
+    #[test]
+    fn test_kolmogorov_smirnov_p_value() {
+        // Critical values of the asymptotic distribution
+        assert!(
+            (kolmogorov_smirnov_p_value(1.358099 / 10_000f64.sqrt(), 10_000) - 0.05).abs() < 1e-3
+        );
+        assert!(
+            (kolmogorov_smirnov_p_value(1.627624 / 10_000f64.sqrt(), 10_000) - 0.01).abs() < 1e-3
+        );
+        assert_eq!(kolmogorov_smirnov_p_value(0.0, 100), 1.0);
+        assert_eq!(kolmogorov_smirnov_p_value(1.0, 0), 1.0);
+        assert!(kolmogorov_smirnov_p_value(0.5, 1000) < 1e-10);
+    }
+
+    #[test]
+    fn test_two_sided_z() {
+        assert_close(two_sided_z(0.95), 1.959964);
+        assert_close(two_sided_z(0.99), 2.575829);
+    }
+}
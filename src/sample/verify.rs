@@ -0,0 +1,343 @@
+//! Checking that samples follow the weights of their outcomes
+//!
+//! A sampler that is only checked by hand can be subtly biased, like favouring one end of a
+//! distribution, without any single value looking wrong. [`verify`] rolls many samples and runs
+//! two goodness-of-fit tests against the exact weights, so that a bias shows up as a small
+//! p-value.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::distribution::OutputDistribution;
+use super::error::SampleError;
+use crate::outcome::Outcome;
+use crate::sample::stats;
+
+/// Values expected fewer times than this are pooled with their neighbours for the chi-square test,
+/// as the test is unreliable for rare values
+const MINIMUM_EXPECTED: f64 = 5.0;
+
+/// The results of testing samples against exact weights
+///
+/// Each p-value is the chance of samples at least this far off if they really did follow the
+/// weights, so a small p-value means the samples are unlikely to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Verification {
+    samples: usize,
+    chi_square: f64,
+    degrees_of_freedom: usize,
+    chi_square_p_value: f64,
+    kolmogorov_smirnov: f64,
+    kolmogorov_smirnov_p_value: f64,
+}
+
+impl Verification {
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Pearson's chi-square statistic, after pooling rare values
+    pub fn chi_square(&self) -> f64 {
+        self.chi_square
+    }
+
+    pub fn degrees_of_freedom(&self) -> usize {
+        self.degrees_of_freedom
+    }
+
+    pub fn chi_square_p_value(&self) -> f64 {
+        self.chi_square_p_value
+    }
+
+    /// The largest difference between the fraction of samples and the exact chance of being at
+    /// most each value
+    pub fn kolmogorov_smirnov(&self) -> f64 {
+        self.kolmogorov_smirnov
+    }
+
+    /// The p-value from the continuous Kolmogorov distribution
+    ///
+    /// This is conservative for dice, which only have a few values, so it errs towards passing.
+    pub fn kolmogorov_smirnov_p_value(&self) -> f64 {
+        self.kolmogorov_smirnov_p_value
+    }
+
+    /// Whether both p-values are at least the given significance level, like 0.01
+    pub fn passes(&self, significance: f64) -> bool {
+        self.chi_square_p_value >= significance && self.kolmogorov_smirnov_p_value >= significance
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "chi-square {:.4} with {} degrees of freedom, p-value {:.4}",
+            self.chi_square, self.degrees_of_freedom, self.chi_square_p_value
+        )?;
+        write!(
+            f,
+            "Kolmogorov-Smirnov {:.4}, p-value {:.4}",
+            self.kolmogorov_smirnov, self.kolmogorov_smirnov_p_value
+        )
+    }
+}
+
+/// Draw `n` samples of the outcomes and test them against their exact weights
+pub fn verify<R: rand::Rng>(
+    outcomes: &[Outcome],
+    n: usize,
+    rng: &mut R,
+) -> Result<Verification, SampleError> {
+    let samples = OutputDistribution::new(outcomes.to_vec())?.sample_many(n, rng);
+
+    verify_samples(outcomes, &samples)
+}
+
+/// Test samples drawn by any means against the exact weights of the outcomes
+///
+/// A sample of a value without any weight can never happen, so it fails both tests outright.
+pub fn verify_samples(outcomes: &[Outcome], samples: &[i32]) -> Result<Verification, SampleError> {
+    if outcomes.is_empty() {
+        return Err(SampleError::NoOutcomes);
+    }
+
+    // Outcomes may repeat values, and values without weight can't be sampled
+    let mut weights = BTreeMap::<i32, f64>::new();
+    for outcome in outcomes.iter().filter(|outcome| outcome.weight > 0) {
+        *weights.entry(outcome.value).or_default() += outcome.weight as f64;
+    }
+    let total = weights.values().sum::<f64>();
+    if total == 0.0 {
+        return Err(SampleError::NoWeight);
+    }
+
+    let mut counts = BTreeMap::<i32, usize>::new();
+    for &sample in samples {
+        *counts.entry(sample).or_default() += 1;
+    }
+
+    let n = samples.len();
+    let impossible = counts.keys().any(|value| !weights.contains_key(value));
+    let (chi_square, degrees_of_freedom) = chi_square(&weights, &counts, total, n);
+    let (chi_square, chi_square_p_value) = if impossible {
+        (f64::INFINITY, 0.0)
+    } else {
+        (
+            chi_square,
+            stats::chi_square_p_value(chi_square, degrees_of_freedom),
+        )
+    };
+    let kolmogorov_smirnov = kolmogorov_smirnov(&weights, &counts, total, n);
+    let kolmogorov_smirnov_p_value = if impossible {
+        0.0
+    } else {
+        stats::kolmogorov_smirnov_p_value(kolmogorov_smirnov, n)
+    };
+
+    Ok(Verification {
+        samples: n,
+        chi_square,
+        degrees_of_freedom,
+        chi_square_p_value,
+        kolmogorov_smirnov,
+        kolmogorov_smirnov_p_value,
+    })
+}
+
+/// The chi-square statistic and its degrees of freedom, pooling neighbouring values until each
+/// group is expected often enough
+fn chi_square(
+    weights: &BTreeMap<i32, f64>,
+    counts: &BTreeMap<i32, usize>,
+    total: f64,
+    n: usize,
+) -> (f64, usize) {
+    let mut groups = vec![];
+    let (mut expected, mut observed) = (0.0, 0.0);
+    for (value, weight) in weights {
+        expected += weight / total * n as f64;
+        observed += counts.get(value).copied().unwrap_or(0) as f64;
+        if expected >= MINIMUM_EXPECTED {
+            groups.push((expected, observed));
+            (expected, observed) = (0.0, 0.0);
+        }
+    }
+    // Whatever is left over is too rare on its own, so it joins the last group
+    match groups.last_mut() {
+        Some(last) => {
+            last.0 += expected;
+            last.1 += observed;
+        }
+        None if n > 0 => groups.push((expected, observed)),
+        None => {}
+    }
+
+    let statistic = groups
+        .iter()
+        .map(|(expected, observed)| (observed - expected).powi(2) / expected)
+        .sum();
+
+    (statistic, groups.len().saturating_sub(1))
+}
+
+/// The largest difference between the cumulative fraction of samples and the exact cumulative
+/// chance, checked at every value either could change at
+fn kolmogorov_smirnov(
+    weights: &BTreeMap<i32, f64>,
+    counts: &BTreeMap<i32, usize>,
+    total: f64,
+    n: usize,
+) -> f64 {
+    if n == 0 {
+        return 0.0;
+    }
+
+    let mut values = weights.keys().chain(counts.keys()).collect::<Vec<_>>();
+    values.sort_unstable();
+    values.dedup();
+
+    let (mut exact, mut observed, mut largest) = (0.0, 0.0, 0.0f64);
+    for value in values {
+        exact += weights.get(value).copied().unwrap_or(0.0) / total;
+        observed += counts.get(value).copied().unwrap_or(0) as f64 / n as f64;
+        largest = largest.max((observed - exact).abs());
+    }
+
+    largest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sample::rng;
+
+    fn two_d_six() -> Vec<Outcome> {
+        (2..=12)
+            .map(|value: i32| Outcome {
+                value,
+                weight: 6 - (value - 7).unsigned_abs() as usize,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify() {
+        let verification = verify(&two_d_six(), 10_000, &mut rng::seeded(0)).unwrap();
+
+        assert_eq!(verification.samples(), 10_000);
+        assert_eq!(verification.degrees_of_freedom(), 10);
+        assert!(verification.passes(0.01), "{}", verification);
+    }
+
+    #[test]
+    fn test_verify_samples_biased() {
+        // Rolling 2d6 with a die that never shows a 1
+        let samples = (2..=6)
+            .flat_map(|a| (2..=6).map(move |b| a + b))
+            .cycle()
+            .take(2500)
+            .collect::<Vec<_>>();
+        let verification = verify_samples(&two_d_six(), &samples).unwrap();
+
+        assert!(verification.chi_square_p_value() < 1e-10);
+        assert!(verification.kolmogorov_smirnov_p_value() < 1e-10);
+        assert!(!verification.passes(0.01));
+    }
+
+    #[test]
+    fn test_verify_samples_exact() {
+        let outcomes = vec![
+            Outcome {
+                value: 1,
+                weight: 1,
+            },
+            Outcome {
+                value: 2,
+                weight: 3,
+            },
+        ];
+        let verification = verify_samples(&outcomes, &[1, 2, 2, 2].repeat(10)).unwrap();
+
+        assert_eq!(verification.chi_square(), 0.0);
+        assert_eq!(verification.degrees_of_freedom(), 1);
+        assert_eq!(verification.chi_square_p_value(), 1.0);
+        assert_eq!(verification.kolmogorov_smirnov(), 0.0);
+        assert_eq!(verification.kolmogorov_smirnov_p_value(), 1.0);
+    }
+
+    #[test]
+    fn test_verify_samples_pooling() {
+        // 1 and 3 are too rare to test alone, so all three values form a single group
+        let outcomes = vec![
+            Outcome {
+                value: 1,
+                weight: 1,
+            },
+            Outcome {
+                value: 2,
+                weight: 98,
+            },
+            Outcome {
+                value: 3,
+                weight: 1,
+            },
+        ];
+        let verification = verify_samples(&outcomes, &[2; 100]).unwrap();
+
+        assert_eq!(verification.degrees_of_freedom(), 0);
+        assert!(verification.passes(0.01));
+    }
+
+    #[test]
+    fn test_verify_samples_impossible() {
+        let outcomes = vec![
+            Outcome {
+                value: 1,
+                weight: 1,
+            },
+            Outcome {
+                value: 2,
+                weight: 0,
+            },
+        ];
+
+        let verification = verify_samples(&outcomes, &[1, 1, 2]).unwrap();
+        assert_eq!(verification.chi_square(), f64::INFINITY);
+        assert_eq!(verification.chi_square_p_value(), 0.0);
+        assert_eq!(verification.kolmogorov_smirnov_p_value(), 0.0);
+
+        assert!(verify_samples(&outcomes, &[1; 100]).unwrap().passes(0.01));
+    }
+
+    #[test]
+    fn test_verify_samples_errors() {
+        assert_eq!(verify_samples(&[], &[1]), Err(SampleError::NoOutcomes));
+        assert_eq!(
+            verify_samples(
+                &[Outcome {
+                    value: 1,
+                    weight: 0
+                }],
+                &[1]
+            ),
+            Err(SampleError::NoWeight)
+        );
+
+        let verification = verify_samples(&two_d_six(), &[]).unwrap();
+        assert_eq!(verification.degrees_of_freedom(), 0);
+        assert!(verification.passes(0.01));
+    }
+
+    #[test]
+    fn test_display() {
+        let verification = verify_samples(&two_d_six(), &[7; 36]).unwrap();
+
+        assert!(verification
+            .to_string()
+            .starts_with("chi-square 180.0000 with 4 degrees of freedom, p-value 0.0000\n"));
+        assert!(verification
+            .to_string()
+            .contains("Kolmogorov-Smirnov 0.4167, p-value"));
+    }
+}